@@ -0,0 +1,76 @@
+//! Parses arbitrary files, and then uses the metadata with the other state machines
+#![no_main]
+
+use arbitrary::Arbitrary;
+use libfuzzer_sys::fuzz_target;
+use pure_wav::{
+    ParseOptions, Parser, ProcessDataOutput, ReadInstruction, WavMetaData,
+    adtl::AdtlReader,
+    playback::{StreamData, StreamDataProcessDataOutput},
+    scan::Chunks,
+};
+
+#[derive(Debug, Arbitrary)]
+struct Input<'a> {
+    options: ParseOptions,
+    read_len: u32,
+    file: &'a [u8],
+}
+
+/// Reads past the end of the file fail, like they would with real storage
+fn read<'a>(file: &'a [u8], read: &ReadInstruction) -> Option<&'a [u8]> {
+    file.get(usize::try_from(read.position).ok()?..)?
+        .get(..usize::try_from(read.len).ok()?)
+}
+
+fn parse(file: &[u8], options: ParseOptions) -> Option<WavMetaData> {
+    let mut parser = Parser::new(options);
+    loop {
+        let data = read(file, &parser.read_instruction())?;
+        match parser.process_data(data).ok()? {
+            ProcessDataOutput::InProgress(next) => parser = next,
+            ProcessDataOutput::Done(meta_data) => return Some(meta_data),
+        }
+    }
+}
+
+fuzz_target!(|input: Input| {
+    let Some(meta_data) = parse(input.file, input.options) else {
+        return;
+    };
+    let _ = meta_data.frame_count();
+    let _ = meta_data.duration_micros();
+    let _ = meta_data.fmt.validate();
+
+    let mut chunks = Chunks::all(&meta_data);
+    while let Some(read_instruction) = chunks.read_instruction() {
+        let Some(data) = read(input.file, &read_instruction) else {
+            break;
+        };
+        let Ok((chunk, next)) = chunks.process_data(data) else {
+            break;
+        };
+        if &chunk.chunk_id == b"LIST" {
+            let mut adtl = AdtlReader::new(&chunk);
+            while let Some(read_instruction) = adtl.read_instruction() {
+                let Some(data) = read(input.file, &read_instruction) else {
+                    break;
+                };
+                let Ok((_, next)) = adtl.process_data(data) else {
+                    break;
+                };
+                adtl = next;
+            }
+        }
+        chunks = next;
+    }
+
+    if let Some(mut stream) = StreamData::new(&meta_data, input.read_len) {
+        while let Some(data) = read(input.file, &stream.read_instruction()) {
+            match stream.process_data(data) {
+                Ok(StreamDataProcessDataOutput::InProgress(_, next)) => stream = next,
+                Ok(StreamDataProcessDataOutput::Done(_)) | Err(_) => break,
+            }
+        }
+    }
+});
@@ -0,0 +1,25 @@
+//! Gives the parser arbitrary data for every read, including data with the wrong len
+#![no_main]
+
+use arbitrary::{Arbitrary, Unstructured};
+use libfuzzer_sys::fuzz_target;
+use pure_wav::{ParseOptions, Parser, ProcessDataOutput};
+
+fuzz_target!(|data: &[u8]| {
+    let mut u = Unstructured::new(data);
+    let Ok(options) = ParseOptions::arbitrary(&mut u) else {
+        return;
+    };
+    let mut parser = Parser::new(options);
+    while let Ok(response) = <&[u8]>::arbitrary(&mut u) {
+        let position = parser.read_instruction().position;
+        match parser.process_data(response) {
+            Ok(ProcessDataOutput::InProgress(next)) => parser = next,
+            Ok(ProcessDataOutput::Done(_)) => break,
+            Err(error) => {
+                assert_eq!(error.location.position, position);
+                break;
+            }
+        }
+    }
+});
@@ -0,0 +1,17 @@
+//! Parses arbitrary files that are in memory
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use pure_wav::slice;
+
+fuzz_target!(|file: &[u8]| {
+    let _ = slice::parse(file);
+    let _ = slice::find_chunk(file, b"LIST");
+    if let Ok(chunks) = slice::chunks(file) {
+        for chunk in chunks {
+            if chunk.is_err() {
+                break;
+            }
+        }
+    }
+});
@@ -0,0 +1,37 @@
+//! Restores arbitrary snapshots, and keeps going with arbitrary data for every read
+#![no_main]
+
+use arbitrary::{Arbitrary, Unstructured};
+use libfuzzer_sys::fuzz_target;
+use pure_wav::{
+    Parser, ProcessDataOutput,
+    playback::{StreamData, StreamDataProcessDataOutput},
+    snapshot::{ParserSnapshot, StreamDataSnapshot},
+};
+
+fuzz_target!(|data: &[u8]| {
+    let mut u = Unstructured::new(data);
+    if let Ok(snapshot) = ParserSnapshot::arbitrary(&mut u)
+        && let Some(mut parser) = Parser::restore(&snapshot)
+    {
+        let _ = parser.read_instruction();
+        while let Ok(response) = <&[u8]>::arbitrary(&mut u) {
+            match parser.process_data(response) {
+                Ok(ProcessDataOutput::InProgress(next)) => parser = next,
+                Ok(ProcessDataOutput::Done(_)) | Err(_) => break,
+            }
+            let _ = parser.read_instruction();
+        }
+    }
+    if let Ok(snapshot) = StreamDataSnapshot::arbitrary(&mut u)
+        && let Some(mut stream) = StreamData::restore(&snapshot)
+    {
+        while let Ok(response) = <&[u8]>::arbitrary(&mut u) {
+            let _ = stream.position();
+            match stream.process_data(response) {
+                Ok(StreamDataProcessDataOutput::InProgress(_, next)) => stream = next,
+                Ok(StreamDataProcessDataOutput::Done(_)) | Err(_) => break,
+            }
+        }
+    }
+});
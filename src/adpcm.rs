@@ -0,0 +1,213 @@
+//! Decoding Microsoft ADPCM (format tag 2).
+//!
+//! The `data` chunk is made of blocks of `n_block_align` bytes. Each block starts with a header for
+//! every channel, and then has 4-bit samples. Read a whole block at a time and decode it with
+//! [`MsAdpcmDecoder::decode_block`].
+//!
+//! The `fmt ` chunk has the coefficients after [`FmtData`]. They are almost always
+//! [`STANDARD_COEFFICIENTS`]. If you want to use the ones in the file, read the bytes after
+//! [`FmtData`] in the `fmt ` chunk (you can find it with [`scan`](crate::scan)) and use
+//! [`MsAdpcmExtension`].
+
+use zerocopy::{
+    FromBytes, Immutable, IntoBytes, KnownLayout,
+    little_endian::{I16, U16},
+};
+
+use crate::FmtData;
+
+pub const WAVE_FORMAT_ADPCM: u16 = 0x0002;
+
+/// The coefficient pairs that all encoders use
+pub const STANDARD_COEFFICIENTS: [(i16, i16); 7] = [
+    (256, 0),
+    (512, -256),
+    (0, 0),
+    (192, 64),
+    (240, 0),
+    (460, -208),
+    (392, -232),
+];
+
+const ADAPTATION: [i32; 16] = [
+    230, 230, 230, 230, 307, 409, 512, 614, 768, 614, 512, 409, 307, 230, 230, 230,
+];
+
+const MIN_DELTA: i32 = 16;
+/// Like ffmpeg, so that the adaptation never overflows
+const MAX_DELTA: i32 = i32::MAX / 768;
+
+/// The fixed-size part of the fields after [`FmtData`] in the `fmt ` chunk. It is followed by
+/// `num_coef` pairs of coefficients.
+#[derive(Debug, Clone, Copy, FromBytes, IntoBytes, Immutable, KnownLayout)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[repr(C)]
+pub struct MsAdpcmExtension {
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_le::u16"))]
+    pub cb_size: U16,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_le::u16"))]
+    pub samples_per_block: U16,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_le::u16"))]
+    pub num_coef: U16,
+}
+
+impl MsAdpcmExtension {
+    /// Reads the coefficients that follow the extension in `data` into `coefficients`. Returns the
+    /// number of coefficients, or [`None`] if `data` is too short or there are too many.
+    pub fn read_coefficients(&self, data: &[u8], coefficients: &mut [(i16, i16)]) -> Option<usize> {
+        let len = usize::from(self.num_coef.get());
+        let (pairs, _) = <[[I16; 2]]>::ref_from_prefix_with_elems(data, len).ok()?;
+        for (pair, coefficient) in pairs.iter().zip(coefficients.get_mut(..len)?) {
+            *coefficient = (pair[0].get(), pair[1].get());
+        }
+        Some(len)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum AdpcmError {
+    /// Only mono and stereo are supported
+    UnsupportedChannels(u16),
+    /// The block align is too small for the block headers
+    BlockAlignTooSmall(u16),
+    /// The block is shorter than the block headers
+    BlockTooShort,
+    /// A block header refers to a coefficient that doesn't exist
+    InvalidPredictor(u8),
+    /// The output can't fit all of the samples of the block
+    OutputTooSmall,
+}
+
+impl core::fmt::Display for AdpcmError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::UnsupportedChannels(channels) => {
+                write!(
+                    f,
+                    "{channels} channels is not supported, only mono and stereo are"
+                )
+            }
+            Self::BlockAlignTooSmall(block_align) => {
+                write!(
+                    f,
+                    "the block align {block_align} is too small for the block headers"
+                )
+            }
+            Self::BlockTooShort => write!(f, "the block is shorter than the block headers"),
+            Self::InvalidPredictor(predictor) => {
+                write!(f, "the predictor {predictor} doesn't exist")
+            }
+            Self::OutputTooSmall => write!(f, "the output is too small for the block"),
+        }
+    }
+}
+
+impl core::error::Error for AdpcmError {}
+
+/// The state of one channel while decoding a block
+#[derive(Debug, Clone, Copy)]
+struct ChannelState {
+    coefficient: (i16, i16),
+    delta: i32,
+    sample1: i32,
+    sample2: i32,
+}
+
+impl ChannelState {
+    fn decode(&mut self, nibble: u8) -> i16 {
+        let signed = i32::from(nibble) - if nibble >= 8 { 16 } else { 0 };
+        let predicted = (i64::from(self.sample1) * i64::from(self.coefficient.0)
+            + i64::from(self.sample2) * i64::from(self.coefficient.1))
+            >> 8;
+        let sample = (predicted + i64::from(signed) * i64::from(self.delta))
+            .clamp(i16::MIN.into(), i16::MAX.into()) as i32;
+        self.sample2 = self.sample1;
+        self.sample1 = sample;
+        self.delta =
+            ((ADAPTATION[usize::from(nibble)] * self.delta) >> 8).clamp(MIN_DELTA, MAX_DELTA);
+        sample as i16
+    }
+}
+
+/// Decodes blocks of MS ADPCM into interleaved signed 16-bit samples
+#[derive(Debug, Clone, Copy)]
+pub struct MsAdpcmDecoder<'a> {
+    channels: usize,
+    block_align: usize,
+    coefficients: &'a [(i16, i16)],
+}
+
+impl<'a> MsAdpcmDecoder<'a> {
+    /// The size of the header of each channel at the start of a block
+    const HEADER_LEN: usize = 7;
+
+    pub fn new(fmt: &FmtData, coefficients: &'a [(i16, i16)]) -> Result<Self, AdpcmError> {
+        let channels = fmt.n_channels.get();
+        if !(1..=2).contains(&channels) {
+            return Err(AdpcmError::UnsupportedChannels(channels));
+        }
+        let channels = usize::from(channels);
+        let block_align = fmt.n_block_align.get();
+        if usize::from(block_align) < Self::HEADER_LEN * channels {
+            return Err(AdpcmError::BlockAlignTooSmall(block_align));
+        }
+        Ok(Self {
+            channels,
+            block_align: block_align.into(),
+            coefficients,
+        })
+    }
+
+    /// The number of frames in a full block
+    pub fn frames_per_block(&self) -> usize {
+        (self.block_align - Self::HEADER_LEN * self.channels) * 2 / self.channels + 2
+    }
+
+    /// Decodes a block, which can be shorter than the block align if it is the last one. Returns
+    /// the number of samples written, which is the number of frames times the number of channels.
+    pub fn decode_block(&self, block: &[u8], output: &mut [i16]) -> Result<usize, AdpcmError> {
+        let header_len = Self::HEADER_LEN * self.channels;
+        let block = &block[..block.len().min(self.block_align)];
+        let (header, nibbles) = block
+            .split_at_checked(header_len)
+            .ok_or(AdpcmError::BlockTooShort)?;
+        let samples = (2 + nibbles.len() * 2 / self.channels) * self.channels;
+        let output = output
+            .get_mut(..samples)
+            .ok_or(AdpcmError::OutputTooSmall)?;
+        let mut states = [ChannelState {
+            coefficient: (0, 0),
+            delta: 0,
+            sample1: 0,
+            sample2: 0,
+        }; 2];
+        let field = |index: usize, channel: usize| {
+            let position = self.channels + (index * self.channels + channel) * 2;
+            i16::from_le_bytes([header[position], header[position + 1]])
+        };
+        for (channel, state) in states[..self.channels].iter_mut().enumerate() {
+            let predictor = header[channel];
+            *state = ChannelState {
+                coefficient: *self
+                    .coefficients
+                    .get(usize::from(predictor))
+                    .ok_or(AdpcmError::InvalidPredictor(predictor))?,
+                delta: field(0, channel).into(),
+                sample1: field(1, channel).into(),
+                sample2: field(2, channel).into(),
+            };
+            // The first 2 samples are stored in the header, oldest last
+            output[channel] = state.sample2 as i16;
+            output[self.channels + channel] = state.sample1 as i16;
+        }
+        // With 2 channels the high nibble is the left channel and the low nibble is the right
+        // channel. With 1 channel, the high nibble comes first.
+        let decoded = nibbles.iter().flat_map(|byte| [byte >> 4, byte & 0x0F]);
+        for (i, (nibble, output)) in decoded.zip(&mut output[2 * self.channels..]).enumerate() {
+            *output = states[i % self.channels].decode(nibble);
+        }
+        Ok(samples)
+    }
+}
@@ -0,0 +1,170 @@
+//! The `LIST` chunk with the `adtl` list type, which names the points in the `cue ` chunk and
+//! turns them into regions.
+//!
+//! Find the `LIST` chunk with [`scan`](crate::scan), then use [`AdtlReader`] to go through its
+//! entries. The text of each entry is not read, so that you can decide which ones to read. For
+//! example, to play a region named "INTRO", read the text of the labels, and then use the
+//! [`Region`] with the same cue point id.
+
+use pure_riff::{Id, RiffChunkHeader};
+use zerocopy::{
+    FromBytes, Immutable, IntoBytes, KnownLayout,
+    little_endian::{U16, U32},
+};
+
+use crate::{
+    Error, ReadInstruction,
+    cue::CuePoint,
+    list::{ListChunks, ListItem},
+    scan::ChunkInfo,
+};
+
+pub const ADTL_LIST_TYPE: Id = *b"adtl";
+/// The name of a cue point
+pub const LABL_ID: Id = *b"labl";
+/// A comment about a cue point
+pub const NOTE_ID: Id = *b"note";
+/// Text for a range of samples starting at a cue point
+pub const LTXT_ID: Id = *b"ltxt";
+
+const HEADER_LEN: u32 = size_of::<RiffChunkHeader>() as u32;
+
+/// The fixed-size part of a `ltxt` chunk, which is followed by the text
+#[derive(Debug, Clone, Copy, FromBytes, IntoBytes, Immutable, KnownLayout)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[repr(C)]
+pub struct LabeledTextData {
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_le::u32"))]
+    pub cue_point_id: U32,
+    /// The len of the region in frames
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_le::u32"))]
+    pub sample_len: U32,
+    /// What the region is for, for example `rgn `
+    pub purpose: Id,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_le::u16"))]
+    pub country: U16,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_le::u16"))]
+    pub language: U16,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_le::u16"))]
+    pub dialect: U16,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_le::u16"))]
+    pub code_page: U16,
+}
+
+/// Where the text of an entry is. The text is usually null-terminated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Text {
+    pub position: u64,
+    pub len: u32,
+}
+
+impl Text {
+    /// Reads the text, or only the first `max_len` bytes of it
+    pub fn read_instruction(&self, max_len: u32) -> ReadInstruction {
+        ReadInstruction {
+            position: self.position,
+            len: self.len.min(max_len),
+        }
+    }
+}
+
+/// A range of frames that starts at a cue point
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Region {
+    pub cue_point_id: u32,
+    pub sample_len: u32,
+    pub purpose: Id,
+    pub text: Text,
+}
+
+impl Region {
+    /// The first frame of the region, from the cue point with the same id
+    pub fn start_frame(&self, cue_points: &[CuePoint]) -> Option<u32> {
+        cue_points
+            .iter()
+            .find(|cue_point| cue_point.id.get() == self.cue_point_id)
+            .map(|cue_point| cue_point.sample_offset.get())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum AdtlEntry {
+    Label { cue_point_id: u32, text: Text },
+    Note { cue_point_id: u32, text: Text },
+    Region(Region),
+}
+
+/// Goes through the entries of a `LIST` `adtl` chunk. Entries that are too short and unknown
+/// entries are skipped.
+pub struct AdtlReader {
+    chunks: ListChunks,
+}
+
+impl AdtlReader {
+    pub const MAX_BUFFER_LEN: usize = HEADER_LEN as usize + size_of::<LabeledTextData>();
+
+    pub fn new(list: &ChunkInfo) -> Self {
+        Self {
+            chunks: ListChunks::new(list, size_of::<LabeledTextData>().try_into().unwrap()),
+        }
+    }
+
+    /// [`None`] if there are no more entries
+    pub fn read_instruction(&self) -> Option<ReadInstruction> {
+        self.chunks.read_instruction()
+    }
+
+    /// Returns the entry, if one was found, and the reader for the rest of the entries
+    pub fn process_data(self, data: &[u8]) -> Result<(Option<AdtlEntry>, Self), Error> {
+        let (item, chunks) = self.chunks.process_data(data)?;
+        let entry = match item {
+            ListItem::ListType(list_type) => {
+                if list_type != ADTL_LIST_TYPE {
+                    return Err(Error::UnexpectedListType(list_type));
+                }
+                None
+            }
+            ListItem::SubChunk {
+                chunk,
+                prefix: data,
+            } => {
+                let text = |fixed_len: usize| Text {
+                    position: chunk.data_position() + u64::try_from(fixed_len).unwrap(),
+                    len: chunk.len - u32::try_from(fixed_len).unwrap(),
+                };
+                match chunk.chunk_id {
+                    LABL_ID | NOTE_ID => {
+                        U32::read_from_prefix(data).ok().map(|(cue_point_id, _)| {
+                            let cue_point_id = cue_point_id.get();
+                            let text = text(size_of::<U32>());
+                            if chunk.chunk_id == LABL_ID {
+                                AdtlEntry::Label { cue_point_id, text }
+                            } else {
+                                AdtlEntry::Note { cue_point_id, text }
+                            }
+                        })
+                    }
+                    LTXT_ID => {
+                        LabeledTextData::read_from_prefix(data)
+                            .ok()
+                            .map(|(labeled_text, _)| {
+                                AdtlEntry::Region(Region {
+                                    cue_point_id: labeled_text.cue_point_id.get(),
+                                    sample_len: labeled_text.sample_len.get(),
+                                    purpose: labeled_text.purpose,
+                                    text: text(size_of::<LabeledTextData>()),
+                                })
+                            })
+                    }
+                    _ => None,
+                }
+            }
+        };
+        Ok((entry, Self { chunks }))
+    }
+}
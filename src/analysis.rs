@@ -0,0 +1,626 @@
+//! Going through all of the samples to measure things about them.
+//!
+//! # Usage
+//! Create an [`Analyzer`] with an [`Analysis`], such as [`Levels`]. Drive it like the
+//! [`Parser`](crate::Parser) with [`Analyzer::read_instruction`] and [`Analyzer::process_data`].
+//! It reads the `data` chunk from start to end, and gives you back the [`Analysis`] with the
+//! results.
+//!
+//! [`Levels`] has the peak of the samples, but the signal between the samples can be louder. Use
+//! [`TruePeak`] to find these inter-sample overs, which can clip when the file is converted or
+//! played.
+//!
+//! To normalize, analyze the [`Levels`] first, and then play with the [`Gain`] from
+//! [`Levels::normalize`].
+
+use crate::{
+    Error, ReadInstruction, WavMetaData, check_data_len,
+    convert::SampleFormat,
+    dsp::{Gain, UNITY_GAIN},
+    frames::{DynFrames, Frame},
+};
+
+/// Something that looks at every frame
+pub trait Analysis {
+    fn frame(&mut self, frame: Frame<'_>);
+
+    /// Called after the last frame
+    fn finish(&mut self) {}
+}
+
+impl<A: Analysis + ?Sized> Analysis for &mut A {
+    fn frame(&mut self, frame: Frame<'_>) {
+        A::frame(self, frame);
+    }
+
+    fn finish(&mut self) {
+        A::finish(self);
+    }
+}
+
+/// Reads the `data` chunk in order, giving every frame to an [`Analysis`]
+pub struct Analyzer<A> {
+    analysis: A,
+    format: SampleFormat,
+    block_align: u32,
+    data_position: u64,
+    data_len: u32,
+    /// The offset in the `data` chunk of the next read
+    offset: u32,
+    read_len: u32,
+}
+
+pub enum AnalyzeProcessDataOutput<A> {
+    Done(A),
+    InProgress(Analyzer<A>),
+}
+
+impl<A: Analysis> Analyzer<A> {
+    /// The `data` chunk is read `read_len` bytes at a time, rounded down to whole frames. Returns
+    /// [`None`] if the sample format is not supported.
+    pub fn new(meta_data: &WavMetaData, analysis: A, read_len: u32) -> Option<Self> {
+        let format = SampleFormat::from_meta_data(meta_data)?;
+        let block_align = u32::from(meta_data.fmt.n_block_align.get());
+        let bytes_per_sample = u32::try_from(format.bytes_per_sample()).unwrap();
+        if block_align == 0 || !block_align.is_multiple_of(bytes_per_sample) {
+            return None;
+        }
+        Some(Self {
+            analysis,
+            format,
+            block_align,
+            data_position: meta_data.data_position,
+            data_len: meta_data.data_len,
+            offset: 0,
+            read_len: (read_len - read_len % block_align).max(block_align),
+        })
+    }
+
+    pub fn read_instruction(&self) -> ReadInstruction {
+        ReadInstruction {
+            position: self.data_position.saturating_add(self.offset.into()),
+            len: (self.data_len - self.offset).min(self.read_len),
+        }
+    }
+
+    /// The number of bytes of the `data` chunk that were analyzed so far
+    pub fn offset(&self) -> u32 {
+        self.offset
+    }
+
+    /// `data` must be the len of the [`ReadInstruction`]. A partial frame at the end of the `data`
+    /// chunk is ignored.
+    pub fn process_data(mut self, data: &[u8]) -> Result<AnalyzeProcessDataOutput<A>, Error> {
+        check_data_len(self.read_instruction().len, data)?;
+        let len = u32::try_from(data.len()).unwrap();
+        let len = len - len % self.block_align;
+        let data = &data[..usize::try_from(len).unwrap()];
+        let frames = DynFrames::from_parts(data, self.format, self.block_align.try_into().unwrap());
+        for frame in frames {
+            self.analysis.frame(frame);
+        }
+        self.offset += len;
+        // A partial frame at the end is ignored
+        Ok(if self.data_len - self.offset < self.block_align {
+            self.analysis.finish();
+            AnalyzeProcessDataOutput::Done(self.analysis)
+        } else {
+            AnalyzeProcessDataOutput::InProgress(self)
+        })
+    }
+}
+
+/// The peak and RMS level of each channel. Levels are scaled like signed 32-bit samples, so full
+/// scale is `1 << 31`. Channels after the first `CHANNELS` are ignored.
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Levels<const CHANNELS: usize = 2> {
+    peaks: [u32; CHANNELS],
+    sums_of_squares: [u128; CHANNELS],
+    frames: u64,
+}
+
+impl<const CHANNELS: usize> Default for Levels<CHANNELS> {
+    fn default() -> Self {
+        Self {
+            peaks: [0; CHANNELS],
+            sums_of_squares: [0; CHANNELS],
+            frames: 0,
+        }
+    }
+}
+
+impl<const CHANNELS: usize> Levels<CHANNELS> {
+    /// The largest absolute value of a sample
+    pub fn peak(&self, channel: usize) -> Option<u32> {
+        self.peaks.get(channel).copied()
+    }
+
+    /// The root mean square of the samples
+    pub fn rms(&self, channel: usize) -> Option<u32> {
+        let mean_square = self.sums_of_squares.get(channel)? / u128::from(self.frames.max(1));
+        // The mean of squares of `u32`s fits in a `u64`
+        Some(u64::try_from(mean_square).unwrap().isqrt() as u32)
+    }
+
+    /// The number of frames analyzed
+    pub fn frames(&self) -> u64 {
+        self.frames
+    }
+}
+
+impl<const CHANNELS: usize> Analysis for Levels<CHANNELS> {
+    fn frame(&mut self, frame: Frame<'_>) {
+        for ((sample, peak), sum_of_squares) in frame
+            .samples()
+            .zip(&mut self.peaks)
+            .zip(&mut self.sums_of_squares)
+        {
+            let magnitude = sample.unsigned_abs();
+            *peak = (*peak).max(magnitude);
+            *sum_of_squares += u128::from(u64::from(magnitude).pow(2));
+        }
+        self.frames += 1;
+    }
+}
+
+/// The level to normalize to, scaled like [`Levels`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum NormalizeTarget {
+    /// The loudest channel's peak becomes this
+    Peak(u32),
+    /// The loudest channel's RMS becomes this, unless that would make a peak clip
+    Rms(u32),
+}
+
+impl NormalizeTarget {
+    /// A level from a fraction of full scale, for example `0.5`
+    pub fn level_from_f32(level: f32) -> u32 {
+        // `as` saturates
+        (level * 2147483648.0) as u32
+    }
+}
+
+impl<const CHANNELS: usize> Levels<CHANNELS> {
+    fn max_of(&self, level: impl Fn(usize) -> Option<u32>) -> u32 {
+        (0..CHANNELS).filter_map(level).max().unwrap_or(0)
+    }
+
+    /// The gain that normalizes the samples to `target`. It is unity if the samples are silent.
+    pub fn normalize_gain(&self, target: NormalizeTarget) -> i32 {
+        let peak = self.max_of(|channel| self.peak(channel));
+        let gain_for = |target: u32, level: u32| {
+            (u64::from(target) << 15)
+                .checked_div(level.into())
+                .map_or(UNITY_GAIN, |gain| gain.try_into().unwrap_or(i32::MAX))
+        };
+        match target {
+            NormalizeTarget::Peak(target) => gain_for(target, peak),
+            NormalizeTarget::Rms(target) => {
+                let rms = self.max_of(|channel| self.rms(channel));
+                gain_for(target, rms).min(gain_for(i32::MAX as u32, peak))
+            }
+        }
+    }
+
+    /// A [`Gain`] stage that normalizes the samples to `target` when playing them
+    pub fn normalize(&self, target: NormalizeTarget) -> Gain {
+        Gain::new(self.normalize_gain(target))
+    }
+}
+
+/// The 4x oversampling filter of ITU-R BS.1770, with each phase scaled by `1 << 13`. The last two
+/// phases are the first two reversed.
+const TRUE_PEAK_PHASES: [[i64; 12]; 2] = [
+    [
+        14, 90, -161, 272, -487, 1125, 7964, -838, 390, -218, 122, -68,
+    ],
+    [
+        -239, 240, -424, 730, -1364, 3810, 6388, -1641, 832, -477, 271, -155,
+    ],
+];
+
+/// The peak of the signal between the samples, estimated by oversampling 4x like ITU-R BS.1770.
+/// Levels are scaled like [`Levels`], so a level above `1 << 31` is an over. Channels after the
+/// first `CHANNELS` are ignored.
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct TruePeak<const CHANNELS: usize = 2> {
+    /// The last samples of each channel, with the newest first
+    history: [[i32; 12]; CHANNELS],
+    peaks: [u32; CHANNELS],
+    overs: [u64; CHANNELS],
+}
+
+impl<const CHANNELS: usize> Default for TruePeak<CHANNELS> {
+    fn default() -> Self {
+        Self {
+            history: [[0; 12]; CHANNELS],
+            peaks: [0; CHANNELS],
+            overs: [0; CHANNELS],
+        }
+    }
+}
+
+impl<const CHANNELS: usize> TruePeak<CHANNELS> {
+    /// The true peak, which is at least the sample peak
+    pub fn true_peak(&self, channel: usize) -> Option<u32> {
+        self.peaks.get(channel).copied()
+    }
+
+    /// The true peak of the loudest channel
+    pub fn max_true_peak(&self) -> u32 {
+        self.peaks.iter().copied().max().unwrap_or(0)
+    }
+
+    /// The number of frames where the signal goes above full scale
+    pub fn overs(&self, channel: usize) -> Option<u64> {
+        self.overs.get(channel).copied()
+    }
+}
+
+impl<const CHANNELS: usize> Analysis for TruePeak<CHANNELS> {
+    fn frame(&mut self, frame: Frame<'_>) {
+        for (((sample, history), peak), overs) in frame
+            .samples()
+            .zip(&mut self.history)
+            .zip(&mut self.peaks)
+            .zip(&mut self.overs)
+        {
+            history.copy_within(..11, 1);
+            history[0] = sample;
+            let phase = |coefficients: &mut dyn Iterator<Item = &i64>| {
+                let sum = coefficients
+                    .zip(history.iter())
+                    .map(|(&coefficient, &sample)| coefficient * i64::from(sample))
+                    .sum::<i64>();
+                u32::try_from((sum >> 13).unsigned_abs()).unwrap_or(u32::MAX)
+            };
+            let true_peak = TRUE_PEAK_PHASES
+                .iter()
+                .flat_map(|coefficients| {
+                    [
+                        phase(&mut coefficients.iter()),
+                        phase(&mut coefficients.iter().rev()),
+                    ]
+                })
+                .fold(sample.unsigned_abs(), u32::max);
+            *peak = (*peak).max(true_peak);
+            if true_peak > 1 << 31 {
+                *overs += 1;
+            }
+        }
+    }
+}
+
+/// A range of frames
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct FrameRange {
+    pub start: u64,
+    /// Not included
+    pub end: u64,
+}
+
+/// Finds silent regions, where every sample of every channel is quieter than a threshold for at
+/// least a number of frames. Up to `MAX_REGIONS` regions are kept.
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct SilenceDetector<const MAX_REGIONS: usize = 8> {
+    /// Scaled like signed 32-bit samples
+    threshold: u32,
+    min_frames: u64,
+    regions: [FrameRange; MAX_REGIONS],
+    len: usize,
+    /// The number of regions that didn't fit
+    dropped: u32,
+    /// The start of the current run of quiet frames
+    quiet_start: Option<u64>,
+    position: u64,
+}
+
+impl<const MAX_REGIONS: usize> SilenceDetector<MAX_REGIONS> {
+    /// `threshold` is scaled like signed 32-bit samples, so full scale is `1 << 31`
+    pub fn new(threshold: u32, min_frames: u64) -> Self {
+        Self {
+            threshold,
+            min_frames: min_frames.max(1),
+            regions: [FrameRange { start: 0, end: 0 }; MAX_REGIONS],
+            len: 0,
+            dropped: 0,
+            quiet_start: None,
+            position: 0,
+        }
+    }
+
+    pub fn regions(&self) -> &[FrameRange] {
+        &self.regions[..self.len]
+    }
+
+    /// The number of silent regions that were found after `MAX_REGIONS` regions were already found
+    pub fn dropped(&self) -> u32 {
+        self.dropped
+    }
+
+    fn end_quiet_run(&mut self) {
+        if let Some(start) = self.quiet_start.take()
+            && self.position - start >= self.min_frames
+        {
+            let region = FrameRange {
+                start,
+                end: self.position,
+            };
+            match self.regions.get_mut(self.len) {
+                Some(slot) => {
+                    *slot = region;
+                    self.len += 1;
+                }
+                None => self.dropped = self.dropped.saturating_add(1),
+            }
+        }
+    }
+}
+
+impl<const MAX_REGIONS: usize> Analysis for SilenceDetector<MAX_REGIONS> {
+    fn frame(&mut self, frame: Frame<'_>) {
+        if frame
+            .samples()
+            .all(|sample| sample.unsigned_abs() < self.threshold)
+        {
+            self.quiet_start.get_or_insert(self.position);
+        } else {
+            self.end_quiet_run();
+        }
+        self.position += 1;
+    }
+
+    fn finish(&mut self) {
+        self.end_quiet_run();
+    }
+}
+
+/// The mean of each channel, which is the DC offset. Channels after the first `CHANNELS` are
+/// ignored. Use the offsets with [`OffsetRemover`](crate::dsp::OffsetRemover).
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct DcOffset<const CHANNELS: usize = 2> {
+    sums: [i128; CHANNELS],
+    frames: u64,
+}
+
+impl<const CHANNELS: usize> Default for DcOffset<CHANNELS> {
+    fn default() -> Self {
+        Self {
+            sums: [0; CHANNELS],
+            frames: 0,
+        }
+    }
+}
+
+impl<const CHANNELS: usize> DcOffset<CHANNELS> {
+    /// The offset of every channel, scaled like signed 32-bit samples
+    pub fn offsets(&self) -> [i32; CHANNELS] {
+        // The mean of `i32`s fits in an `i32`
+        self.sums
+            .map(|sum| (sum / i128::from(self.frames.max(1))) as i32)
+    }
+}
+
+impl<const CHANNELS: usize> Analysis for DcOffset<CHANNELS> {
+    fn frame(&mut self, frame: Frame<'_>) {
+        for (sample, sum) in frame.samples().zip(&mut self.sums) {
+            *sum += i128::from(sample);
+        }
+        self.frames += 1;
+    }
+}
+
+/// A run of clipped samples in one channel
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct ClippedRegion {
+    pub channel: usize,
+    pub frames: FrameRange,
+}
+
+/// Finds runs of at least `min_run` consecutive full-scale samples, which usually means that the
+/// recording clipped. Channels after the first `CHANNELS` are ignored, and up to `MAX_REGIONS`
+/// regions are kept.
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct ClippingDetector<const CHANNELS: usize = 2, const MAX_REGIONS: usize = 8> {
+    min_run: u64,
+    /// The start of the current run of full-scale samples in each channel
+    run_starts: [Option<u64>; CHANNELS],
+    clipped_samples: [u64; CHANNELS],
+    regions: [ClippedRegion; MAX_REGIONS],
+    len: usize,
+    dropped: u32,
+    position: u64,
+}
+
+impl<const CHANNELS: usize, const MAX_REGIONS: usize> ClippingDetector<CHANNELS, MAX_REGIONS> {
+    pub fn new(min_run: u64) -> Self {
+        Self {
+            min_run: min_run.max(1),
+            run_starts: [None; CHANNELS],
+            clipped_samples: [0; CHANNELS],
+            regions: [ClippedRegion {
+                channel: 0,
+                frames: FrameRange { start: 0, end: 0 },
+            }; MAX_REGIONS],
+            len: 0,
+            dropped: 0,
+            position: 0,
+        }
+    }
+
+    /// The number of samples in each channel that are in a clipped region
+    pub fn clipped_samples(&self) -> [u64; CHANNELS] {
+        self.clipped_samples
+    }
+
+    pub fn regions(&self) -> &[ClippedRegion] {
+        &self.regions[..self.len]
+    }
+
+    /// The number of clipped regions that were found after `MAX_REGIONS` regions were already found
+    pub fn dropped(&self) -> u32 {
+        self.dropped
+    }
+
+    /// `true` if anything clipped
+    pub fn clipped(&self) -> bool {
+        self.len > 0 || self.dropped > 0
+    }
+
+    fn end_run(&mut self, channel: usize) {
+        if let Some(start) = self.run_starts[channel].take()
+            && self.position - start >= self.min_run
+        {
+            self.clipped_samples[channel] += self.position - start;
+            let region = ClippedRegion {
+                channel,
+                frames: FrameRange {
+                    start,
+                    end: self.position,
+                },
+            };
+            match self.regions.get_mut(self.len) {
+                Some(slot) => {
+                    *slot = region;
+                    self.len += 1;
+                }
+                None => self.dropped = self.dropped.saturating_add(1),
+            }
+        }
+    }
+}
+
+impl<const CHANNELS: usize, const MAX_REGIONS: usize> Analysis
+    for ClippingDetector<CHANNELS, MAX_REGIONS>
+{
+    fn frame(&mut self, frame: Frame<'_>) {
+        for (channel, sample) in frame.samples().take(CHANNELS).enumerate() {
+            if frame.is_full_scale(sample) {
+                self.run_starts[channel].get_or_insert(self.position);
+            } else {
+                self.end_run(channel);
+            }
+        }
+        self.position += 1;
+    }
+
+    fn finish(&mut self) {
+        for channel in 0..CHANNELS {
+            self.end_run(channel);
+        }
+    }
+}
+
+/// Finds the first and last frame that is not silent, for trimming silence at the start and end
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct TrimFinder {
+    /// Scaled like signed 32-bit samples
+    threshold: u32,
+    first: Option<u64>,
+    last: Option<u64>,
+    position: u64,
+}
+
+/// The part of the `data` chunk to keep when trimming
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct CropPlan {
+    /// The offset in the `data` chunk of the first byte to keep
+    pub data_offset: u32,
+    /// The number of bytes to keep. Write them to a new file with
+    /// [`Writer::write_samples`](crate::writer::Writer::write_samples).
+    pub data_len: u32,
+}
+
+impl TrimFinder {
+    /// A frame is silent if every sample is quieter than `threshold`, which is scaled like signed
+    /// 32-bit samples
+    pub fn new(threshold: u32) -> Self {
+        Self {
+            threshold,
+            first: None,
+            last: None,
+            position: 0,
+        }
+    }
+
+    /// The frames from the first to the last frame that is not silent, with up to `padding` frames
+    /// of silence kept before and after. [`None`] if everything is silent.
+    pub fn range(&self, padding: u64) -> Option<FrameRange> {
+        Some(FrameRange {
+            start: self.first?.saturating_sub(padding),
+            end: self
+                .last?
+                .saturating_add(1)
+                .saturating_add(padding)
+                .min(self.position),
+        })
+    }
+
+    /// Like [`TrimFinder::range`], but in bytes of the `data` chunk
+    pub fn crop_plan(&self, meta_data: &WavMetaData, padding: u64) -> Option<CropPlan> {
+        let range = self.range(padding)?;
+        let block_align = u64::from(meta_data.fmt.n_block_align.get());
+        // Within the `data` chunk, so they fit
+        Some(CropPlan {
+            data_offset: (range.start * block_align) as u32,
+            data_len: ((range.end - range.start) * block_align) as u32,
+        })
+    }
+}
+
+impl Analysis for TrimFinder {
+    fn frame(&mut self, frame: Frame<'_>) {
+        if frame
+            .samples()
+            .any(|sample| sample.unsigned_abs() >= self.threshold)
+        {
+            self.first.get_or_insert(self.position);
+            self.last = Some(self.position);
+        }
+        self.position += 1;
+    }
+}
+
+#[cfg(all(test, feature = "test-wav"))]
+mod tests {
+    use super::*;
+    use crate::{
+        fmt::{Codec, FmtDataBuilder},
+        slice,
+        test_wav::TestWav,
+    };
+
+    fn analyzer() -> Analyzer<Levels> {
+        let fmt = FmtDataBuilder::new(Codec::Pcm, 8000, 2, 16)
+            .build()
+            .unwrap();
+        let mut buffer = [0; 128];
+        let len = TestWav::new(fmt, 10).write(&mut buffer).unwrap();
+        let meta_data = slice::parse(&buffer[..len]).unwrap();
+        Analyzer::new(&meta_data, Levels::default(), 16).unwrap()
+    }
+
+    #[test]
+    fn wrong_data_len() {
+        for len in [12, 20] {
+            assert!(matches!(
+                analyzer().process_data(&[0; 20][..len]),
+                Err(Error::WrongDataLen { expected: 16, actual }) if actual == len
+            ));
+        }
+        assert!(matches!(
+            analyzer().process_data(&[0; 16]),
+            Ok(AnalyzeProcessDataOutput::InProgress(_))
+        ));
+    }
+}
@@ -0,0 +1,34 @@
+//! `arbitrary::Arbitrary` for types that have zerocopy fields, which can't derive it. The fields
+//! are made from arbitrary bytes.
+
+use arbitrary::{Arbitrary, Result, Unstructured};
+use zerocopy::transmute;
+
+use crate::{
+    FmtData,
+    fact::FactData,
+    fmt::FmtExtension,
+    snapshot::{ParserSnapshot, StreamDataSnapshot},
+};
+
+macro_rules! impl_arbitrary_from_bytes {
+    ($($t:ty),*) => {
+        $(impl<'a> Arbitrary<'a> for $t {
+            fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+                Ok(transmute!(<[u8; size_of::<$t>()]>::arbitrary(u)?))
+            }
+
+            fn size_hint(depth: usize) -> (usize, Option<usize>) {
+                <[u8; size_of::<$t>()]>::size_hint(depth)
+            }
+        })*
+    };
+}
+
+impl_arbitrary_from_bytes!(
+    FmtData,
+    FmtExtension,
+    FactData,
+    ParserSnapshot,
+    StreamDataSnapshot
+);
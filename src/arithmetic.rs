@@ -0,0 +1,50 @@
+//! Checked math for positions and sizes.
+//!
+//! Positions and sizes usually come from a file, which might be malformed. All math on them goes
+//! through these helpers. Overflow caused by the contents of a file is always returned as an error
+//! (and never asserted on, so that debug builds don't panic on malformed files). Math that can only
+//! overflow if this crate has a bug uses [`add_in_bounds`], which has a debug assertion.
+
+/// A position or size doesn't fit in its type
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Overflow;
+
+/// Integer types used for positions and sizes
+pub(crate) trait Position: Copy {
+    fn checked_add(self, rhs: Self) -> Option<Self>;
+    fn checked_next_multiple_of_2(self) -> Option<Self>;
+}
+
+macro_rules! impl_position {
+    ($($t:ty),*) => {
+        $(impl Position for $t {
+            fn checked_add(self, rhs: Self) -> Option<Self> {
+                <$t>::checked_add(self, rhs)
+            }
+
+            fn checked_next_multiple_of_2(self) -> Option<Self> {
+                <$t>::checked_next_multiple_of(self, 2)
+            }
+        })*
+    };
+}
+
+impl_position!(u32, u64);
+
+/// Returns `address + size`, plus a padding byte if `pad` is `true` and `size` is odd
+pub(crate) fn checked_advance<T: Position>(address: T, size: T, pad: bool) -> Result<T, Overflow> {
+    let size = if pad {
+        size.checked_next_multiple_of_2().ok_or(Overflow)?
+    } else {
+        size
+    };
+    address.checked_add(size).ok_or(Overflow)
+}
+
+/// For math that was already checked to be in bounds
+#[track_caller]
+pub(crate) fn add_in_bounds(a: u32, b: u32) -> u32 {
+    let result = a.checked_add(b);
+    debug_assert!(result.is_some(), "{a} + {b} overflowed");
+    result.unwrap_or(u32::MAX)
+}
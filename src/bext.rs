@@ -0,0 +1,311 @@
+//! The Broadcast Wave Format `bext` chunk.
+//!
+//! See <https://tech.ebu.ch/docs/tech/tech3285.pdf>.
+
+use pure_riff::{Id, RiffChunkHeader};
+use zerocopy::{
+    FromBytes, FromZeros, Immutable, IntoBytes, KnownLayout,
+    little_endian::{I16, U16, U32},
+};
+
+use crate::writer::{WriteChunk, WriteInstruction};
+
+pub const BEXT_ID: Id = *b"bext";
+
+/// The fixed-size part of the `bext` chunk. Text fields are ASCII and padded with null bytes.
+#[derive(Debug, Clone, Copy, FromBytes, IntoBytes, Immutable, KnownLayout)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[repr(C)]
+pub struct BextData {
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_le::bytes"))]
+    pub description: [u8; 256],
+    pub originator: [u8; 32],
+    pub originator_reference: [u8; 32],
+    /// `yyyy:mm:dd`
+    pub origination_date: [u8; 10],
+    /// `hh:mm:ss`
+    pub origination_time: [u8; 8],
+    /// The number of samples since midnight of the first sample
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_le::u32"))]
+    pub time_reference_low: U32,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_le::u32"))]
+    pub time_reference_high: U32,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_le::u16"))]
+    pub version: U16,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_le::bytes"))]
+    pub umid: [u8; 64],
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_le::i16"))]
+    pub loudness_value: I16,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_le::i16"))]
+    pub loudness_range: I16,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_le::i16"))]
+    pub max_true_peak_level: I16,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_le::i16"))]
+    pub max_momentary_loudness: I16,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_le::i16"))]
+    pub max_short_term_loudness: I16,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_le::bytes"))]
+    pub reserved: [u8; 180],
+}
+
+const _: () = assert!(size_of::<BextData>() == 602);
+
+impl Default for BextData {
+    fn default() -> Self {
+        Self {
+            version: 2.into(),
+            ..FromZeros::new_zeroed()
+        }
+    }
+}
+
+/// Copies as much of `value` as fits and fills the rest with null bytes
+fn copy_text(field: &mut [u8], value: &str) {
+    let len = value.len().min(field.len());
+    field[..len].copy_from_slice(&value.as_bytes()[..len]);
+    field[len..].fill(0);
+}
+
+impl BextData {
+    /// The offset of `time_reference_low` in the chunk data
+    pub const TIME_REFERENCE_OFFSET: u32 = 256 + 32 + 32 + 10 + 8;
+
+    pub fn time_reference(&self) -> u64 {
+        u64::from(self.time_reference_low.get()) | (u64::from(self.time_reference_high.get()) << 32)
+    }
+
+    pub fn set_time_reference(&mut self, time_reference: u64) {
+        self.time_reference_low = (time_reference as u32).into();
+        self.time_reference_high = ((time_reference >> 32) as u32).into();
+    }
+
+    /// The time reference as a timecode. [`None`] if `sample_rate` is 0.
+    pub fn timecode(&self, sample_rate: u32, frame_rate: FrameRate) -> Option<Timecode> {
+        Timecode::from_samples(self.time_reference(), sample_rate, frame_rate)
+    }
+
+    /// The UMID, which identifies the recording. [`None`] if the version is 0 or if there isn't a
+    /// valid UMID.
+    pub fn umid(&self) -> Option<Umid> {
+        if self.version.get() == 0 || self.umid[..UMID_LABEL.len()] != UMID_LABEL {
+            return None;
+        }
+        match self.umid[UMID_LEN_OFFSET] {
+            0x13 => Some(Umid::Basic(self.umid[..32].try_into().unwrap())),
+            0x33 => Some(Umid::Extended(self.umid)),
+            _ => None,
+        }
+    }
+
+    /// Text that doesn't fit is truncated
+    pub fn set_description(&mut self, description: &str) {
+        copy_text(&mut self.description, description);
+    }
+
+    /// Text that doesn't fit is truncated
+    pub fn set_originator(&mut self, originator: &str) {
+        copy_text(&mut self.originator, originator);
+    }
+
+    /// Text that doesn't fit is truncated
+    pub fn set_originator_reference(&mut self, originator_reference: &str) {
+        copy_text(&mut self.originator_reference, originator_reference);
+    }
+
+    /// Should be formatted as `yyyy:mm:dd`. Text that doesn't fit is truncated.
+    pub fn set_origination_date(&mut self, origination_date: &str) {
+        copy_text(&mut self.origination_date, origination_date);
+    }
+
+    /// Should be formatted as `hh:mm:ss`. Text that doesn't fit is truncated.
+    pub fn set_origination_time(&mut self, origination_time: &str) {
+        copy_text(&mut self.origination_time, origination_time);
+    }
+}
+
+/// The start of the universal label of a UMID, which is defined in SMPTE 330M. The byte after it
+/// is the version, which can be different.
+const UMID_LABEL: [u8; 7] = [0x06, 0x0A, 0x2B, 0x34, 0x01, 0x01, 0x01];
+/// The len byte is after the 12 byte universal label
+const UMID_LEN_OFFSET: usize = 12;
+
+/// A unique material identifier
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Umid {
+    Basic([u8; 32]),
+    /// A basic UMID followed by the time, place and creator of the recording
+    Extended([u8; 64]),
+}
+
+impl Umid {
+    pub fn as_bytes(&self) -> &[u8] {
+        match self {
+            Self::Basic(umid) => umid,
+            Self::Extended(umid) => umid,
+        }
+    }
+
+    /// The basic UMID, which is the same for both kinds
+    pub fn basic(&self) -> [u8; 32] {
+        self.as_bytes()[..32].try_into().unwrap()
+    }
+}
+
+/// The frame rate of a timecode, which is `numerator / denominator` frames per second
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct FrameRate {
+    pub numerator: u32,
+    pub denominator: u32,
+    /// Skip frame numbers so that the timecode matches the time of day. Only for 29.97 and 59.94.
+    pub drop_frame: bool,
+}
+
+impl FrameRate {
+    pub const FPS_23_976: Self = Self::new(24_000, 1001, false);
+    pub const FPS_24: Self = Self::new(24, 1, false);
+    pub const FPS_25: Self = Self::new(25, 1, false);
+    pub const FPS_29_97: Self = Self::new(30_000, 1001, false);
+    pub const FPS_29_97_DROP_FRAME: Self = Self::new(30_000, 1001, true);
+    pub const FPS_30: Self = Self::new(30, 1, false);
+    pub const FPS_50: Self = Self::new(50, 1, false);
+    pub const FPS_59_94_DROP_FRAME: Self = Self::new(60_000, 1001, true);
+    pub const FPS_60: Self = Self::new(60, 1, false);
+
+    pub const fn new(numerator: u32, denominator: u32, drop_frame: bool) -> Self {
+        Self {
+            numerator,
+            denominator,
+            drop_frame,
+        }
+    }
+
+    /// The frames per second that are counted, such as 30 for 29.97
+    pub fn nominal(&self) -> u32 {
+        self.numerator.div_ceil(self.denominator.max(1))
+    }
+}
+
+/// A SMPTE timecode, which is shown as `hh:mm:ss:ff`, or `hh:mm:ss;ff` for drop frame
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Timecode {
+    pub hours: u8,
+    pub minutes: u8,
+    pub seconds: u8,
+    pub frames: u8,
+    pub drop_frame: bool,
+}
+
+impl Timecode {
+    /// The timecode of the sample `samples` samples after midnight. Wraps around after 24 hours.
+    /// [`None`] if `sample_rate` or the frame rate is 0.
+    pub fn from_samples(samples: u64, sample_rate: u32, frame_rate: FrameRate) -> Option<Self> {
+        if sample_rate == 0 || frame_rate.numerator == 0 || frame_rate.denominator == 0 {
+            return None;
+        }
+        let nominal = u64::from(frame_rate.nominal());
+        let mut frames = u64::try_from(
+            u128::from(samples) * u128::from(frame_rate.numerator)
+                / (u128::from(sample_rate) * u128::from(frame_rate.denominator)),
+        )
+        .unwrap_or(u64::MAX);
+        if frame_rate.drop_frame {
+            // Frame numbers 0 and 1 (0 to 3 for 59.94) are skipped at the start of every minute
+            // except every 10th minute
+            let dropped = nominal / 15;
+            let frames_per_minute = nominal * 60 - dropped;
+            let frames_per_10_minutes = frames_per_minute * 10 + dropped;
+            let (tens, rest) = (
+                frames / frames_per_10_minutes,
+                frames % frames_per_10_minutes,
+            );
+            frames += dropped * 9 * tens;
+            if rest > dropped {
+                frames += dropped * ((rest - dropped) / frames_per_minute);
+            }
+        }
+        let seconds = frames / nominal;
+        Some(Self {
+            hours: (seconds / 3600 % 24) as u8,
+            minutes: (seconds / 60 % 60) as u8,
+            seconds: (seconds % 60) as u8,
+            frames: (frames % nominal) as u8,
+            drop_frame: frame_rate.drop_frame,
+        })
+    }
+}
+
+impl core::fmt::Display for Timecode {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(
+            f,
+            "{:02}:{:02}:{:02}{}{:02}",
+            self.hours,
+            self.minutes,
+            self.seconds,
+            if self.drop_frame { ';' } else { ':' },
+            self.frames
+        )
+    }
+}
+
+/// A `bext` chunk that can be written with
+/// [`Writer::write_chunk`](crate::writer::Writer::write_chunk).
+///
+/// The size of the chunk doesn't depend on the time reference, so you can write the chunk before
+/// recording, and then use [`time_reference_patch`] to set the time reference when you are done.
+#[derive(Debug, Clone, Copy)]
+pub struct Bext<'a> {
+    pub data: BextData,
+    pub coding_history: &'a str,
+    /// The coding history is padded with null bytes to at least this len, leaving room to change
+    /// it later without moving other chunks
+    pub reserved_coding_history_len: u32,
+}
+
+impl Bext<'_> {
+    fn coding_history_len(&self) -> u32 {
+        u32::try_from(self.coding_history.len())
+            .unwrap_or(u32::MAX)
+            .max(self.reserved_coding_history_len)
+    }
+}
+
+impl WriteChunk for Bext<'_> {
+    fn chunk_id(&self) -> Id {
+        BEXT_ID
+    }
+
+    fn data_len(&self) -> u32 {
+        u32::try_from(size_of::<BextData>())
+            .unwrap()
+            .saturating_add(self.coding_history_len())
+    }
+
+    fn write_data(&self, buffer: &mut [u8]) {
+        let (data, coding_history) = buffer.split_at_mut(size_of::<BextData>());
+        data.copy_from_slice(self.data.as_bytes());
+        coding_history[..self.coding_history.len()].copy_from_slice(self.coding_history.as_bytes());
+        coding_history[self.coding_history.len()..].fill(0);
+    }
+}
+
+/// Overwrites the time reference of a `bext` chunk that was already written at
+/// `bext_chunk_position`
+pub fn time_reference_patch(
+    bext_chunk_position: u64,
+    time_reference: u64,
+    buffer: &mut [u8; 8],
+) -> WriteInstruction<'_> {
+    *buffer = time_reference.to_le_bytes();
+    WriteInstruction {
+        position: bext_chunk_position
+            + u64::try_from(size_of::<RiffChunkHeader>()).unwrap()
+            + u64::from(BextData::TIME_REFERENCE_OFFSET),
+        data: buffer,
+    }
+}
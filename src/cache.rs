@@ -0,0 +1,108 @@
+//! Fewer, bigger reads, for storage where every read has a high latency, like SPI flash behind a
+//! file system.
+//!
+//! The state machines ask for a few bytes at a time, usually one chunk header per read. With a
+//! [`ReadCache`], the first read gets `N` bytes, and the reads after it that are in those bytes
+//! don't need to touch the storage.
+
+use crate::{ReadInstruction, storage::Storage};
+
+/// Remembers the bytes of the last big read
+#[derive(Debug, Clone)]
+pub struct ReadCache<const N: usize = 512> {
+    buffer: [u8; N],
+    position: u64,
+    len: usize,
+}
+
+impl<const N: usize> Default for ReadCache<N> {
+    fn default() -> Self {
+        Self {
+            buffer: [0; N],
+            position: 0,
+            len: 0,
+        }
+    }
+}
+
+impl<const N: usize> ReadCache<N> {
+    /// The bytes of the read, if they are in the cache
+    pub fn get(&self, read_instruction: &ReadInstruction) -> Option<&[u8]> {
+        let start = usize::try_from(read_instruction.position.checked_sub(self.position)?).ok()?;
+        let len = usize::try_from(read_instruction.len).ok()?;
+        self.buffer[..self.len].get(start..)?.get(..len)
+    }
+
+    /// The big read to do when [`ReadCache::get`] returns [`None`]. Read it into
+    /// [`ReadCache::buffer`] and then call [`ReadCache::filled`]. Returns [`None`] if the read is
+    /// bigger than the cache.
+    pub fn fill_instruction(&self, read_instruction: &ReadInstruction) -> Option<ReadInstruction> {
+        let len = u32::try_from(N).unwrap_or(u32::MAX);
+        (read_instruction.len <= len).then_some(ReadInstruction {
+            position: read_instruction.position,
+            len,
+        })
+    }
+
+    pub fn buffer(&mut self) -> &mut [u8; N] {
+        &mut self.buffer
+    }
+
+    /// `len` can be less than `N` if the file ended
+    pub fn filled(&mut self, position: u64, len: usize) {
+        self.position = position;
+        self.len = len.min(N);
+    }
+
+    /// Forget the cached bytes, for example after the file was written to
+    pub fn clear(&mut self) {
+        self.len = 0;
+    }
+}
+
+/// A [`Storage`] that reads through a [`ReadCache`]. If a big read fails, for example because it
+/// goes past the end of the file, the bytes are read directly instead.
+#[derive(Debug)]
+pub struct CachedStorage<S, const N: usize = 512> {
+    pub storage: S,
+    cache: ReadCache<N>,
+}
+
+impl<S, const N: usize> CachedStorage<S, N> {
+    pub fn new(storage: S) -> Self {
+        Self {
+            storage,
+            cache: Default::default(),
+        }
+    }
+}
+
+impl<S: Storage, const N: usize> Storage for CachedStorage<S, N> {
+    type Error = S::Error;
+
+    fn read(&mut self, position: u64, buffer: &mut [u8]) -> Result<(), Self::Error> {
+        let read_instruction = ReadInstruction {
+            position,
+            len: buffer.len().try_into().unwrap_or(u32::MAX),
+        };
+        if self.cache.get(&read_instruction).is_none()
+            && let Some(fill) = self.cache.fill_instruction(&read_instruction)
+        {
+            self.cache.clear();
+            if self
+                .storage
+                .read(fill.position, self.cache.buffer())
+                .is_ok()
+            {
+                self.cache.filled(fill.position, N);
+            }
+        }
+        match self.cache.get(&read_instruction) {
+            Some(data) => {
+                buffer.copy_from_slice(data);
+                Ok(())
+            }
+            None => self.storage.read(position, buffer),
+        }
+    }
+}
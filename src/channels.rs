@@ -0,0 +1,210 @@
+//! Rearranging the channels of interleaved frames.
+
+use crate::{FmtData, convert::Converted, fmt::FmtExtension};
+
+/// Splits interleaved frames into one buffer for each channel. The number of channels is the
+/// number of outputs. Returns the number of frames, which is limited by the input and every output.
+pub fn deinterleave<T: Copy>(input: &[T], outputs: &mut [&mut [T]]) -> usize {
+    let Some(frames) = input.len().checked_div(outputs.len()) else {
+        return 0;
+    };
+    let frames = outputs
+        .iter()
+        .map(|output| output.len())
+        .fold(frames, usize::min);
+    for (i, frame) in input.chunks_exact(outputs.len()).take(frames).enumerate() {
+        for (sample, output) in frame.iter().zip(outputs.iter_mut()) {
+            output[i] = *sample;
+        }
+    }
+    frames
+}
+
+/// Like [`deinterleave`], but the input can be split anywhere, even in the middle of a frame
+#[derive(Debug, Clone, Copy, Default)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Deinterleaver {
+    /// The channel of the next sample
+    channel: usize,
+    /// The index in the outputs of the next sample
+    frame: usize,
+}
+
+impl Deinterleaver {
+    /// Writes samples into the outputs, after the ones that were already written. Returns the
+    /// number of samples used from the input, which is less than its len when the outputs are full.
+    pub fn push<T: Copy>(&mut self, input: &[T], outputs: &mut [&mut [T]]) -> usize {
+        let mut used = 0;
+        for sample in input {
+            let Some(output) = outputs
+                .get_mut(self.channel)
+                .and_then(|output| output.get_mut(self.frame))
+            else {
+                break;
+            };
+            *output = *sample;
+            used += 1;
+            self.channel += 1;
+            if self.channel == outputs.len() {
+                self.channel = 0;
+                self.frame += 1;
+            }
+        }
+        used
+    }
+
+    /// The number of complete frames in the outputs
+    pub fn frames(&self) -> usize {
+        self.frame
+    }
+
+    /// Start writing at the beginning of the outputs again, for example after they were sent to
+    /// the DAC. The samples of a partial frame are moved to the beginning.
+    pub fn reset<T: Copy>(&mut self, outputs: &mut [&mut [T]]) {
+        for output in &mut outputs[..self.channel] {
+            output[0] = output[self.frame];
+        }
+        self.frame = 0;
+    }
+}
+
+/// How [`downmix_stereo`] combines the left and right channels
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Downmix {
+    /// Half of each channel, which never clips
+    Average,
+    /// Each channel is multiplied by a weight where 32768 is 1.0, and the results are added with
+    /// saturation
+    Weights { left: i32, right: i32 },
+}
+
+/// Combines interleaved stereo frames into mono samples. Returns the number of frames.
+pub fn downmix_stereo(input: &[i16], output: &mut [i16], downmix: Downmix) -> usize {
+    let frames = (input.len() / 2).min(output.len());
+    let (left_weight, right_weight) = match downmix {
+        Downmix::Average => (1 << 14, 1 << 14),
+        Downmix::Weights { left, right } => (left, right),
+    };
+    for (frame, output) in input.chunks_exact(2).zip(&mut output[..frames]) {
+        let sum = i64::from(frame[0]) * i64::from(left_weight)
+            + i64::from(frame[1]) * i64::from(right_weight);
+        *output = (sum >> 15).clamp(i16::MIN.into(), i16::MAX.into()) as i16;
+    }
+    frames
+}
+
+/// Where [`upmix_mono`] puts the mono samples
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Upmix {
+    /// The same sample in both channels
+    Both,
+    /// Only in the left channel, with silence in the right channel
+    Left,
+    /// Only in the right channel, with silence in the left channel
+    Right,
+}
+
+/// Turns mono samples into interleaved stereo frames. Silence is [`Default::default`], so this is
+/// meant for signed samples. Returns the number of frames.
+pub fn upmix_mono<T: Copy + Default>(input: &[T], output: &mut [T], upmix: Upmix) -> usize {
+    let frames = input.len().min(output.len() / 2);
+    for (sample, frame) in input[..frames].iter().zip(output.chunks_exact_mut(2)) {
+        let silence = T::default();
+        frame.copy_from_slice(&match upmix {
+            Upmix::Both => [*sample, *sample],
+            Upmix::Left => [*sample, silence],
+            Upmix::Right => [silence, *sample],
+        });
+    }
+    frames
+}
+
+/// Takes some of the channels out of interleaved frames, for example channels 3 and 4 of an 8
+/// channel recording
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct ChannelSelection {
+    block_align: usize,
+    bytes_per_sample: usize,
+    /// Bit `i` is set if channel `i` is selected
+    channels: u32,
+}
+
+impl ChannelSelection {
+    /// `channels` has bit `i` set for every channel index `i` to keep. Returns [`None`] if no
+    /// channels are selected, a selected channel doesn't exist, or the block align is not a
+    /// multiple of the number of channels.
+    pub fn new(fmt: &FmtData, channels: u32) -> Option<Self> {
+        let n_channels = usize::from(fmt.n_channels.get());
+        let block_align = usize::from(fmt.n_block_align.get());
+        if channels == 0
+            || channels
+                .checked_shr(n_channels.try_into().ok()?)
+                .unwrap_or(0)
+                != 0
+            || block_align == 0
+            || !block_align.is_multiple_of(n_channels)
+        {
+            return None;
+        }
+        Some(Self {
+            block_align,
+            bytes_per_sample: block_align / n_channels,
+            channels,
+        })
+    }
+
+    /// Selects channels by speaker position. `speakers` uses the same bits as
+    /// [`FmtExtension::dw_channel_mask`]. The channels in the file are in the order of the bits
+    /// set in its channel mask. Returns [`None`] if the file has no channel mask or doesn't have
+    /// all of the speakers.
+    pub fn from_speakers(
+        fmt: &FmtData,
+        fmt_extension: &FmtExtension,
+        speakers: u32,
+    ) -> Option<Self> {
+        let channel_mask = fmt_extension.dw_channel_mask.get();
+        if speakers & !channel_mask != 0 {
+            return None;
+        }
+        // The index of a speaker's channel is the number of speakers before it in the mask
+        let channels = (0..u32::BITS)
+            .filter(|bit| speakers & (1 << bit) != 0)
+            .map(|bit| 1 << (channel_mask & ((1 << bit) - 1)).count_ones())
+            .fold(0, |channels, channel| channels | channel);
+        Self::new(fmt, channels)
+    }
+
+    /// The len of an output frame
+    pub fn output_block_align(&self) -> usize {
+        self.bytes_per_sample * usize::try_from(self.channels.count_ones()).unwrap()
+    }
+
+    /// Copies the selected channels of whole frames into the output, interleaved
+    pub fn select(&self, input: &[u8], output: &mut [u8]) -> Converted {
+        let frames = (input.len() / self.block_align).min(output.len() / self.output_block_align());
+        for (input, output) in input
+            .chunks_exact(self.block_align)
+            .zip(output.chunks_exact_mut(self.output_block_align()))
+            .take(frames)
+        {
+            let selected = input
+                .chunks_exact(self.bytes_per_sample)
+                // Only the first 32 channels can be selected
+                .take(u32::BITS.try_into().unwrap())
+                .enumerate()
+                .filter(|(channel, _)| self.channels & (1 << channel) != 0);
+            for ((_, sample), output) in
+                selected.zip(output.chunks_exact_mut(self.bytes_per_sample))
+            {
+                output.copy_from_slice(sample);
+            }
+        }
+        Converted {
+            input_len: frames * self.block_align,
+            samples: frames * usize::try_from(self.channels.count_ones()).unwrap(),
+        }
+    }
+}
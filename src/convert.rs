@@ -0,0 +1,495 @@
+//! Converting samples into the format that I2S peripherals want.
+//!
+//! The converters work on buffers that you provide. Each call converts as many whole samples as fit
+//! in both the input and the output. Converters for formats where a sample can be split across two
+//! reads, like [`Packed24ToI32`], keep the partial sample and return a [`Converted`].
+
+use crate::{
+    WavMetaData,
+    fmt::Codec,
+    sample::{I24, Sample},
+};
+
+/// Converts unsigned 8-bit PCM, which is biased by 0x80, into signed 16-bit samples
+pub fn u8_to_i16(input: &[u8], output: &mut [i16]) -> usize {
+    let len = input.len().min(output.len());
+    for (sample, output) in input[..len].iter().zip(&mut output[..len]) {
+        *output = i16::from(*sample as i8 ^ i8::MIN) << 8;
+    }
+    len
+}
+
+/// Converts little-endian signed 16-bit PCM into native samples
+pub fn i16_le_to_i16(input: &[u8], output: &mut [i16]) -> usize {
+    let len = (input.len() / size_of::<i16>()).min(output.len());
+    for (sample, output) in input.chunks_exact(size_of::<i16>()).zip(&mut output[..len]) {
+        *output = i16::from_le_bytes([sample[0], sample[1]]);
+    }
+    len
+}
+
+/// Converts little-endian 32-bit float samples into signed 16-bit samples. Samples outside of
+/// `-1.0..=1.0` are clamped and NaN becomes 0.
+pub fn f32_le_to_i16(input: &[u8], output: &mut [i16]) -> usize {
+    let len = (input.len() / size_of::<f32>()).min(output.len());
+    for (sample, output) in input.chunks_exact(size_of::<f32>()).zip(&mut output[..len]) {
+        // `as` saturates
+        *output = (f32::from_le_bytes(sample.try_into().unwrap()) * 32768.0) as i16;
+    }
+    len
+}
+
+/// Converts little-endian 32-bit float samples into signed 32-bit samples. Samples outside of
+/// `-1.0..=1.0` are clamped and NaN becomes 0.
+pub fn f32_le_to_i32(input: &[u8], output: &mut [i32]) -> usize {
+    let len = (input.len() / size_of::<f32>()).min(output.len());
+    for (sample, output) in input.chunks_exact(size_of::<f32>()).zip(&mut output[..len]) {
+        // `as` saturates
+        *output = (f32::from_le_bytes(sample.try_into().unwrap()) * 2147483648.0) as i32;
+    }
+    len
+}
+
+const fn decode_alaw(sample: u8) -> i16 {
+    let sample = sample ^ 0x55;
+    let mantissa = ((sample & 0x0F) as i16) << 4;
+    let segment = (sample & 0x70) >> 4;
+    let magnitude = match segment {
+        0 => mantissa + 8,
+        _ => (mantissa + 0x108) << (segment - 1),
+    };
+    if sample & 0x80 != 0 {
+        magnitude
+    } else {
+        -magnitude
+    }
+}
+
+/// The linear value of every A-law byte
+pub static ALAW_TO_I16: [i16; 256] = {
+    let mut table = [0; 256];
+    let mut i = 0;
+    while i < table.len() {
+        table[i] = decode_alaw(i as u8);
+        i += 1;
+    }
+    table
+};
+
+/// Converts A-law samples into signed 16-bit samples
+pub fn alaw_to_i16(input: &[u8], output: &mut [i16]) -> usize {
+    let len = input.len().min(output.len());
+    for (sample, output) in input[..len].iter().zip(&mut output[..len]) {
+        *output = ALAW_TO_I16[usize::from(*sample)];
+    }
+    len
+}
+
+const fn decode_mulaw(sample: u8) -> i16 {
+    let sample = !sample;
+    let magnitude = ((((sample & 0x0F) as i16) << 3) + 0x84) << ((sample & 0x70) >> 4);
+    if sample & 0x80 != 0 {
+        0x84 - magnitude
+    } else {
+        magnitude - 0x84
+    }
+}
+
+/// The linear value of every µ-law byte
+pub static MULAW_TO_I16: [i16; 256] = {
+    let mut table = [0; 256];
+    let mut i = 0;
+    while i < table.len() {
+        table[i] = decode_mulaw(i as u8);
+        i += 1;
+    }
+    table
+};
+
+/// Converts µ-law samples into signed 16-bit samples
+pub fn mulaw_to_i16(input: &[u8], output: &mut [i16]) -> usize {
+    let len = input.len().min(output.len());
+    for (sample, output) in input[..len].iter().zip(&mut output[..len]) {
+        *output = MULAW_TO_I16[usize::from(*sample)];
+    }
+    len
+}
+
+/// Converts the samples of a WAVE into signed 16-bit samples
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum I16Converter {
+    /// Unsigned 8-bit PCM
+    U8,
+    /// Signed 16-bit PCM
+    I16,
+    /// 32-bit IEEE float
+    F32,
+    ALaw,
+    MuLaw,
+}
+
+impl I16Converter {
+    /// Picks the converter for the format of the samples, if there is one
+    pub fn for_meta_data(meta_data: &WavMetaData) -> Option<Self> {
+        match (meta_data.codec()?, meta_data.fmt.w_bits_per_sample.get()) {
+            (Codec::Pcm, 8) => Some(Self::U8),
+            (Codec::Pcm, 16) => Some(Self::I16),
+            (Codec::IeeeFloat, 32) => Some(Self::F32),
+            (Codec::ALaw, 8) => Some(Self::ALaw),
+            (Codec::MuLaw, 8) => Some(Self::MuLaw),
+            _ => None,
+        }
+    }
+
+    /// The number of bytes of input for each sample
+    pub fn bytes_per_sample(&self) -> usize {
+        match self {
+            Self::U8 | Self::ALaw | Self::MuLaw => size_of::<u8>(),
+            Self::I16 => size_of::<i16>(),
+            Self::F32 => size_of::<f32>(),
+        }
+    }
+
+    pub fn convert(&self, input: &[u8], output: &mut [i16]) -> usize {
+        match self {
+            Self::U8 => u8_to_i16(input, output),
+            Self::I16 => i16_le_to_i16(input, output),
+            Self::F32 => f32_le_to_i16(input, output),
+            Self::ALaw => alaw_to_i16(input, output),
+            Self::MuLaw => mulaw_to_i16(input, output),
+        }
+    }
+}
+
+fn i24_le_to_i32(sample: [u8; 3]) -> i32 {
+    I24::from_le_bytes(sample).to_i32()
+}
+
+/// How much a converter used and produced
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Converted {
+    /// The number of bytes of input that were used
+    pub input_len: usize,
+    /// The number of samples written to the output
+    pub samples: usize,
+}
+
+/// Expands byte-packed 24-bit PCM into 32-bit samples, with the 24 bits in the most significant
+/// bits.
+///
+/// A sample can be split across two inputs. The bytes of the first part are kept until the next
+/// call to [`Packed24ToI32::convert`].
+#[derive(Debug, Clone, Copy, Default)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Packed24ToI32 {
+    partial: [u8; 3],
+    partial_len: usize,
+}
+
+impl Packed24ToI32 {
+    pub fn convert(&mut self, input: &[u8], output: &mut [i32]) -> Converted {
+        let mut input_len = 0;
+        let mut samples = 0;
+        while samples < output.len() {
+            let needed = self.partial.len() - self.partial_len;
+            let available = input.len() - input_len;
+            if self.partial_len == 0 && available >= self.partial.len() {
+                let sample = input[input_len..][..3].try_into().unwrap();
+                output[samples] = i24_le_to_i32(sample);
+                input_len += 3;
+                samples += 1;
+            } else if available >= needed {
+                self.partial[self.partial_len..].copy_from_slice(&input[input_len..][..needed]);
+                output[samples] = i24_le_to_i32(self.partial);
+                self.partial_len = 0;
+                input_len += needed;
+                samples += 1;
+            } else {
+                self.partial[self.partial_len..][..available].copy_from_slice(&input[input_len..]);
+                self.partial_len += available;
+                input_len += available;
+                break;
+            }
+        }
+        Converted { input_len, samples }
+    }
+
+    /// `true` if part of a sample is waiting for the rest of its bytes
+    pub fn has_partial_sample(&self) -> bool {
+        self.partial_len != 0
+    }
+}
+
+/// Expands the byte-packed 24-bit samples in the first `packed_len` bytes of `buffer` into native
+/// endian 32-bit words in the same buffer, with the 24 bits in the most significant bits. Returns
+/// the number of samples, or [`None`] if `buffer` is too small for the expanded samples. Bytes
+/// after the last whole sample are ignored.
+pub fn expand_24_in_place(buffer: &mut [u8], packed_len: usize) -> Option<usize> {
+    let samples = packed_len / 3;
+    if buffer.len() < samples.checked_mul(size_of::<i32>())? {
+        return None;
+    }
+    // Going backwards means that a sample is never overwritten before it is read
+    for i in (0..samples).rev() {
+        let sample = buffer[i * 3..][..3].try_into().unwrap();
+        buffer[i * size_of::<i32>()..][..size_of::<i32>()]
+            .copy_from_slice(&i24_le_to_i32(sample).to_ne_bytes());
+    }
+    Some(samples)
+}
+
+/// Where the valid bits of a sample go in a 32-bit I2S slot
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Justification {
+    /// In the most significant bits, with the rest of the bits 0
+    Left,
+    /// In the least significant bits, sign extended
+    Right,
+}
+
+/// Puts 32-bit samples with the valid bits in the most significant bits, like the ones from
+/// [`Packed24ToI32`], into the slots that an I2S peripheral wants
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct SlotPacker {
+    justification: Justification,
+    valid_bits: u32,
+}
+
+impl SlotPacker {
+    /// `valid_bits` is clamped to 1..=32
+    pub fn new(justification: Justification, valid_bits: u16) -> Self {
+        Self {
+            justification,
+            valid_bits: u32::from(valid_bits).clamp(1, 32),
+        }
+    }
+
+    /// Uses `w_valid_bits_per_sample`, or `w_bits_per_sample` if there is no extension
+    pub fn for_meta_data(meta_data: &WavMetaData, justification: Justification) -> Self {
+        let valid_bits = meta_data
+            .fmt_extension
+            .as_ref()
+            .map(|fmt_extension| fmt_extension.w_valid_bits_per_sample.get())
+            .filter(|&valid_bits| valid_bits != 0)
+            .unwrap_or(meta_data.fmt.w_bits_per_sample.get());
+        Self::new(justification, valid_bits)
+    }
+
+    pub fn pack(&self, samples: &mut [i32]) {
+        let shift = 32 - self.valid_bits;
+        for sample in samples {
+            *sample = match self.justification {
+                Justification::Left => (*sample >> shift) << shift,
+                Justification::Right => *sample >> shift,
+            };
+        }
+    }
+}
+
+/// Reverses the bytes of every `sample_width` byte sample in place, for outputs that want big
+/// endian samples. Use it after converting, since the converters read little endian samples.
+/// Bytes after the last whole sample are left as they are.
+pub fn swap_bytes(buffer: &mut [u8], sample_width: usize) {
+    if sample_width < 2 {
+        return;
+    }
+    for sample in buffer.chunks_exact_mut(sample_width) {
+        sample.reverse();
+    }
+}
+
+/// A sample encoding, for converting between bit depths with [`convert_format`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum SampleFormat {
+    /// Unsigned 8-bit
+    U8,
+    /// Signed 16-bit little-endian
+    I16,
+    /// Signed 24-bit little-endian, packed into 3 bytes
+    I24,
+    /// Signed 32-bit little-endian
+    I32,
+    /// 32-bit little-endian IEEE float
+    F32,
+}
+
+impl SampleFormat {
+    /// The format of the samples of a WAVE, if it is one of the supported formats
+    pub fn from_meta_data(meta_data: &WavMetaData) -> Option<Self> {
+        match (meta_data.codec()?, meta_data.fmt.w_bits_per_sample.get()) {
+            (Codec::Pcm, 8) => Some(Self::U8),
+            (Codec::Pcm, 16) => Some(Self::I16),
+            (Codec::Pcm, 24) => Some(Self::I24),
+            (Codec::Pcm, 32) => Some(Self::I32),
+            (Codec::IeeeFloat, 32) => Some(Self::F32),
+            _ => None,
+        }
+    }
+
+    /// The number of bits of an integer format, or [`None`] for float
+    fn integer_bits(&self) -> Option<u32> {
+        match self {
+            Self::F32 => None,
+            format => Some(u32::try_from(format.bytes_per_sample()).unwrap() * 8),
+        }
+    }
+
+    pub fn bytes_per_sample(&self) -> usize {
+        match self {
+            Self::U8 => 1,
+            Self::I16 => 2,
+            Self::I24 => 3,
+            Self::I32 | Self::F32 => 4,
+        }
+    }
+
+    /// The largest sample, scaled like a signed 32-bit sample
+    pub(crate) fn full_scale(&self) -> i32 {
+        match self {
+            Self::U8 => u8::MAX.to_i32(),
+            Self::I16 => i16::MAX.to_i32(),
+            Self::I24 => I24::MAX.to_i32(),
+            Self::I32 | Self::F32 => i32::MAX,
+        }
+    }
+
+    /// Reads a sample as a signed 32-bit sample
+    pub(crate) fn read(&self, bytes: &[u8]) -> i32 {
+        match self {
+            Self::U8 => u8::read_le(bytes).to_i32(),
+            Self::I16 => i16::read_le(bytes).to_i32(),
+            Self::I24 => I24::read_le(bytes).to_i32(),
+            Self::I32 => i32::read_le(bytes),
+            Self::F32 => f32::read_le(bytes).to_i32(),
+        }
+    }
+
+    /// Writes a signed 32-bit sample, keeping the most significant bits
+    fn write(&self, sample: i32, bytes: &mut [u8]) {
+        match self {
+            Self::U8 => u8::from_i32(sample).write_le(bytes),
+            Self::I16 => i16::from_i32(sample).write_le(bytes),
+            Self::I24 => I24::from_i32(sample).write_le(bytes),
+            Self::I32 => sample.write_le(bytes),
+            Self::F32 => f32::from_i32(sample).write_le(bytes),
+        }
+    }
+}
+
+/// Converts between sample types. Returns the number of samples.
+pub fn convert_samples<I: Sample, O: Sample>(input: &[I], output: &mut [O]) -> usize {
+    let len = input.len().min(output.len());
+    for (input, output) in input.iter().zip(&mut output[..len]) {
+        *output = input.convert();
+    }
+    len
+}
+
+/// Converts samples from one format to another. When the output has fewer bits, the least
+/// significant bits are dropped. Float samples outside of `-1.0..=1.0` are clamped, unless both
+/// formats are the same.
+pub fn convert_format(
+    input_format: SampleFormat,
+    input: &[u8],
+    output_format: SampleFormat,
+    output: &mut [u8],
+) -> Converted {
+    let samples = (input.len() / input_format.bytes_per_sample())
+        .min(output.len() / output_format.bytes_per_sample());
+    let input = &input[..samples * input_format.bytes_per_sample()];
+    if input_format == output_format {
+        output[..input.len()].copy_from_slice(input);
+    } else {
+        for (input, output) in input
+            .chunks_exact(input_format.bytes_per_sample())
+            .zip(output.chunks_exact_mut(output_format.bytes_per_sample()))
+        {
+            output_format.write(input_format.read(input), output);
+        }
+    }
+    Converted {
+        input_len: input.len(),
+        samples,
+    }
+}
+
+/// TPDF (triangular) dither for [`convert_format_dithered`], with a small built-in random number
+/// generator
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Dither {
+    /// xorshift32 state, which is never 0
+    state: u32,
+}
+
+impl Dither {
+    pub fn new(seed: u32) -> Self {
+        Self {
+            state: if seed == 0 { 0x9E37_79B9 } else { seed },
+        }
+    }
+
+    fn next_u32(&mut self) -> u32 {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 17;
+        self.state ^= self.state << 5;
+        self.state
+    }
+
+    /// Noise between -1 and 1 LSB, where the LSB is `1 << shift`
+    fn noise(&mut self, shift: u32) -> i32 {
+        let mask = (1u32 << shift) - 1;
+        let a = i64::from(self.next_u32() & mask);
+        let b = i64::from(self.next_u32() & mask);
+        (a - b).try_into().unwrap()
+    }
+}
+
+impl Default for Dither {
+    fn default() -> Self {
+        Self::new(0)
+    }
+}
+
+/// Like [`convert_format`], but adds dither when the output has fewer integer bits than the input,
+/// so quiet parts don't get truncation distortion
+pub fn convert_format_dithered(
+    input_format: SampleFormat,
+    input: &[u8],
+    output_format: SampleFormat,
+    output: &mut [u8],
+    dither: &mut Dither,
+) -> Converted {
+    // A 32-bit output has no bits below the LSB to dither
+    let Some(output_bits) = output_format.integer_bits().filter(|&output_bits| {
+        output_bits < i32::BITS
+            && input_format
+                .integer_bits()
+                .is_none_or(|bits| bits > output_bits)
+    }) else {
+        return convert_format(input_format, input, output_format, output);
+    };
+    let shift = i32::BITS - output_bits;
+    let samples = (input.len() / input_format.bytes_per_sample())
+        .min(output.len() / output_format.bytes_per_sample());
+    for (input, output) in input
+        .chunks_exact(input_format.bytes_per_sample())
+        .zip(output.chunks_exact_mut(output_format.bytes_per_sample()))
+        .take(samples)
+    {
+        // Half an LSB is added because writing drops the low bits, which rounds down
+        let sample = input_format
+            .read(input)
+            .saturating_add(dither.noise(shift) + (1 << (shift - 1)));
+        output_format.write(sample, output);
+    }
+    Converted {
+        input_len: samples * input_format.bytes_per_sample(),
+        samples,
+    }
+}
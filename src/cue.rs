@@ -0,0 +1,70 @@
+//! The `cue ` chunk, which marks positions in the samples.
+
+use pure_riff::Id;
+use zerocopy::{FromBytes, Immutable, IntoBytes, KnownLayout, little_endian::U32};
+
+use crate::writer::WriteChunk;
+
+pub const CUE_ID: Id = *b"cue ";
+
+#[derive(Debug, Clone, Copy, FromBytes, IntoBytes, Immutable, KnownLayout)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[repr(C)]
+pub struct CuePoint {
+    /// Unique id, which other chunks such as `smpl` and `LIST` `adtl` use to refer to this point
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_le::u32"))]
+    pub id: U32,
+    /// The sample position when playing in order. Usually the same as `sample_offset`.
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_le::u32"))]
+    pub position: U32,
+    /// The id of the chunk the point is in. Usually `data`.
+    pub data_chunk_id: Id,
+    /// Only used with `wavl` lists
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_le::u32"))]
+    pub chunk_start: U32,
+    /// Only used with compressed formats
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_le::u32"))]
+    pub block_start: U32,
+    /// The frame index in the `data` chunk
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_le::u32"))]
+    pub sample_offset: U32,
+}
+
+impl CuePoint {
+    /// A point in the `data` chunk
+    pub fn new(id: u32, sample_offset: u32) -> Self {
+        Self {
+            id: id.into(),
+            position: sample_offset.into(),
+            data_chunk_id: *b"data",
+            chunk_start: 0.into(),
+            block_start: 0.into(),
+            sample_offset: sample_offset.into(),
+        }
+    }
+}
+
+/// A `cue ` chunk that can be written with
+/// [`Writer::write_chunk`](crate::writer::Writer::write_chunk)
+#[derive(Debug, Clone, Copy)]
+pub struct Cue<'a> {
+    pub points: &'a [CuePoint],
+}
+
+impl WriteChunk for Cue<'_> {
+    fn chunk_id(&self) -> Id {
+        CUE_ID
+    }
+
+    fn data_len(&self) -> u32 {
+        (size_of::<U32>() + size_of_val(self.points))
+            .try_into()
+            .unwrap_or(u32::MAX)
+    }
+
+    fn write_data(&self, buffer: &mut [u8]) {
+        let (count, points) = buffer.split_at_mut(size_of::<U32>());
+        count.copy_from_slice(&u32::try_from(self.points.len()).unwrap().to_le_bytes());
+        points.copy_from_slice(self.points.as_bytes());
+    }
+}
@@ -0,0 +1,261 @@
+//! [`defmt::Format`] for types with little endian fields or chunk ids, so that the fields are
+//! printed as numbers and the chunk ids are printed as ASCII.
+
+use defmt::{Format, Formatter, write};
+
+use crate::{
+    Error, ErrorLocation, FmtData,
+    adpcm::MsAdpcmExtension,
+    adtl::{LabeledTextData, Region},
+    bext::BextData,
+    cue::CuePoint,
+    edit::ChunkSelector,
+    fmt::FmtExtension,
+    scan::ChunkInfo,
+    smpl::{SampleLoop, SmplData},
+    text::until_null as text,
+    writer::Ds64Data,
+};
+
+impl Format for FmtData {
+    fn format(&self, f: Formatter) {
+        write!(
+            f,
+            "FmtData {{ format_tag: {=u16:#x}, n_channels: {=u16}, n_samples_per_sec: {=u32}, n_avg_bytes_per_sec: {=u32}, n_block_align: {=u16}, w_bits_per_sample: {=u16} }}",
+            self.format_tag.get(),
+            self.n_channels.get(),
+            self.n_samples_per_sec.get(),
+            self.n_avg_bytes_per_sec.get(),
+            self.n_block_align.get(),
+            self.w_bits_per_sample.get(),
+        );
+    }
+}
+
+impl Format for FmtExtension {
+    fn format(&self, f: Formatter) {
+        write!(
+            f,
+            "FmtExtension {{ cb_size: {=u16}, w_valid_bits_per_sample: {=u16}, dw_channel_mask: {=u32:#x}, sub_format: {=[u8]:x} }}",
+            self.cb_size.get(),
+            self.w_valid_bits_per_sample.get(),
+            self.dw_channel_mask.get(),
+            self.sub_format[..],
+        );
+    }
+}
+
+impl Format for Error {
+    fn format(&self, f: Formatter) {
+        match self {
+            Self::UnexpectedChunkId(id) => write!(f, "UnexpectedChunkId({=[u8]:a})", id[..]),
+            Self::InvalidRiff => write!(f, "InvalidRiff"),
+            Self::UnexpectedContainerId(id) => {
+                write!(f, "UnexpectedContainerId({=[u8]:a})", id[..])
+            }
+            Self::FmtDataTooSmall(len) => write!(f, "FmtDataTooSmall({=u32})", len),
+            Self::ChunkSizeOverflow { chunk_id, position } => write!(
+                f,
+                "ChunkSizeOverflow {{ chunk_id: {=[u8]:a}, position: {=u64} }}",
+                chunk_id[..],
+                position,
+            ),
+            Self::ChunkExtendsPastEnd { chunk_id, position } => write!(
+                f,
+                "ChunkExtendsPastEnd {{ chunk_id: {=[u8]:a}, position: {=u64} }}",
+                chunk_id[..],
+                position,
+            ),
+            Self::InvalidChunkId { chunk_id, position } => write!(
+                f,
+                "InvalidChunkId {{ chunk_id: {=[u8]:a}, position: {=u64} }}",
+                chunk_id[..],
+                position,
+            ),
+            Self::InvalidFmt(e) => write!(f, "InvalidFmt({})", e),
+            Self::MissingChunk(id) => write!(f, "MissingChunk({=[u8]:a})", id[..]),
+            Self::ScanLimitExceeded { position } => {
+                write!(f, "ScanLimitExceeded {{ position: {=u64} }}", position)
+            }
+            Self::FileTruncated { missing } => {
+                write!(f, "FileTruncated {{ missing: {=u64} }}", missing)
+            }
+            Self::InvalidFmtExtension => write!(f, "InvalidFmtExtension"),
+            Self::UnexpectedListType(id) => write!(f, "UnexpectedListType({=[u8]:a})", id[..]),
+            Self::WrongDataLen { expected, actual } => write!(
+                f,
+                "WrongDataLen {{ expected: {=u32}, actual: {=usize} }}",
+                expected, actual,
+            ),
+            Self::NoPendingRead => write!(f, "NoPendingRead"),
+        }
+    }
+}
+
+impl Format for ChunkInfo {
+    fn format(&self, f: Formatter) {
+        write!(
+            f,
+            "ChunkInfo {{ chunk_id: {=[u8]:a}, position: {=u64}, len: {=u32} }}",
+            self.chunk_id[..],
+            self.position,
+            self.len,
+        );
+    }
+}
+
+impl Format for ErrorLocation {
+    fn format(&self, f: Formatter) {
+        match &self.chunk_id {
+            Some(chunk_id) => write!(
+                f,
+                "ErrorLocation {{ position: {=u64}, chunk_id: {=[u8]:a} }}",
+                self.position,
+                chunk_id[..],
+            ),
+            None => write!(f, "ErrorLocation {{ position: {=u64} }}", self.position),
+        }
+    }
+}
+
+impl Format for ChunkSelector {
+    fn format(&self, f: Formatter) {
+        match &self.list_type {
+            Some(list_type) => write!(
+                f,
+                "ChunkSelector {{ chunk_id: {=[u8]:a}, list_type: {=[u8]:a} }}",
+                self.chunk_id[..],
+                list_type[..],
+            ),
+            None => write!(
+                f,
+                "ChunkSelector {{ chunk_id: {=[u8]:a} }}",
+                self.chunk_id[..]
+            ),
+        }
+    }
+}
+
+impl Format for CuePoint {
+    fn format(&self, f: Formatter) {
+        write!(
+            f,
+            "CuePoint {{ id: {=u32}, position: {=u32}, data_chunk_id: {=[u8]:a}, chunk_start: {=u32}, block_start: {=u32}, sample_offset: {=u32} }}",
+            self.id.get(),
+            self.position.get(),
+            self.data_chunk_id[..],
+            self.chunk_start.get(),
+            self.block_start.get(),
+            self.sample_offset.get(),
+        );
+    }
+}
+
+impl Format for SmplData {
+    fn format(&self, f: Formatter) {
+        write!(
+            f,
+            "SmplData {{ manufacturer: {=u32:#x}, product: {=u32:#x}, sample_period: {=u32}, midi_unity_note: {=u32}, midi_pitch_fraction: {=u32}, smpte_format: {=u32}, smpte_offset: {=u32:#x}, sample_loops_len: {=u32}, sampler_data_len: {=u32} }}",
+            self.manufacturer.get(),
+            self.product.get(),
+            self.sample_period.get(),
+            self.midi_unity_note.get(),
+            self.midi_pitch_fraction.get(),
+            self.smpte_format.get(),
+            self.smpte_offset.get(),
+            self.sample_loops_len.get(),
+            self.sampler_data_len.get(),
+        );
+    }
+}
+
+impl Format for SampleLoop {
+    fn format(&self, f: Formatter) {
+        write!(
+            f,
+            "SampleLoop {{ cue_point_id: {=u32}, loop_type: {=u32}, start: {=u32}, end: {=u32}, fraction: {=u32}, play_count: {=u32} }}",
+            self.cue_point_id.get(),
+            self.loop_type.get(),
+            self.start.get(),
+            self.end.get(),
+            self.fraction.get(),
+            self.play_count.get(),
+        );
+    }
+}
+
+impl Format for BextData {
+    fn format(&self, f: Formatter) {
+        write!(
+            f,
+            "BextData {{ description: {=[u8]:a}, originator: {=[u8]:a}, originator_reference: {=[u8]:a}, origination_date: {=[u8]:a}, origination_time: {=[u8]:a}, time_reference: {=u64}, version: {=u16}, loudness_value: {=i16}, loudness_range: {=i16}, max_true_peak_level: {=i16}, max_momentary_loudness: {=i16}, max_short_term_loudness: {=i16} }}",
+            text(&self.description),
+            text(&self.originator),
+            text(&self.originator_reference),
+            text(&self.origination_date),
+            text(&self.origination_time),
+            (u64::from(self.time_reference_high.get()) << 32)
+                | u64::from(self.time_reference_low.get()),
+            self.version.get(),
+            self.loudness_value.get(),
+            self.loudness_range.get(),
+            self.max_true_peak_level.get(),
+            self.max_momentary_loudness.get(),
+            self.max_short_term_loudness.get(),
+        );
+    }
+}
+
+impl Format for LabeledTextData {
+    fn format(&self, f: Formatter) {
+        write!(
+            f,
+            "LabeledTextData {{ cue_point_id: {=u32}, sample_len: {=u32}, purpose: {=[u8]:a}, country: {=u16}, language: {=u16}, dialect: {=u16}, code_page: {=u16} }}",
+            self.cue_point_id.get(),
+            self.sample_len.get(),
+            self.purpose[..],
+            self.country.get(),
+            self.language.get(),
+            self.dialect.get(),
+            self.code_page.get(),
+        );
+    }
+}
+
+impl Format for Region {
+    fn format(&self, f: Formatter) {
+        write!(
+            f,
+            "Region {{ cue_point_id: {=u32}, sample_len: {=u32}, purpose: {=[u8]:a}, text: {} }}",
+            self.cue_point_id,
+            self.sample_len,
+            self.purpose[..],
+            self.text,
+        );
+    }
+}
+
+impl Format for MsAdpcmExtension {
+    fn format(&self, f: Formatter) {
+        write!(
+            f,
+            "MsAdpcmExtension {{ cb_size: {=u16}, samples_per_block: {=u16}, num_coef: {=u16} }}",
+            self.cb_size.get(),
+            self.samples_per_block.get(),
+            self.num_coef.get(),
+        );
+    }
+}
+
+impl Format for Ds64Data {
+    fn format(&self, f: Formatter) {
+        write!(
+            f,
+            "Ds64Data {{ riff_size: {=u64}, data_size: {=u64}, sample_count: {=u64}, table_len: {=u32} }}",
+            self.riff_size.get(),
+            self.data_size.get(),
+            self.sample_count.get(),
+            self.table_len.get(),
+        );
+    }
+}
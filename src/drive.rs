@@ -0,0 +1,309 @@
+//! Running a state machine to the end with a closure that does the reads.
+//!
+//! Instead of writing the `read_instruction` / `process_data` loop yourself:
+//! ```ignore
+//! let meta_data = drive::drive(Parser::default(), |read| {
+//!     &file[read.position as usize..][..read.len as usize]
+//! })?;
+//! ```
+//! - [`drive`] is for bytes that are already in memory
+//! - [`try_drive`] is for reads that can fail
+//! - [`try_drive_into`] reads into a buffer, for storage like an SD card. The buffer must be at
+//!   least the `MAX_BUFFER_LEN` of the state machine.
+//!
+//! If the reads can't be done in a closure, for example because they are async, use a [`Stepper`].
+//! It has one method, [`Stepper::step`], which consumes it, so a read can't be given to the wrong
+//! state:
+//! ```ignore
+//! let mut stepper = Stepper::new(Parser::default());
+//! let mut response = None;
+//! let meta_data = loop {
+//!     match stepper.step(response.as_deref())? {
+//!         Step::NeedRead(read, next) => {
+//!             stepper = next;
+//!             response = Some(file.read(read).await?);
+//!         }
+//!         Step::Done(meta_data) => break meta_data,
+//!     }
+//! };
+//! ```
+//!
+//! Errors from the state machine are [`Located`], so you can see where in the file they happened.
+
+use core::convert::Infallible;
+
+use crate::{
+    Error, ErrorLocation, Located, Parser, ProcessDataOutput, ReadInstruction, WavMetaData,
+    analysis::{Analysis, AnalyzeProcessDataOutput, Analyzer},
+    edit::{
+        ChecksumProcessDataOutput, ChunksChecksum, EditError, EditPlan, EditPlanner,
+        EditProcessDataOutput,
+    },
+    md5::{DataMd5, Md5ProcessDataOutput},
+    repair::{Repair, RepairPlan, RepairProcessDataOutput},
+};
+
+pub enum Progress<S, T> {
+    InProgress(S),
+    Done(T),
+}
+
+/// A state machine that reads until it is done. [`StateMachine::process_data`] takes the state
+/// machine by value and moves the output out of it, so the output doesn't have to be [`Copy`] or
+/// [`Clone`].
+pub trait StateMachine: Sized {
+    type Output;
+    type Error;
+
+    fn read_instruction(&self) -> ReadInstruction;
+
+    /// Where an error from the next [`StateMachine::process_data`] happens
+    fn location(&self) -> ErrorLocation {
+        ErrorLocation {
+            position: self.read_instruction().position,
+            chunk_id: None,
+        }
+    }
+
+    fn process_data(self, data: &[u8]) -> Result<Progress<Self, Self::Output>, Self::Error>;
+}
+
+impl StateMachine for Parser {
+    type Output = WavMetaData;
+    type Error = Error;
+
+    fn read_instruction(&self) -> ReadInstruction {
+        self.read_instruction()
+    }
+
+    fn location(&self) -> ErrorLocation {
+        self.location()
+    }
+
+    fn process_data(self, data: &[u8]) -> Result<Progress<Self, Self::Output>, Self::Error> {
+        // The same location is added back from `StateMachine::location`
+        Ok(
+            match self.process_data(data).map_err(|located| located.error)? {
+                ProcessDataOutput::InProgress(parser) => Progress::InProgress(parser),
+                ProcessDataOutput::Done(meta_data) => Progress::Done(meta_data),
+            },
+        )
+    }
+}
+
+impl StateMachine for Repair {
+    type Output = RepairPlan;
+    type Error = Error;
+
+    fn read_instruction(&self) -> ReadInstruction {
+        self.read_instruction()
+    }
+
+    fn process_data(self, data: &[u8]) -> Result<Progress<Self, Self::Output>, Self::Error> {
+        Ok(match self.process_data(data)? {
+            RepairProcessDataOutput::InProgress(repair) => Progress::InProgress(repair),
+            RepairProcessDataOutput::Done(plan) => Progress::Done(plan),
+        })
+    }
+}
+
+impl StateMachine for EditPlanner {
+    type Output = EditPlan;
+    type Error = EditError;
+
+    fn read_instruction(&self) -> ReadInstruction {
+        self.read_instruction()
+    }
+
+    fn process_data(self, data: &[u8]) -> Result<Progress<Self, Self::Output>, Self::Error> {
+        Ok(match self.process_data(data)? {
+            EditProcessDataOutput::InProgress(planner) => Progress::InProgress(planner),
+            EditProcessDataOutput::Done(plan) => Progress::Done(plan),
+        })
+    }
+}
+
+impl StateMachine for ChunksChecksum {
+    type Output = u64;
+    type Error = Error;
+
+    fn read_instruction(&self) -> ReadInstruction {
+        self.read_instruction()
+    }
+
+    fn process_data(self, data: &[u8]) -> Result<Progress<Self, Self::Output>, Self::Error> {
+        Ok(match self.process_data(data)? {
+            ChecksumProcessDataOutput::InProgress(checksum) => Progress::InProgress(checksum),
+            ChecksumProcessDataOutput::Done(checksum) => Progress::Done(checksum),
+        })
+    }
+}
+
+impl<A: Analysis> StateMachine for Analyzer<A> {
+    type Output = A;
+    type Error = Error;
+
+    fn read_instruction(&self) -> ReadInstruction {
+        self.read_instruction()
+    }
+
+    fn location(&self) -> ErrorLocation {
+        ErrorLocation {
+            position: self.read_instruction().position,
+            chunk_id: Some(*b"data"),
+        }
+    }
+
+    fn process_data(self, data: &[u8]) -> Result<Progress<Self, Self::Output>, Self::Error> {
+        Ok(match self.process_data(data)? {
+            AnalyzeProcessDataOutput::InProgress(analyzer) => Progress::InProgress(analyzer),
+            AnalyzeProcessDataOutput::Done(analysis) => Progress::Done(analysis),
+        })
+    }
+}
+
+impl StateMachine for DataMd5 {
+    type Output = [u8; 16];
+    type Error = Error;
+
+    fn read_instruction(&self) -> ReadInstruction {
+        self.read_instruction()
+    }
+
+    fn location(&self) -> ErrorLocation {
+        ErrorLocation {
+            position: self.read_instruction().position,
+            chunk_id: Some(*b"data"),
+        }
+    }
+
+    fn process_data(self, data: &[u8]) -> Result<Progress<Self, Self::Output>, Self::Error> {
+        Ok(match self.process_data(data)? {
+            Md5ProcessDataOutput::InProgress(md5) => Progress::InProgress(md5),
+            Md5ProcessDataOutput::Done(digest) => Progress::Done(digest),
+        })
+    }
+}
+
+/// The read failed, or the state machine returned an error
+#[derive(Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum DriveError<E, R> {
+    Read(R),
+    Process(Located<E>),
+}
+
+impl<E: core::fmt::Display, R: core::fmt::Display> core::fmt::Display for DriveError<E, R> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Read(error) => write!(f, "read error: {error}"),
+            Self::Process(error) => write!(f, "{error}"),
+        }
+    }
+}
+
+impl<E: core::error::Error + 'static, R: core::error::Error + 'static> core::error::Error
+    for DriveError<E, R>
+{
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        match self {
+            Self::Read(error) => Some(error),
+            Self::Process(error) => Some(error),
+        }
+    }
+}
+
+/// What a [`Stepper`] needs next
+#[derive(Debug)]
+pub enum Step<S: StateMachine> {
+    /// Call [`Stepper::step`] on the returned stepper with the bytes of this read
+    NeedRead(ReadInstruction, Stepper<S>),
+    Done(S::Output),
+}
+
+/// Runs a state machine one read at a time
+#[derive(Debug)]
+pub struct Stepper<S> {
+    state_machine: S,
+}
+
+impl<S: StateMachine> Stepper<S> {
+    pub fn new(state_machine: S) -> Self {
+        Self { state_machine }
+    }
+
+    /// Call this with [`None`] the first time, and then with the bytes of the read from the last
+    /// [`Step::NeedRead`]. Calling it with [`None`] again asks for the same read again.
+    ///
+    /// The stepper is consumed, so it can't be stepped again after it is done or returned an error.
+    pub fn step(self, response: Option<&[u8]>) -> Result<Step<S>, Located<S::Error>> {
+        let Some(data) = response else {
+            let read = self.state_machine.read_instruction();
+            return Ok(Step::NeedRead(read, self));
+        };
+        let location = self.state_machine.location();
+        match self
+            .state_machine
+            .process_data(data)
+            .map_err(|error| Located { error, location })?
+        {
+            Progress::InProgress(state_machine) => {
+                let read = state_machine.read_instruction();
+                Ok(Step::NeedRead(read, Self { state_machine }))
+            }
+            Progress::Done(output) => Ok(Step::Done(output)),
+        }
+    }
+}
+
+/// `read` returns the bytes of a [`ReadInstruction`]
+pub fn drive<'a, S: StateMachine>(
+    state_machine: S,
+    mut read: impl FnMut(ReadInstruction) -> &'a [u8],
+) -> Result<S::Output, Located<S::Error>> {
+    try_drive(state_machine, |read_instruction| {
+        Ok::<_, Infallible>(read(read_instruction))
+    })
+    .map_err(|e| match e {
+        DriveError::Process(e) => e,
+    })
+}
+
+/// Like [`drive`], but `read` can fail
+pub fn try_drive<'a, S: StateMachine, R>(
+    mut state_machine: S,
+    mut read: impl FnMut(ReadInstruction) -> Result<&'a [u8], R>,
+) -> Result<S::Output, DriveError<S::Error, R>> {
+    loop {
+        let data = read(state_machine.read_instruction()).map_err(DriveError::Read)?;
+        let location = state_machine.location();
+        match state_machine
+            .process_data(data)
+            .map_err(|error| DriveError::Process(Located { error, location }))?
+        {
+            Progress::InProgress(next) => state_machine = next,
+            Progress::Done(output) => return Ok(output),
+        }
+    }
+}
+
+/// Like [`try_drive`], but `read` fills `buffer` with the bytes starting at a position
+pub fn try_drive_into<S: StateMachine, R>(
+    mut state_machine: S,
+    buffer: &mut [u8],
+    mut read: impl FnMut(u64, &mut [u8]) -> Result<(), R>,
+) -> Result<S::Output, DriveError<S::Error, R>> {
+    loop {
+        let ReadInstruction { position, len } = state_machine.read_instruction();
+        let buffer = &mut buffer[..len.try_into().unwrap()];
+        read(position, buffer).map_err(DriveError::Read)?;
+        let location = state_machine.location();
+        match state_machine
+            .process_data(buffer)
+            .map_err(|error| DriveError::Process(Located { error, location }))?
+        {
+            Progress::InProgress(next) => state_machine = next,
+            Progress::Done(output) => return Ok(output),
+        }
+    }
+}
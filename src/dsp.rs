@@ -0,0 +1,310 @@
+//! Processing that changes samples on their way to the output.
+//!
+//! Everything here works on interleaved samples of any [`Sample`] type, in place.
+
+use crate::sample::Sample;
+
+/// 1.0 for gains, which are fixed point with 15 fractional bits
+pub const UNITY_GAIN: i32 = 1 << 15;
+
+/// Multiplies a sample by a gain where [`UNITY_GAIN`] is 1.0, with saturation
+fn apply_gain<S: Sample>(sample: S, gain: i32) -> S {
+    let scaled = (i64::from(sample.to_i32()) * i64::from(gain)) >> 15;
+    S::from_i32(scaled.clamp(i32::MIN.into(), i32::MAX.into()) as i32)
+}
+
+/// A volume control. Changing the volume ramps to it over a number of frames, which avoids the
+/// clicks ("zipper noise") that a sudden change would make.
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Gain {
+    start: i32,
+    target: i32,
+    ramp_frames: u32,
+    /// How many frames of the ramp are done. The ramp is done when this is `ramp_frames`.
+    progress: u32,
+}
+
+impl Gain {
+    /// `gain` is fixed point, where [`UNITY_GAIN`] is 1.0
+    pub fn new(gain: i32) -> Self {
+        Self {
+            start: gain,
+            target: gain,
+            ramp_frames: 0,
+            progress: 0,
+        }
+    }
+
+    /// Values that don't fit are saturated
+    pub fn from_f32(gain: f32) -> Self {
+        Self::new(gain_from_f32(gain))
+    }
+
+    /// The gain of the next frame
+    pub fn current(&self) -> i32 {
+        if self.progress >= self.ramp_frames {
+            return self.target;
+        }
+        let difference = i64::from(self.target) - i64::from(self.start);
+        let offset = difference * i64::from(self.progress) / i64::from(self.ramp_frames);
+        // Between `start` and `target`
+        (i64::from(self.start) + offset) as i32
+    }
+
+    pub fn target(&self) -> i32 {
+        self.target
+    }
+
+    pub fn is_ramping(&self) -> bool {
+        self.progress < self.ramp_frames
+    }
+
+    /// Changes the gain linearly from the current gain to `target` over `frames` frames
+    pub fn ramp_to(&mut self, target: i32, frames: u32) {
+        self.start = self.current();
+        self.target = target;
+        self.ramp_frames = frames;
+        self.progress = 0;
+    }
+
+    /// Applies the gain to interleaved frames of `channels` samples
+    pub fn apply<S: Sample>(&mut self, samples: &mut [S], channels: usize) {
+        for frame in samples.chunks_mut(channels.max(1)) {
+            let gain = self.current();
+            for sample in frame {
+                *sample = apply_gain(*sample, gain);
+            }
+            if self.is_ramping() {
+                self.progress += 1;
+            }
+        }
+    }
+}
+
+impl Default for Gain {
+    fn default() -> Self {
+        Self::new(UNITY_GAIN)
+    }
+}
+
+/// Converts a gain to fixed point, saturating values that don't fit
+pub fn gain_from_f32(gain: f32) -> i32 {
+    // `as` saturates
+    (gain * UNITY_GAIN as f32) as i32
+}
+
+/// The shape of a [`Fade`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum FadeCurve {
+    Linear,
+    /// Starts and ends slowly (smoothstep), which sounds smoother than [`FadeCurve::Linear`]
+    SCurve,
+}
+
+impl FadeCurve {
+    /// The gain at `position` out of `len` frames into a fade in
+    fn gain(&self, position: u32, len: u32) -> i32 {
+        if position >= len {
+            return UNITY_GAIN;
+        }
+        let x = (i64::from(position) << 15) / i64::from(len);
+        let gain = match self {
+            Self::Linear => x,
+            // 3x² - 2x³
+            Self::SCurve => (x * x * (3 * i64::from(UNITY_GAIN) - 2 * x)) >> 30,
+        };
+        gain as i32
+    }
+}
+
+/// Fades in at the start of playback and out at the end, or when playback is stopped.
+///
+/// The fade keeps track of the frame position, so tell it when you seek with [`Fade::seek`].
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Fade {
+    curve: FadeCurve,
+    fade_in_frames: u32,
+    fade_out_frames: u32,
+    /// The number of frames in the file, for fading out at the end
+    total_frames: Option<u32>,
+    position: u32,
+    /// The position where the fade out because of [`Fade::stop`] started
+    stopped_at: Option<u32>,
+}
+
+impl Fade {
+    pub fn new(curve: FadeCurve, fade_in_frames: u32, fade_out_frames: u32) -> Self {
+        Self {
+            curve,
+            fade_in_frames,
+            fade_out_frames,
+            total_frames: None,
+            position: 0,
+            stopped_at: None,
+        }
+    }
+
+    /// Fade out before this many frames
+    pub fn set_total_frames(&mut self, total_frames: u32) {
+        self.total_frames = Some(total_frames);
+    }
+
+    /// Starts fading out now, for pausing or stopping. The samples are silent after the fade out.
+    pub fn stop(&mut self) {
+        if self.stopped_at.is_none() {
+            self.stopped_at = Some(self.position);
+        }
+    }
+
+    /// Sets the frame position, and fades in again from the start of the fade in if `position` is
+    /// inside it. Clears [`Fade::stop`], so it can be used to resume.
+    pub fn seek(&mut self, position: u32) {
+        self.position = position;
+        self.stopped_at = None;
+    }
+
+    /// The frame position of the next frame
+    pub fn position(&self) -> u32 {
+        self.position
+    }
+
+    /// `true` after the fade out from [`Fade::stop`] is done
+    pub fn is_silent(&self) -> bool {
+        self.stopped_at
+            .is_some_and(|stopped_at| self.position - stopped_at >= self.fade_out_frames)
+    }
+
+    fn gain(&self) -> i32 {
+        let fade_in = self.curve.gain(self.position, self.fade_in_frames);
+        let fade_out_end = self
+            .total_frames
+            .map(|total_frames| {
+                self.curve.gain(
+                    total_frames.saturating_sub(self.position),
+                    self.fade_out_frames,
+                )
+            })
+            .unwrap_or(UNITY_GAIN);
+        let stop = self
+            .stopped_at
+            .map(|stopped_at| {
+                let done = self.position - stopped_at;
+                // Also silent right away if there is no fade out
+                if done >= self.fade_out_frames {
+                    return 0;
+                }
+                self.curve
+                    .gain(self.fade_out_frames - done, self.fade_out_frames)
+            })
+            .unwrap_or(UNITY_GAIN);
+        fade_in.min(fade_out_end).min(stop)
+    }
+
+    /// Applies the fade to interleaved frames of `channels` samples
+    pub fn apply<S: Sample>(&mut self, samples: &mut [S], channels: usize) {
+        for frame in samples.chunks_mut(channels.max(1)) {
+            let gain = self.gain();
+            if gain != UNITY_GAIN {
+                for sample in frame {
+                    *sample = apply_gain(*sample, gain);
+                }
+            }
+            self.position = self.position.saturating_add(1);
+        }
+    }
+}
+
+/// Subtracts a fixed offset from each channel, for example the ones measured with
+/// [`DcOffset`](crate::analysis::DcOffset)
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct OffsetRemover<const CHANNELS: usize> {
+    /// Scaled like signed 32-bit samples
+    pub offsets: [i32; CHANNELS],
+}
+
+impl<const CHANNELS: usize> OffsetRemover<CHANNELS> {
+    /// Applies to interleaved frames of `CHANNELS` samples, with saturation
+    pub fn apply<S: Sample>(&self, samples: &mut [S]) {
+        for frame in samples.chunks_mut(CHANNELS.max(1)) {
+            for (sample, offset) in frame.iter_mut().zip(self.offsets) {
+                *sample = S::from_i32(sample.to_i32().saturating_sub(offset));
+            }
+        }
+    }
+}
+
+/// A first-order high-pass filter that removes DC offset while playing or recording, without
+/// measuring it first. The cutoff is about `(1 - pole) * sample_rate / 2π`.
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct DcBlocker<const CHANNELS: usize> {
+    /// Fixed point where 1.0 is `1 << 15`
+    pole: i32,
+    previous_input: [i64; CHANNELS],
+    previous_output: [i64; CHANNELS],
+}
+
+impl<const CHANNELS: usize> DcBlocker<CHANNELS> {
+    /// `pole` is fixed point where [`UNITY_GAIN`] is 1.0. It should be a bit less than 1.0.
+    pub fn new(pole: i32) -> Self {
+        Self {
+            pole,
+            previous_input: [0; CHANNELS],
+            previous_output: [0; CHANNELS],
+        }
+    }
+
+    /// Applies to interleaved frames of `CHANNELS` samples
+    pub fn apply<S: Sample>(&mut self, samples: &mut [S]) {
+        for frame in samples.chunks_mut(CHANNELS.max(1)) {
+            for (channel, sample) in frame.iter_mut().enumerate() {
+                let input = i64::from(sample.to_i32());
+                // y[n] = x[n] - x[n - 1] + pole * y[n - 1]
+                let output = input - self.previous_input[channel]
+                    + ((i64::from(self.pole) * self.previous_output[channel]) >> 15);
+                let output = output.clamp(i32::MIN.into(), i32::MAX.into());
+                self.previous_input[channel] = input;
+                self.previous_output[channel] = output;
+                *sample = S::from_i32(output as i32);
+            }
+        }
+    }
+}
+
+impl<const CHANNELS: usize> Default for DcBlocker<CHANNELS> {
+    /// A pole of about 0.995, which has a cutoff of about 35 Hz at 44.1 kHz
+    fn default() -> Self {
+        Self::new(32604)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stop_without_fade_out() {
+        let mut fade = Fade::new(FadeCurve::Linear, 0, 0);
+        let mut samples = [1000_i16; 4];
+        fade.apply(&mut samples, 2);
+        assert_eq!(samples, [1000; 4]);
+        fade.stop();
+        assert!(fade.is_silent());
+        fade.apply(&mut samples, 2);
+        assert_eq!(samples, [0; 4]);
+    }
+
+    #[test]
+    fn stop_fades_out() {
+        let mut fade = Fade::new(FadeCurve::Linear, 0, 4);
+        fade.stop();
+        let mut samples = [1000_i16; 5];
+        fade.apply(&mut samples, 1);
+        assert_eq!(samples, [1000, 750, 500, 250, 0]);
+        assert!(fade.is_silent());
+    }
+}
@@ -0,0 +1,579 @@
+//! Changing metadata chunks of an existing file without rewriting the whole file.
+//!
+//! # Usage
+//! Create an [`EditPlanner`] with the chunk you want to write. Drive it like the
+//! [`Parser`](crate::Parser) with [`EditPlanner::read_instruction`] and
+//! [`EditPlanner::process_data`]. It scans the top-level chunks and gives you an [`EditPlan`].
+//! Then call [`EditPlan::write`] and do all the writes.
+//!
+//! To change multiple chunks, plan and write them one at a time.
+//!
+//! The plan is chosen in this order:
+//! 1. Overwrite the existing chunk, if the new chunk fits in it (and the following `JUNK` chunk, if
+//!    there is one). Left over space becomes a `JUNK` chunk.
+//! 2. If there is no existing chunk, overwrite the smallest `JUNK` chunk that the new chunk fits in
+//! 3. If the existing chunk is the last chunk (not counting `JUNK` chunks), overwrite it and
+//!    update the RIFF len, growing (or shrinking) the file
+//! 4. Relocate the chunk: append the new chunk to the end of the file, turn the existing chunk into
+//!    a `JUNK` chunk, and update the RIFF len. This way the edit never fails because the reserved
+//!    space is too small, and no other chunks have to be moved.
+//!
+//! # Round trip mode
+//! In [`EditPlanner::round_trip`] mode, the order of chunks never changes, so an existing chunk is
+//! never moved to the end. All other chunks, except `JUNK` chunks, are never changed.
+//! To verify this, compare the [`ChunksChecksum`] of the file before and after the edit.
+
+use pure_riff::{Id, RiffChunkHeader};
+use zerocopy::FromBytes;
+
+use crate::{
+    Error, ReadInstruction,
+    arithmetic::checked_advance,
+    check_data_len,
+    writer::{
+        WriteChunk, WriteError, WriteInstructions, WriteInstructionsBuilder, chunk_len,
+        write_chunk, write_chunk_header,
+    },
+};
+
+pub const JUNK_ID: Id = *b"JUNK";
+
+const HEADER_LEN: u32 = size_of::<RiffChunkHeader>() as u32;
+const ID_LEN: u32 = size_of::<Id>() as u32;
+
+#[derive(Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum EditError {
+    Parse(Error),
+    /// In round trip mode, the new chunk doesn't fit where the existing chunk is
+    WouldReorderChunks,
+    /// With the new chunk, the file would be bigger than 4 GiB
+    TooLarge,
+}
+
+impl core::fmt::Display for EditError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Parse(error) => write!(f, "parse error: {error}"),
+            Self::WouldReorderChunks => {
+                write!(f, "the new chunk doesn't fit where the existing chunk is")
+            }
+            Self::TooLarge => write!(f, "the file would be too large"),
+        }
+    }
+}
+
+impl core::error::Error for EditError {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        match self {
+            Self::Parse(error) => Some(error),
+            Self::WouldReorderChunks | Self::TooLarge => None,
+        }
+    }
+}
+
+impl From<Error> for EditError {
+    fn from(value: Error) -> Self {
+        Self::Parse(value)
+    }
+}
+
+/// Which chunk to replace
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChunkSelector {
+    pub chunk_id: Id,
+    /// For `LIST` chunks, the list type, such as `INFO`
+    pub list_type: Option<Id>,
+}
+
+/// A chunk that was found while scanning. The len includes the header and padding.
+#[derive(Debug, Clone, Copy)]
+struct FoundChunk {
+    position: u64,
+    len: u64,
+}
+
+enum EditStage {
+    Riff,
+    Header { position: u64 },
+    ListType { position: u64, len: u64 },
+}
+
+pub struct EditPlanner {
+    selector: ChunkSelector,
+    /// The len of the new chunk, including the header and padding
+    new_len: u64,
+    stage: EditStage,
+    riff_end: u64,
+    existing: Option<FoundChunk>,
+    /// The `JUNK` chunk right after the existing chunk
+    junk_after_existing: Option<FoundChunk>,
+    /// The previous chunk was the existing chunk
+    previous_was_existing: bool,
+    /// There are only `JUNK` chunks after the existing chunk
+    existing_is_last: bool,
+    smallest_fitting_junk: Option<FoundChunk>,
+    round_trip: bool,
+}
+
+/// How to write the new chunk
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum EditPlan {
+    /// Write the new chunk at `position`, followed by the header of a `JUNK` chunk that is
+    /// `junk_len` bytes in total (if `junk_len` is not 0)
+    InPlace { position: u64, junk_len: u64 },
+    /// Write the new chunk at `position`, which is the last chunk, and update the RIFF len to
+    /// `riff_len`. If the file shrinks, you can truncate it to `riff_len + 8` bytes.
+    Extend { position: u64, riff_len: u64 },
+    /// Change the id of the chunk at `old_position` to `JUNK`, write the new chunk at `position`
+    /// (the end of the file), and update the RIFF len to `riff_len`
+    Append {
+        old_position: Option<u64>,
+        position: u64,
+        riff_len: u64,
+    },
+}
+
+pub enum EditProcessDataOutput {
+    Done(EditPlan),
+    InProgress(EditPlanner),
+}
+
+/// Space can be used if the chunk fills it completely, or if the rest fits a `JUNK` chunk
+fn fits(space: u64, len: u64) -> bool {
+    space == len || space >= len.saturating_add(HEADER_LEN.into())
+}
+
+fn chunk_overflow(chunk_id: Id, position: u64) -> Error {
+    Error::ChunkSizeOverflow { chunk_id, position }
+}
+
+fn riff_overflow() -> Error {
+    chunk_overflow(*b"RIFF", 0)
+}
+
+/// The position after the chunk at `position`, including the header and padding
+fn chunk_end(position: u64, chunk_len: u32) -> Option<u64> {
+    checked_advance(position, u64::from(HEADER_LEN), false)
+        .and_then(|data_position| checked_advance(data_position, chunk_len.into(), true))
+        .ok()
+}
+
+/// The RIFF len of a file that ends at `end`, if it fits in the `RIFF` header
+fn riff_len(end: u64) -> Result<u64, EditError> {
+    end.checked_sub(HEADER_LEN.into())
+        .filter(|&riff_len| riff_len <= u32::MAX.into())
+        .ok_or(EditError::TooLarge)
+}
+
+impl EditPlanner {
+    pub const MAX_BUFFER_LEN: usize = size_of::<RiffChunkHeader>() + size_of::<Id>();
+
+    pub fn new(selector: ChunkSelector, new_chunk: &impl WriteChunk) -> Self {
+        Self {
+            selector,
+            new_len: chunk_len(new_chunk).try_into().unwrap_or(u64::MAX),
+            stage: EditStage::Riff,
+            riff_end: 0,
+            existing: None,
+            junk_after_existing: None,
+            previous_was_existing: false,
+            existing_is_last: false,
+            smallest_fitting_junk: None,
+            round_trip: false,
+        }
+    }
+
+    /// Never move an existing chunk. See the [module docs](self).
+    pub fn round_trip(mut self) -> Self {
+        self.round_trip = true;
+        self
+    }
+
+    pub fn read_instruction(&self) -> ReadInstruction {
+        match self.stage {
+            EditStage::Riff => ReadInstruction {
+                position: 0,
+                len: Self::MAX_BUFFER_LEN.try_into().unwrap(),
+            },
+            EditStage::Header { position } => ReadInstruction {
+                position,
+                len: HEADER_LEN,
+            },
+            EditStage::ListType { position, .. } => ReadInstruction {
+                position: position + u64::from(HEADER_LEN),
+                len: ID_LEN,
+            },
+        }
+    }
+
+    pub fn process_data(mut self, data: &[u8]) -> Result<EditProcessDataOutput, EditError> {
+        check_data_len(self.read_instruction().len, data).map_err(EditError::Parse)?;
+        match self.stage {
+            EditStage::Riff => {
+                let riff_chunk =
+                    RiffChunkHeader::read_from_bytes(&data[..size_of::<RiffChunkHeader>()])
+                        .unwrap();
+                if &riff_chunk.chunk_id != b"RIFF" {
+                    return Err(Error::UnexpectedChunkId(riff_chunk.chunk_id).into());
+                }
+                let container_id = Id::try_from(&data[size_of::<RiffChunkHeader>()..]).unwrap();
+                if &container_id != b"WAVE" {
+                    return Err(Error::UnexpectedContainerId(container_id).into());
+                }
+                self.riff_end =
+                    chunk_end(0, riff_chunk.chunk_len.get()).ok_or_else(riff_overflow)?;
+                self.next_or_done(Self::MAX_BUFFER_LEN.try_into().unwrap())
+            }
+            EditStage::Header { position } => {
+                let header = RiffChunkHeader::read_from_bytes(data).unwrap();
+                let len = chunk_end(0, header.chunk_len.get())
+                    .ok_or_else(|| chunk_overflow(header.chunk_id, position))?;
+                if header.chunk_id == self.selector.chunk_id {
+                    if self.selector.list_type.is_some() && header.chunk_len.get() >= 4 {
+                        self.stage = EditStage::ListType { position, len };
+                        return Ok(EditProcessDataOutput::InProgress(self));
+                    } else if self.selector.list_type.is_none() {
+                        self.found_existing(position, len);
+                        return self.next(header.chunk_id, position, len);
+                    }
+                }
+                self.found_other(header.chunk_id, position, len);
+                self.next(header.chunk_id, position, len)
+            }
+            EditStage::ListType { position, len } => {
+                if Some(Id::try_from(data).unwrap()) == self.selector.list_type {
+                    self.found_existing(position, len);
+                } else {
+                    self.found_other(self.selector.chunk_id, position, len);
+                }
+                let chunk_id = self.selector.chunk_id;
+                self.next(chunk_id, position, len)
+            }
+        }
+    }
+
+    fn found_existing(&mut self, position: u64, len: u64) {
+        if self.existing.is_none() {
+            self.existing = Some(FoundChunk { position, len });
+            self.previous_was_existing = true;
+            self.existing_is_last = true;
+        }
+    }
+
+    fn found_other(&mut self, chunk_id: Id, position: u64, len: u64) {
+        let chunk = FoundChunk { position, len };
+        if chunk_id == JUNK_ID {
+            if self.previous_was_existing {
+                self.junk_after_existing = Some(chunk);
+            }
+            if fits(len, self.new_len)
+                && self
+                    .smallest_fitting_junk
+                    .is_none_or(|smallest| len < smallest.len)
+            {
+                self.smallest_fitting_junk = Some(chunk);
+            }
+        } else {
+            self.existing_is_last = false;
+        }
+        self.previous_was_existing = false;
+    }
+
+    /// Go to the chunk after the chunk at `position` with `len`
+    fn next(
+        self,
+        chunk_id: Id,
+        position: u64,
+        len: u64,
+    ) -> Result<EditProcessDataOutput, EditError> {
+        let next_position = checked_advance(position, len, false)
+            .map_err(|_| chunk_overflow(chunk_id, position))?;
+        self.next_or_done(next_position)
+    }
+
+    fn next_or_done(mut self, position: u64) -> Result<EditProcessDataOutput, EditError> {
+        if position.saturating_add(HEADER_LEN.into()) <= self.riff_end {
+            self.stage = EditStage::Header { position };
+            Ok(EditProcessDataOutput::InProgress(self))
+        } else {
+            Ok(EditProcessDataOutput::Done(self.plan()?))
+        }
+    }
+
+    fn plan(&self) -> Result<EditPlan, EditError> {
+        if let Some(existing) = self.existing {
+            if fits(existing.len, self.new_len) {
+                return Ok(EditPlan::InPlace {
+                    position: existing.position,
+                    junk_len: existing.len - self.new_len,
+                });
+            }
+            if let Some(junk) = self.junk_after_existing {
+                let space = existing.len.saturating_add(junk.len);
+                if fits(space, self.new_len) {
+                    return Ok(EditPlan::InPlace {
+                        position: existing.position,
+                        junk_len: space - self.new_len,
+                    });
+                }
+            }
+        } else if let Some(junk) = self.smallest_fitting_junk {
+            return Ok(EditPlan::InPlace {
+                position: junk.position,
+                junk_len: junk.len - self.new_len,
+            });
+        }
+        if let Some(existing) = self.existing
+            && self.existing_is_last
+        {
+            let end = checked_advance(existing.position, self.new_len, false)
+                .map_err(|_| EditError::TooLarge)?;
+            return Ok(EditPlan::Extend {
+                position: existing.position,
+                riff_len: riff_len(end)?,
+            });
+        }
+        if self.round_trip && self.existing.is_some() {
+            return Err(EditError::WouldReorderChunks);
+        }
+        let end =
+            checked_advance(self.riff_end, self.new_len, false).map_err(|_| EditError::TooLarge)?;
+        Ok(EditPlan::Append {
+            old_position: self.existing.map(|existing| existing.position),
+            position: self.riff_end,
+            riff_len: riff_len(end)?,
+        })
+    }
+}
+
+impl EditPlan {
+    /// A buffer of the new chunk's len plus 16 bytes is enough
+    pub fn write<'a>(
+        &self,
+        chunk: &impl WriteChunk,
+        buffer: &'a mut [u8],
+    ) -> Result<WriteInstructions<'a>, WriteError> {
+        let chunk_len = chunk_len(chunk);
+        let mut instructions = WriteInstructionsBuilder::new(buffer);
+        // The lens in chunk headers are 32 bits
+        let header_len = |len: u64| u32::try_from(len).map_err(|_| WriteError::TooLarge);
+        match *self {
+            EditPlan::InPlace { position, junk_len } => {
+                let junk_header_len = if junk_len == 0 {
+                    0
+                } else {
+                    HEADER_LEN as usize
+                };
+                let junk_data_len = header_len(junk_len.saturating_sub(HEADER_LEN.into()))?;
+                instructions.push_with(position, chunk_len + junk_header_len, |buffer| {
+                    let (chunk_buffer, junk_header) = buffer.split_at_mut(chunk_len);
+                    write_chunk(chunk_buffer, chunk);
+                    if junk_len != 0 {
+                        write_chunk_header(junk_header, JUNK_ID, junk_data_len);
+                    }
+                })?;
+            }
+            EditPlan::Extend { position, riff_len } => {
+                instructions.push_with(position, chunk_len, |buffer| write_chunk(buffer, chunk))?;
+                instructions.push(ID_LEN.into(), &header_len(riff_len)?.to_le_bytes())?;
+            }
+            EditPlan::Append {
+                old_position,
+                position,
+                riff_len,
+            } => {
+                if let Some(old_position) = old_position {
+                    instructions.push(old_position, &JUNK_ID)?;
+                }
+                instructions.push_with(position, chunk_len, |buffer| write_chunk(buffer, chunk))?;
+                instructions.push(ID_LEN.into(), &header_len(riff_len)?.to_le_bytes())?;
+            }
+        }
+        Ok(instructions.build())
+    }
+}
+
+enum ChecksumStage {
+    Riff,
+    Header {
+        position: u64,
+    },
+    ListType {
+        position: u64,
+        header: RiffChunkHeader,
+    },
+    Payload {
+        /// The position of the next byte of the payload to read
+        position: u64,
+        /// The number of bytes left in the payload
+        len: u32,
+        /// The position of the next chunk
+        next_position: u64,
+    },
+}
+
+/// A checksum of all top-level chunks (ids, lens, and payloads) in order, except for `JUNK` chunks
+/// and the chunk matching the [`ChunkSelector`]. Used to check that an edit didn't change any
+/// other chunks.
+///
+/// Drive it like the [`Parser`](crate::Parser). It uses the 64-bit FNV-1a hash.
+pub struct ChunksChecksum {
+    exclude: Option<ChunkSelector>,
+    read_len: u32,
+    stage: ChecksumStage,
+    riff_end: u64,
+    hash: u64,
+}
+
+pub enum ChecksumProcessDataOutput {
+    Done(u64),
+    InProgress(ChunksChecksum),
+}
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+const FNV_PRIME: u64 = 0x100000001b3;
+
+impl ChunksChecksum {
+    /// Payloads are read `read_len` bytes at a time, which must be at least
+    /// [`EditPlanner::MAX_BUFFER_LEN`]
+    pub fn new(exclude: Option<ChunkSelector>, read_len: u32) -> Self {
+        Self {
+            exclude,
+            read_len: read_len.max(EditPlanner::MAX_BUFFER_LEN.try_into().unwrap()),
+            stage: ChecksumStage::Riff,
+            riff_end: 0,
+            hash: FNV_OFFSET_BASIS,
+        }
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        for byte in data {
+            self.hash = (self.hash ^ u64::from(*byte)).wrapping_mul(FNV_PRIME);
+        }
+    }
+
+    pub fn read_instruction(&self) -> ReadInstruction {
+        match self.stage {
+            ChecksumStage::Riff => ReadInstruction {
+                position: 0,
+                len: EditPlanner::MAX_BUFFER_LEN.try_into().unwrap(),
+            },
+            ChecksumStage::Header { position } => ReadInstruction {
+                position,
+                len: HEADER_LEN,
+            },
+            ChecksumStage::ListType { position, .. } => ReadInstruction {
+                position: position + u64::from(HEADER_LEN),
+                len: ID_LEN,
+            },
+            ChecksumStage::Payload { position, len, .. } => ReadInstruction {
+                position,
+                len: len.min(self.read_len),
+            },
+        }
+    }
+
+    pub fn process_data(mut self, data: &[u8]) -> Result<ChecksumProcessDataOutput, Error> {
+        check_data_len(self.read_instruction().len, data)?;
+        match self.stage {
+            ChecksumStage::Riff => {
+                let riff_chunk =
+                    RiffChunkHeader::read_from_bytes(&data[..size_of::<RiffChunkHeader>()])
+                        .unwrap();
+                if &riff_chunk.chunk_id != b"RIFF" {
+                    return Err(Error::UnexpectedChunkId(riff_chunk.chunk_id));
+                }
+                self.riff_end =
+                    chunk_end(0, riff_chunk.chunk_len.get()).ok_or_else(riff_overflow)?;
+                self.next_or_done(EditPlanner::MAX_BUFFER_LEN.try_into().unwrap())
+            }
+            ChecksumStage::Header { position } => {
+                let header = RiffChunkHeader::read_from_bytes(data).unwrap();
+                let next_position = chunk_end(position, header.chunk_len.get())
+                    .ok_or_else(|| chunk_overflow(header.chunk_id, position))?;
+                match self.exclude {
+                    _ if header.chunk_id == JUNK_ID => self.next_or_done(next_position),
+                    Some(ChunkSelector {
+                        chunk_id,
+                        list_type: None,
+                    }) if chunk_id == header.chunk_id => self.next_or_done(next_position),
+                    Some(ChunkSelector {
+                        chunk_id,
+                        list_type: Some(_),
+                    }) if chunk_id == header.chunk_id && header.chunk_len.get() >= 4 => {
+                        self.stage = ChecksumStage::ListType { position, header };
+                        Ok(ChecksumProcessDataOutput::InProgress(self))
+                    }
+                    _ => {
+                        self.update(data);
+                        self.payload(
+                            position + u64::from(HEADER_LEN),
+                            header.chunk_len.get(),
+                            next_position,
+                        )
+                    }
+                }
+            }
+            ChecksumStage::ListType { position, header } => {
+                let next_position = chunk_end(position, header.chunk_len.get())
+                    .ok_or_else(|| chunk_overflow(header.chunk_id, position))?;
+                if self.exclude.and_then(|exclude| exclude.list_type)
+                    == Some(Id::try_from(data).unwrap())
+                {
+                    self.next_or_done(next_position)
+                } else {
+                    self.update(&header.chunk_id);
+                    self.update(&header.chunk_len.get().to_le_bytes());
+                    self.update(data);
+                    self.payload(
+                        position + u64::from(HEADER_LEN + ID_LEN),
+                        header.chunk_len.get() - ID_LEN,
+                        next_position,
+                    )
+                }
+            }
+            ChecksumStage::Payload {
+                position,
+                len,
+                next_position,
+            } => {
+                self.update(data);
+                let read_len = u32::try_from(data.len()).unwrap();
+                self.payload(
+                    position + u64::from(read_len),
+                    len - read_len,
+                    next_position,
+                )
+            }
+        }
+    }
+
+    fn payload(
+        mut self,
+        position: u64,
+        len: u32,
+        next_position: u64,
+    ) -> Result<ChecksumProcessDataOutput, Error> {
+        if len == 0 {
+            self.next_or_done(next_position)
+        } else {
+            self.stage = ChecksumStage::Payload {
+                position,
+                len,
+                next_position,
+            };
+            Ok(ChecksumProcessDataOutput::InProgress(self))
+        }
+    }
+
+    fn next_or_done(mut self, position: u64) -> Result<ChecksumProcessDataOutput, Error> {
+        if position.saturating_add(HEADER_LEN.into()) <= self.riff_end {
+            self.stage = ChecksumStage::Header { position };
+            Ok(ChecksumProcessDataOutput::InProgress(self))
+        } else {
+            Ok(ChecksumProcessDataOutput::Done(self.hash))
+        }
+    }
+}
@@ -0,0 +1,19 @@
+//! The `fact` chunk, which has the number of frames of a compressed format.
+//!
+//! The [`Parser`](crate::Parser) reads it if it is before the `data` chunk, and puts it in
+//! [`WavMetaData::fact_sample_length`](crate::WavMetaData::fact_sample_length).
+
+use pure_riff::Id;
+use zerocopy::{FromBytes, Immutable, IntoBytes, KnownLayout, little_endian::U32};
+
+pub const FACT_ID: Id = *b"fact";
+
+/// The data of a `fact` chunk. Some formats have more fields after this.
+#[derive(Debug, Clone, Copy, FromBytes, IntoBytes, Immutable, KnownLayout)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[repr(C)]
+pub struct FactData {
+    /// The number of frames
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_le::u32"))]
+    pub dw_sample_length: U32,
+}
@@ -0,0 +1,320 @@
+//! Things related to the `fmt ` chunk.
+//!
+//! See <https://www.mmsp.ece.mcgill.ca/Documents/AudioFormats/WAVE/WAVE.html> for the layout of
+//! the chunk.
+
+use zerocopy::{
+    FromBytes, Immutable, IntoBytes, KnownLayout,
+    little_endian::{U16, U32},
+};
+
+use crate::FmtData;
+
+pub const WAVE_FORMAT_PCM: u16 = 0x0001;
+pub const WAVE_FORMAT_IEEE_FLOAT: u16 = 0x0003;
+pub const WAVE_FORMAT_ALAW: u16 = 0x0006;
+pub const WAVE_FORMAT_MULAW: u16 = 0x0007;
+pub const WAVE_FORMAT_EXTENSIBLE: u16 = 0xFFFE;
+
+/// The last 14 bytes of the `KSDATAFORMAT_SUBTYPE_*` GUIDs. The first 2 bytes are the format tag,
+/// followed by 2 zero bytes.
+pub const SUB_FORMAT_GUID_SUFFIX: [u8; 12] = [
+    0x00, 0x00, 0x10, 0x00, 0x80, 0x00, 0x00, 0xAA, 0x00, 0x38, 0x9B, 0x71,
+];
+
+/// The fields after [`FmtData`] in the 40-byte `WAVE_FORMAT_EXTENSIBLE` version of the `fmt `
+/// chunk.
+#[derive(Debug, Clone, Copy, FromBytes, IntoBytes, Immutable, KnownLayout)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[repr(C)]
+pub struct FmtExtension {
+    /// The number of bytes after this field. Should be 22.
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_le::u16"))]
+    pub cb_size: U16,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_le::u16"))]
+    pub w_valid_bits_per_sample: U16,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_le::u32"))]
+    pub dw_channel_mask: U32,
+    pub sub_format: [u8; 16],
+}
+
+impl FmtExtension {
+    /// The format tag of the sub format, if the sub format is one of the standard
+    /// `KSDATAFORMAT_SUBTYPE_*` GUIDs.
+    pub fn sub_format_tag(&self) -> Option<u16> {
+        if self.sub_format[2..4] == [0, 0] && self.sub_format[4..] == SUB_FORMAT_GUID_SUFFIX {
+            Some(u16::from_le_bytes([self.sub_format[0], self.sub_format[1]]))
+        } else {
+            None
+        }
+    }
+}
+
+/// Sample encodings that can be described with the `fmt ` chunk.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Codec {
+    Pcm,
+    IeeeFloat,
+    ALaw,
+    MuLaw,
+}
+
+impl Codec {
+    pub fn format_tag(&self) -> u16 {
+        match self {
+            Self::Pcm => WAVE_FORMAT_PCM,
+            Self::IeeeFloat => WAVE_FORMAT_IEEE_FLOAT,
+            Self::ALaw => WAVE_FORMAT_ALAW,
+            Self::MuLaw => WAVE_FORMAT_MULAW,
+        }
+    }
+
+    pub fn from_format_tag(format_tag: u16) -> Option<Self> {
+        match format_tag {
+            WAVE_FORMAT_PCM => Some(Self::Pcm),
+            WAVE_FORMAT_IEEE_FLOAT => Some(Self::IeeeFloat),
+            WAVE_FORMAT_ALAW => Some(Self::ALaw),
+            WAVE_FORMAT_MULAW => Some(Self::MuLaw),
+            _ => None,
+        }
+    }
+}
+
+/// A complete `fmt ` chunk.
+#[derive(Debug, Clone, Copy)]
+pub struct Fmt {
+    pub base: FmtData,
+    /// Present if this is the `WAVE_FORMAT_EXTENSIBLE` version of the chunk
+    pub extension: Option<FmtExtension>,
+}
+
+impl Fmt {
+    /// The length of the data of the `fmt ` chunk
+    pub fn chunk_len(&self) -> u32 {
+        let len = size_of::<FmtData>()
+            + match self.extension {
+                Some(_) => size_of::<FmtExtension>(),
+                None => 0,
+            };
+        len.try_into().unwrap()
+    }
+}
+
+/// A field of [`FmtData`] doesn't make sense, or doesn't match the other fields
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum FmtError {
+    ZeroChannels,
+    ZeroSampleRate,
+    /// The format tag doesn't support this number of bits per sample
+    UnsupportedBitsPerSample {
+        format_tag: u16,
+        bits_per_sample: u16,
+    },
+    /// The number of bits set in the channel mask is more than the number of channels
+    ChannelMaskMismatch,
+    /// `n_block_align` or `n_avg_bytes_per_sec` doesn't fit in its field
+    TooManyBytesPerSecond,
+    /// `n_block_align` isn't the number of channels times the bytes per sample
+    BlockAlignMismatch {
+        expected: u32,
+        actual: u16,
+    },
+    /// `n_avg_bytes_per_sec` isn't the sample rate times `n_block_align`
+    AvgBytesPerSecMismatch {
+        expected: u64,
+        actual: u32,
+    },
+}
+
+impl core::fmt::Display for FmtError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::ZeroChannels => write!(f, "there are 0 channels"),
+            Self::ZeroSampleRate => write!(f, "the sample rate is 0"),
+            Self::UnsupportedBitsPerSample {
+                format_tag,
+                bits_per_sample,
+            } => write!(
+                f,
+                "format tag {format_tag:#06x} doesn't support {bits_per_sample} bits per sample"
+            ),
+            Self::ChannelMaskMismatch => {
+                write!(
+                    f,
+                    "the channel mask has more channels than the number of channels"
+                )
+            }
+            Self::TooManyBytesPerSecond => write!(f, "there are too many bytes per second"),
+            Self::BlockAlignMismatch { expected, actual } => {
+                write!(f, "the block align is {actual}, expected {expected}")
+            }
+            Self::AvgBytesPerSecMismatch { expected, actual } => {
+                write!(f, "the bytes per second is {actual}, expected {expected}")
+            }
+        }
+    }
+}
+
+impl core::error::Error for FmtError {}
+
+/// Whether the format supports this number of bits per sample, or [`None`] if it is a compressed
+/// format with its own rules
+fn supports_bits_per_sample(format_tag: u16, bits_per_sample: u16) -> Option<bool> {
+    match format_tag {
+        WAVE_FORMAT_PCM => Some((1..=32).contains(&bits_per_sample)),
+        WAVE_FORMAT_IEEE_FLOAT => Some(matches!(bits_per_sample, 32 | 64)),
+        WAVE_FORMAT_ALAW | WAVE_FORMAT_MULAW => Some(bits_per_sample == 8),
+        // The container size, which is always whole bytes
+        WAVE_FORMAT_EXTENSIBLE => {
+            Some((8..=64).contains(&bits_per_sample) && bits_per_sample.is_multiple_of(8))
+        }
+        _ => None,
+    }
+}
+
+impl FmtData {
+    /// Checks that the fields make sense. The bits per sample, block align and bytes per second
+    /// are only checked for PCM, float, A-law, μ-law and `WAVE_FORMAT_EXTENSIBLE`, because
+    /// compressed formats have their own rules.
+    pub fn validate(&self) -> Result<(), FmtError> {
+        let n_channels = self.n_channels.get();
+        if n_channels == 0 {
+            return Err(FmtError::ZeroChannels);
+        }
+        let n_samples_per_sec = self.n_samples_per_sec.get();
+        if n_samples_per_sec == 0 {
+            return Err(FmtError::ZeroSampleRate);
+        }
+        let format_tag = self.format_tag.get();
+        let bits_per_sample = self.w_bits_per_sample.get();
+        let Some(supported) = supports_bits_per_sample(format_tag, bits_per_sample) else {
+            return Ok(());
+        };
+        if !supported {
+            return Err(FmtError::UnsupportedBitsPerSample {
+                format_tag,
+                bits_per_sample,
+            });
+        }
+        let n_block_align = self.n_block_align.get();
+        let expected = u32::from(n_channels) * u32::from(bits_per_sample.div_ceil(8));
+        if u32::from(n_block_align) != expected {
+            return Err(FmtError::BlockAlignMismatch {
+                expected,
+                actual: n_block_align,
+            });
+        }
+        let n_avg_bytes_per_sec = self.n_avg_bytes_per_sec.get();
+        let expected = u64::from(n_samples_per_sec) * u64::from(n_block_align);
+        if u64::from(n_avg_bytes_per_sec) != expected {
+            return Err(FmtError::AvgBytesPerSecMismatch {
+                expected,
+                actual: n_avg_bytes_per_sec,
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Builds a [`Fmt`], calculating the redundant fields and deciding whether the
+/// `WAVE_FORMAT_EXTENSIBLE` version is needed.
+#[derive(Debug, Clone)]
+pub struct FmtDataBuilder {
+    codec: Codec,
+    sample_rate: u32,
+    channels: u16,
+    bits_per_sample: u16,
+    channel_mask: Option<u32>,
+    force_extensible: bool,
+}
+
+impl FmtDataBuilder {
+    /// For PCM, `bits_per_sample` is the number of valid bits. If it isn't a multiple of 8, samples
+    /// are stored in the next multiple of 8.
+    pub fn new(codec: Codec, sample_rate: u32, channels: u16, bits_per_sample: u16) -> Self {
+        Self {
+            codec,
+            sample_rate,
+            channels,
+            bits_per_sample,
+            channel_mask: None,
+            force_extensible: false,
+        }
+    }
+
+    /// Set the speaker positions of the channels. This makes the format extensible.
+    pub fn channel_mask(mut self, channel_mask: u32) -> Self {
+        self.channel_mask = Some(channel_mask);
+        self
+    }
+
+    /// Use the extensible form even if it isn't needed
+    pub fn force_extensible(mut self) -> Self {
+        self.force_extensible = true;
+        self
+    }
+
+    pub fn build(&self) -> Result<Fmt, FmtError> {
+        let format_tag = self.codec.format_tag();
+        // The format tag of the built fmt can be `WAVE_FORMAT_EXTENSIBLE`, which is checked less
+        if supports_bits_per_sample(format_tag, self.bits_per_sample) != Some(true) {
+            return Err(FmtError::UnsupportedBitsPerSample {
+                format_tag,
+                bits_per_sample: self.bits_per_sample,
+            });
+        }
+        if let Some(channel_mask) = self.channel_mask
+            && channel_mask.count_ones() > u32::from(self.channels)
+        {
+            return Err(FmtError::ChannelMaskMismatch);
+        }
+        let container_bits = self.bits_per_sample.next_multiple_of(8);
+        let n_block_align = (container_bits / 8)
+            .checked_mul(self.channels)
+            .ok_or(FmtError::TooManyBytesPerSecond)?;
+        let n_avg_bytes_per_sec = self
+            .sample_rate
+            .checked_mul(n_block_align.into())
+            .ok_or(FmtError::TooManyBytesPerSecond)?;
+        let extensible = self.force_extensible
+            || self.channel_mask.is_some()
+            || self.channels > 2
+            // Only PCM needs the extensible form for more than 16 bits
+            || (self.codec == Codec::Pcm && container_bits > 16)
+            || container_bits != self.bits_per_sample;
+        let fmt = Fmt {
+            base: FmtData {
+                format_tag: if extensible {
+                    WAVE_FORMAT_EXTENSIBLE
+                } else {
+                    format_tag
+                }
+                .into(),
+                n_channels: self.channels.into(),
+                n_samples_per_sec: self.sample_rate.into(),
+                n_avg_bytes_per_sec: n_avg_bytes_per_sec.into(),
+                n_block_align: n_block_align.into(),
+                w_bits_per_sample: container_bits.into(),
+            },
+            extension: extensible.then(|| {
+                let mut sub_format = [0; 16];
+                sub_format[..2].copy_from_slice(&format_tag.to_le_bytes());
+                sub_format[4..].copy_from_slice(&SUB_FORMAT_GUID_SUFFIX);
+                FmtExtension {
+                    cb_size: u16::try_from(size_of::<FmtExtension>() - size_of::<U16>())
+                        .unwrap()
+                        .into(),
+                    w_valid_bits_per_sample: self.bits_per_sample.into(),
+                    dw_channel_mask: self.channel_mask.unwrap_or_default().into(),
+                    sub_format,
+                }
+            }),
+        };
+        fmt.base.validate()?;
+        Ok(fmt)
+    }
+}
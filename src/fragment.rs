@@ -0,0 +1,53 @@
+//! Reads that arrive in pieces, for example over a network or a UART.
+//!
+//! The state machines want all of the bytes of a [`ReadInstruction`] at once. [`FragmentedRead`]
+//! collects the pieces until the read is complete.
+
+use crate::{ReadInstruction, SMALL_BUFFER_LEN};
+
+/// Collects the bytes of one [`ReadInstruction`]. `N` is the biggest read it can hold, which is
+/// enough for every state machine's metadata reads by default.
+#[derive(Debug, Clone)]
+pub struct FragmentedRead<const N: usize = SMALL_BUFFER_LEN> {
+    position: u64,
+    buffer: [u8; N],
+    expected: usize,
+    len: usize,
+}
+
+impl<const N: usize> FragmentedRead<N> {
+    /// Returns [`None`] if the read is longer than `N`
+    pub fn new(read_instruction: &ReadInstruction) -> Option<Self> {
+        let expected = usize::try_from(read_instruction.len).ok()?;
+        (expected <= N).then_some(Self {
+            position: read_instruction.position,
+            buffer: [0; N],
+            expected,
+            len: 0,
+        })
+    }
+
+    /// The position in the file of the next byte that is needed
+    pub fn position(&self) -> u64 {
+        self.position + u64::try_from(self.len).unwrap()
+    }
+
+    /// The number of bytes that are still needed
+    pub fn outstanding(&self) -> u32 {
+        (self.expected - self.len).try_into().unwrap()
+    }
+
+    /// Adds the next piece. Returns the number of bytes that were used, which is less than the len
+    /// of `data` if it goes past the end of the read.
+    pub fn push(&mut self, data: &[u8]) -> usize {
+        let used = data.len().min(self.expected - self.len);
+        self.buffer[self.len..][..used].copy_from_slice(&data[..used]);
+        self.len += used;
+        used
+    }
+
+    /// All of the bytes of the read, once every piece was added. Pass them to `process_data`.
+    pub fn data(&self) -> Option<&[u8]> {
+        (self.len == self.expected).then(|| &self.buffer[..self.len])
+    }
+}
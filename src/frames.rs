@@ -0,0 +1,160 @@
+//! Going through the frames in a part of the `data` chunk without doing offset math.
+//!
+//! If the sample type and the number of channels are known at compile time, use [`Frames`], which
+//! gives you `&[S; CHANNELS]`. Use the little-endian types from [`zerocopy::little_endian`] as
+//! `S`, because the samples are little-endian and might not be aligned. Otherwise, use
+//! [`DynFrames`].
+//!
+//! A partial frame at the end of the data is not returned. Use `remainder` to get it, so you can
+//! put it in front of the next data that you read.
+
+use zerocopy::{FromBytes, Immutable, KnownLayout, Unaligned};
+
+use crate::{WavMetaData, convert::SampleFormat};
+
+/// An iterator over frames of `CHANNELS` samples of type `S`
+#[derive(Debug, Clone)]
+pub struct Frames<'a, S, const CHANNELS: usize> {
+    frames: core::slice::Iter<'a, [S; CHANNELS]>,
+    remainder: &'a [u8],
+}
+
+impl<'a, S: FromBytes + Immutable + KnownLayout + Unaligned, const CHANNELS: usize>
+    Frames<'a, S, CHANNELS>
+{
+    /// Returns [`None`] if the size of a frame doesn't match the block align, or the size of `S`
+    /// doesn't match the bits per sample
+    pub fn new(meta_data: &WavMetaData, data: &'a [u8]) -> Option<Self> {
+        let bits_per_sample = usize::from(meta_data.fmt.w_bits_per_sample.get());
+        if usize::from(meta_data.fmt.n_channels.get()) != CHANNELS
+            || usize::from(meta_data.fmt.n_block_align.get()) != size_of::<[S; CHANNELS]>()
+            || bits_per_sample.div_ceil(8) != size_of::<S>()
+        {
+            return None;
+        }
+        Some(Self::new_unchecked(data))
+    }
+
+    /// Doesn't check that the frames match the format of the WAVE
+    pub fn new_unchecked(data: &'a [u8]) -> Self {
+        let len = data.len() / size_of::<[S; CHANNELS]>().max(1);
+        let (frames, remainder) = <[[S; CHANNELS]]>::ref_from_prefix_with_elems(data, len).unwrap();
+        Self {
+            frames: frames.iter(),
+            remainder,
+        }
+    }
+
+    /// The bytes after the last whole frame
+    pub fn remainder(&self) -> &'a [u8] {
+        self.remainder
+    }
+}
+
+impl<'a, S, const CHANNELS: usize> Iterator for Frames<'a, S, CHANNELS> {
+    type Item = &'a [S; CHANNELS];
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.frames.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.frames.size_hint()
+    }
+}
+
+impl<S, const CHANNELS: usize> ExactSizeIterator for Frames<'_, S, CHANNELS> {}
+
+/// A frame from [`DynFrames`]
+#[derive(Debug, Clone, Copy)]
+pub struct Frame<'a> {
+    bytes: &'a [u8],
+    format: SampleFormat,
+}
+
+impl<'a> Frame<'a> {
+    pub fn bytes(&self) -> &'a [u8] {
+        self.bytes
+    }
+
+    pub fn channels(&self) -> usize {
+        self.bytes.len() / self.format.bytes_per_sample()
+    }
+
+    /// The sample of a channel, scaled to a signed 32-bit sample
+    pub fn sample(&self, channel: usize) -> Option<i32> {
+        let bytes_per_sample = self.format.bytes_per_sample();
+        let bytes = self
+            .bytes
+            .get(channel.checked_mul(bytes_per_sample)?..)?
+            .get(..bytes_per_sample)?;
+        Some(self.format.read(bytes))
+    }
+
+    /// `true` if a sample from [`Frame::sample`] is at the positive or negative full scale of
+    /// the format
+    pub fn is_full_scale(&self, sample: i32) -> bool {
+        sample == i32::MIN || sample >= self.format.full_scale()
+    }
+
+    pub fn samples(&self) -> impl Iterator<Item = i32> + 'a {
+        let format = self.format;
+        self.bytes
+            .chunks_exact(format.bytes_per_sample())
+            .map(move |bytes| format.read(bytes))
+    }
+}
+
+/// An iterator over frames, with the number of channels and the sample format from the
+/// [`WavMetaData`]
+#[derive(Debug, Clone)]
+pub struct DynFrames<'a> {
+    frames: core::slice::ChunksExact<'a, u8>,
+    format: SampleFormat,
+}
+
+impl<'a> DynFrames<'a> {
+    /// Returns [`None`] if the sample format is not supported, or the block align doesn't match the
+    /// number of channels
+    pub fn new(meta_data: &WavMetaData, data: &'a [u8]) -> Option<Self> {
+        let format = SampleFormat::from_meta_data(meta_data)?;
+        let block_align = usize::from(meta_data.fmt.n_block_align.get());
+        if block_align == 0
+            || block_align
+                != usize::from(meta_data.fmt.n_channels.get()) * format.bytes_per_sample()
+        {
+            return None;
+        }
+        Some(Self::from_parts(data, format, block_align))
+    }
+
+    /// `block_align` must be a multiple of the bytes per sample of `format`
+    pub(crate) fn from_parts(data: &'a [u8], format: SampleFormat, block_align: usize) -> Self {
+        Self {
+            frames: data.chunks_exact(block_align),
+            format,
+        }
+    }
+
+    /// The bytes after the last whole frame
+    pub fn remainder(&self) -> &'a [u8] {
+        self.frames.remainder()
+    }
+}
+
+impl<'a> Iterator for DynFrames<'a> {
+    type Item = Frame<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        Some(Frame {
+            bytes: self.frames.next()?,
+            format: self.format,
+        })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.frames.size_hint()
+    }
+}
+
+impl ExactSizeIterator for DynFrames<'_> {}
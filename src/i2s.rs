@@ -0,0 +1,71 @@
+//! Configuring an I2S peripheral for a WAVE file.
+
+use crate::FmtData;
+
+const PPM: i128 = 1_000_000;
+
+/// The clock frequencies in Hz that an I2S peripheral needs to play samples at their sample rate
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct I2sClocks {
+    /// The frame clock, which is the sample rate
+    pub lrclk: u32,
+    /// The bit clock
+    pub bclk: u32,
+    /// The master clock
+    pub mclk: u32,
+}
+
+/// The closest clocks that a clock divider can make
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct ClockConfig {
+    /// The divider of the source clock that makes the master clock
+    pub divider: u32,
+    /// The master clock that the divider makes, rounded down
+    pub mclk: u32,
+    /// How far the master clock, and so the sample rate, is from the wanted one in parts per
+    /// million. Positive means playing too fast.
+    pub ppm_error: i32,
+}
+
+impl I2sClocks {
+    /// `slot_bits` is the number of bits that each channel takes in a frame, which is usually 16 or
+    /// 32. `mclk_multiple` is the master clock divided by the sample rate, which is usually 256.
+    /// There are at least 2 slots per frame, because I2S always has a left and a right slot.
+    /// Returns [`None`] if a clock doesn't fit in a `u32`.
+    pub fn new(fmt: &FmtData, slot_bits: u32, mclk_multiple: u32) -> Option<Self> {
+        let lrclk = fmt.n_samples_per_sec.get();
+        let slots = u32::from(fmt.n_channels.get()).max(2);
+        Some(Self {
+            lrclk,
+            bclk: lrclk.checked_mul(slots)?.checked_mul(slot_bits)?,
+            mclk: lrclk.checked_mul(mclk_multiple)?,
+        })
+    }
+
+    /// The divider from `dividers` that makes a master clock closest to [`I2sClocks::mclk`] from a
+    /// `source_clock` in Hz. Returns [`None`] if `dividers` has no dividers other than 0.
+    pub fn closest_divider(&self, source_clock: u32, dividers: &[u32]) -> Option<ClockConfig> {
+        let wanted = i128::from(self.mclk);
+        dividers
+            .iter()
+            .filter(|&&divider| divider != 0)
+            .map(|&divider| {
+                // In millionths of a Hz, so that the error isn't rounded to whole Hz
+                let micro_hz = i128::from(source_clock) * PPM / i128::from(divider);
+                let ppm_error = (micro_hz - wanted * PPM)
+                    .checked_div(wanted)
+                    .unwrap_or(i128::MAX);
+                ClockConfig {
+                    divider,
+                    mclk: source_clock / divider,
+                    ppm_error: ppm_error
+                        .clamp(i32::MIN.into(), i32::MAX.into())
+                        .try_into()
+                        .unwrap(),
+                }
+            })
+            .min_by_key(|config| config.ppm_error.unsigned_abs())
+    }
+}
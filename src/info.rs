@@ -0,0 +1,146 @@
+//! The `LIST` chunk with the `INFO` list type, which contains text tags such as the title and
+//! artist of the song.
+//!
+//! See <https://www.robotplanet.dk/audio/wav_meta_data/> for a list of tags.
+//!
+//! Write tags with [`InfoTags`]. Read them with [`InfoReader`].
+
+use pure_riff::{Id, RiffChunkHeader};
+
+use crate::{
+    Error, ReadInstruction,
+    adtl::Text,
+    list::{LIST_ID, ListChunks, ListItem},
+    scan::ChunkInfo,
+    writer::WriteChunk,
+};
+
+pub const INFO_LIST_TYPE: Id = *b"INFO";
+
+/// Title
+pub const INAM: Id = *b"INAM";
+/// Artist
+pub const IART: Id = *b"IART";
+/// Creation date, usually `YYYY-MM-DD`
+pub const ICRD: Id = *b"ICRD";
+/// Software used to create the file
+pub const ISFT: Id = *b"ISFT";
+/// Comment
+pub const ICMT: Id = *b"ICMT";
+/// Genre
+pub const IGNR: Id = *b"IGNR";
+
+/// Tags that can be written as a `LIST` `INFO` chunk with
+/// [`Writer::write_chunk`](crate::writer::Writer::write_chunk). Each value is written with a null
+/// terminator and padded to an even len.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct InfoTags<'a> {
+    pub title: Option<&'a str>,
+    pub artist: Option<&'a str>,
+    pub date: Option<&'a str>,
+    pub software: Option<&'a str>,
+    /// Any other tags
+    pub other: &'a [(Id, &'a str)],
+}
+
+impl<'a> InfoTags<'a> {
+    pub fn tags(&self) -> impl Iterator<Item = (Id, &'a str)> {
+        [
+            (INAM, self.title),
+            (IART, self.artist),
+            (ICRD, self.date),
+            (ISFT, self.software),
+        ]
+        .into_iter()
+        .filter_map(|(id, value)| Some((id, value?)))
+        .chain(self.other.iter().copied())
+    }
+}
+
+/// The len of a tag's value, including the null terminator
+fn value_len(value: &str) -> usize {
+    value.len() + 1
+}
+
+impl WriteChunk for InfoTags<'_> {
+    fn chunk_id(&self) -> Id {
+        LIST_ID
+    }
+
+    fn data_len(&self) -> u32 {
+        let len = size_of::<Id>()
+            + self
+                .tags()
+                .map(|(_, value)| {
+                    size_of::<RiffChunkHeader>() + value_len(value).next_multiple_of(2)
+                })
+                .sum::<usize>();
+        len.try_into().unwrap_or(u32::MAX)
+    }
+
+    fn write_data(&self, buffer: &mut [u8]) {
+        let (list_type, mut buffer) = buffer.split_at_mut(size_of::<Id>());
+        list_type.copy_from_slice(&INFO_LIST_TYPE);
+        for (id, value) in self.tags() {
+            let len = value_len(value);
+            let (header, rest) = buffer.split_at_mut(size_of::<RiffChunkHeader>());
+            header[..size_of::<Id>()].copy_from_slice(&id);
+            header[size_of::<Id>()..].copy_from_slice(&u32::try_from(len).unwrap().to_le_bytes());
+            let (value_buffer, rest) = rest.split_at_mut(len.next_multiple_of(2));
+            value_buffer[..value.len()].copy_from_slice(value.as_bytes());
+            value_buffer[value.len()..].fill(0);
+            buffer = rest;
+        }
+    }
+}
+
+/// A tag in a `LIST` `INFO` chunk. The value is usually null-terminated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct InfoTag {
+    pub id: Id,
+    pub value: Text,
+}
+
+/// Goes through the tags of a `LIST` `INFO` chunk. The values are not read, so that you can decide
+/// which ones to read.
+pub struct InfoReader {
+    chunks: ListChunks,
+}
+
+impl InfoReader {
+    pub const MAX_BUFFER_LEN: usize = size_of::<RiffChunkHeader>();
+
+    pub fn new(list: &ChunkInfo) -> Self {
+        Self {
+            chunks: ListChunks::new(list, 0),
+        }
+    }
+
+    /// [`None`] if there are no more tags
+    pub fn read_instruction(&self) -> Option<ReadInstruction> {
+        self.chunks.read_instruction()
+    }
+
+    /// Returns the tag, if one was found, and the reader for the rest of the tags
+    pub fn process_data(self, data: &[u8]) -> Result<(Option<InfoTag>, Self), Error> {
+        let (item, chunks) = self.chunks.process_data(data)?;
+        let tag = match item {
+            ListItem::ListType(list_type) => {
+                if list_type != INFO_LIST_TYPE {
+                    return Err(Error::UnexpectedListType(list_type));
+                }
+                None
+            }
+            ListItem::SubChunk { chunk, .. } => Some(InfoTag {
+                id: chunk.chunk_id,
+                value: Text {
+                    position: chunk.data_position(),
+                    len: chunk.len,
+                },
+            }),
+        };
+        Ok((tag, Self { chunks }))
+    }
+}
@@ -0,0 +1,112 @@
+//! Blocking functions for looking at files on a desktop, for example in a CLI that debugs files
+//! recorded by a device. Requires the `std` feature.
+
+use std::{
+    fs::File,
+    io::{self, Read, Seek, SeekFrom, Write},
+    path::Path,
+    vec::Vec,
+};
+
+use pure_riff::{Id, SUB_CHUNKS_OFFSET};
+
+use crate::{
+    Error, ErrorLocation, Located, ParseOptions, ReadInstruction, WavMetaData,
+    scan::{ChunkInfo, Chunks},
+    storage::{IoStorage, ReadError, Storage, WavReader},
+};
+
+type FileStorage = IoStorage<File>;
+
+/// Everything [`inspect`] found out about a file
+#[derive(Debug)]
+pub struct FileReport {
+    /// The len of the file, which can be different from what the `RIFF` chunk says
+    pub file_len: u64,
+    /// The error if the file doesn't parse
+    pub meta_data: Result<WavMetaData, ReadError<io::Error>>,
+    /// Every top-level chunk in the file, in order, even if the file doesn't parse or the `RIFF`
+    /// len is wrong
+    pub chunks: Vec<ChunkInfo>,
+}
+
+/// Goes through the chunks up to the end of the file, without trusting the `RIFF` len
+fn chunks(
+    storage: &mut FileStorage,
+    file_len: u64,
+) -> Result<Vec<ChunkInfo>, ReadError<io::Error>> {
+    let mut chunks = Vec::new();
+    let mut scanner = Chunks::between(SUB_CHUNKS_OFFSET.into(), file_len);
+    let mut buffer = [0; Chunks::MAX_BUFFER_LEN];
+    while let Some(ReadInstruction { position, len }) = scanner.read_instruction() {
+        let buffer = &mut buffer[..len.try_into().unwrap()];
+        storage.read(position, buffer).map_err(ReadError::Storage)?;
+        let location = ErrorLocation {
+            position,
+            chunk_id: None,
+        };
+        let (chunk, next_scanner) = scanner
+            .process_data(buffer)
+            .map_err(|error| ReadError::Parse(Located { error, location }))?;
+        chunks.push(chunk);
+        scanner = next_scanner;
+    }
+    Ok(chunks)
+}
+
+fn open(path: &Path) -> Result<(FileStorage, u64), ReadError<io::Error>> {
+    let file = File::open(path).map_err(ReadError::Storage)?;
+    let file_len = file.metadata().map_err(ReadError::Storage)?.len();
+    Ok((IoStorage(file), file_len))
+}
+
+/// Parses the file and lists its chunks. Only fails if the file can't be read. If it doesn't
+/// parse, the error is in [`FileReport::meta_data`] and the chunks are still listed.
+pub fn inspect(path: impl AsRef<Path>) -> Result<FileReport, ReadError<io::Error>> {
+    let (mut storage, file_len) = open(path.as_ref())?;
+    let options = ParseOptions {
+        file_len: Some(file_len),
+        ..Default::default()
+    };
+    let meta_data = WavReader::open_with_options(IoStorage(&mut storage.0), options)
+        .map(|reader| reader.into_parts().1);
+    Ok(FileReport {
+        file_len,
+        meta_data,
+        chunks: chunks(&mut storage, file_len)?,
+    })
+}
+
+/// Copies the data of the first top-level chunk with the id `chunk_id` into `out`. Returns
+/// [`None`] if there is no such chunk. If the chunk goes past the end of the file, the part that
+/// is in the file is copied, and then [`Error::FileTruncated`] is returned.
+pub fn extract_chunk(
+    path: impl AsRef<Path>,
+    chunk_id: Id,
+    mut out: impl Write,
+) -> Result<Option<ChunkInfo>, ReadError<io::Error>> {
+    let (mut storage, file_len) = open(path.as_ref())?;
+    let Some(chunk) = chunks(&mut storage, file_len)?
+        .into_iter()
+        .find(|chunk| chunk.chunk_id == chunk_id)
+    else {
+        return Ok(None);
+    };
+    let file = &mut storage.0;
+    file.seek(SeekFrom::Start(chunk.data_position()))
+        .map_err(ReadError::Storage)?;
+    let copied =
+        io::copy(&mut file.take(chunk.len.into()), &mut out).map_err(ReadError::Storage)?;
+    if copied < chunk.len.into() {
+        return Err(ReadError::Parse(Located {
+            error: Error::FileTruncated {
+                missing: u64::from(chunk.len) - copied,
+            },
+            location: ErrorLocation {
+                position: chunk.position,
+                chunk_id: Some(chunk.chunk_id),
+            },
+        }));
+    }
+    Ok(Some(chunk))
+}
@@ -0,0 +1,356 @@
+//! The EBU peak envelope `levl` chunk, which has the peaks of every block of frames so that a
+//! waveform overview can be drawn without reading the samples.
+//!
+//! See <https://tech.ebu.ch/docs/tech/tech3285s3.pdf>.
+//!
+//! # Usage
+//! Find the `levl` chunk with [`scan`](crate::scan) and read its [`LevlData`] at
+//! [`ChunkInfo::data_position`]. Read peak frames with [`LevlData::peak_frames_read`] and decode
+//! them with [`PeakFrames`].
+//!
+//! To make a `levl` chunk, analyze the samples with a [`PeakEnvelope`] and write the [`Levl`] that
+//! it makes with [`Writer::write_chunk`](crate::writer::Writer::write_chunk).
+
+use pure_riff::{Id, RiffChunkHeader};
+use zerocopy::{FromBytes, FromZeros, Immutable, IntoBytes, KnownLayout, little_endian::U32};
+
+use crate::{
+    ReadInstruction, analysis::Analysis, frames::Frame, scan::ChunkInfo, writer::WriteChunk,
+};
+
+pub const LEVL_ID: Id = *b"levl";
+
+/// A peak value is a `u8`
+pub const LEVL_FORMAT_8_BIT: u32 = 1;
+/// A peak value is a little-endian `u16`
+pub const LEVL_FORMAT_16_BIT: u32 = 2;
+
+/// The header of the `levl` chunk, which is followed by the peak frames
+#[derive(Debug, Clone, Copy, FromBytes, IntoBytes, Immutable, KnownLayout)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[repr(C)]
+pub struct LevlData {
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_le::u32"))]
+    pub version: U32,
+    /// [`LEVL_FORMAT_8_BIT`] or [`LEVL_FORMAT_16_BIT`]
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_le::u32"))]
+    pub format: U32,
+    /// 1 if there is only the absolute peak, 2 if there is a positive and a negative peak
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_le::u32"))]
+    pub points_per_value: U32,
+    /// The number of frames in each block, which is usually 256
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_le::u32"))]
+    pub block_size: U32,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_le::u32"))]
+    pub peak_channels: U32,
+    /// The number of peak frames, which is one for every block
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_le::u32"))]
+    pub num_peak_frames: U32,
+    /// The offset of the peak of peaks in the samples, in frames. `u32::MAX` if it is unknown.
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_le::u32"))]
+    pub pos_peak_of_peaks: U32,
+    /// The offset of the peak frames from the start of the chunk header, which is usually 128
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_le::u32"))]
+    pub offset_to_peaks: U32,
+    /// `yyyy:mm:dd:hh:mm:ss:uuuuuu` followed by a null byte
+    pub timestamp: [u8; 28],
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_le::bytes"))]
+    pub reserved: [u8; 60],
+}
+
+const _: () = assert!(size_of::<LevlData>() == 120);
+
+impl Default for LevlData {
+    fn default() -> Self {
+        Self {
+            format: LEVL_FORMAT_16_BIT.into(),
+            points_per_value: 2.into(),
+            block_size: 256.into(),
+            pos_peak_of_peaks: u32::MAX.into(),
+            offset_to_peaks: u32::try_from(size_of::<RiffChunkHeader>() + size_of::<Self>())
+                .unwrap()
+                .into(),
+            ..FromZeros::new_zeroed()
+        }
+    }
+}
+
+impl LevlData {
+    /// The len of one peak value. [`None`] if the format is unknown.
+    pub fn value_len(&self) -> Option<u32> {
+        match self.format.get() {
+            LEVL_FORMAT_8_BIT => Some(1),
+            LEVL_FORMAT_16_BIT => Some(2),
+            _ => None,
+        }
+    }
+
+    /// The len of one peak frame. [`None`] if the format is unknown, the number of points per
+    /// value isn't 1 or 2, or there are no channels.
+    pub fn peak_frame_len(&self) -> Option<u32> {
+        if !matches!(self.points_per_value.get(), 1 | 2) || self.peak_channels.get() == 0 {
+            return None;
+        }
+        self.value_len()?
+            .checked_mul(self.points_per_value.get())?
+            .checked_mul(self.peak_channels.get())
+    }
+
+    /// The position of the first peak frame in the file
+    pub fn peaks_position(&self, chunk: &ChunkInfo) -> u64 {
+        chunk
+            .position
+            .saturating_add(self.offset_to_peaks.get().into())
+    }
+
+    /// The number of peak frames that are in the chunk. This is less than
+    /// [`LevlData::num_peak_frames`] if the chunk is too small.
+    pub fn available_peak_frames(&self, chunk: &ChunkInfo) -> u32 {
+        let Some(peak_frame_len) = self.peak_frame_len() else {
+            return 0;
+        };
+        let peaks_len = chunk
+            .data_position()
+            .saturating_add(chunk.len.into())
+            .saturating_sub(self.peaks_position(chunk));
+        u32::try_from(peaks_len / u64::from(peak_frame_len))
+            .unwrap_or(u32::MAX)
+            .min(self.num_peak_frames.get())
+    }
+
+    /// Where to read whole peak frames starting at `first_frame`, up to `max_len` bytes but at
+    /// least one peak frame.
+    /// [`None`] if there are no peak frames left or the header is invalid.
+    pub fn peak_frames_read(
+        &self,
+        chunk: &ChunkInfo,
+        first_frame: u32,
+        max_len: u32,
+    ) -> Option<ReadInstruction> {
+        let peak_frame_len = self.peak_frame_len()?;
+        let frames = self
+            .available_peak_frames(chunk)
+            .checked_sub(first_frame)
+            .filter(|&frames| frames != 0)?
+            .min((max_len / peak_frame_len).max(1));
+        Some(ReadInstruction {
+            position: self
+                .peaks_position(chunk)
+                .saturating_add(u64::from(first_frame) * u64::from(peak_frame_len)),
+            len: frames * peak_frame_len,
+        })
+    }
+}
+
+/// The peak of a channel in a block. For 8-bit peaks, the values are 0 to 255.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Peak {
+    /// The absolute peak if there is only one point per value
+    pub positive: u16,
+    /// The magnitude of the negative peak, if there are two points per value
+    pub negative: Option<u16>,
+}
+
+/// Peak frames that were read
+#[derive(Debug, Clone, Copy)]
+pub struct PeakFrames<'a> {
+    data: &'a [u8],
+    value_len: usize,
+    points_per_value: usize,
+    channels: usize,
+}
+
+impl<'a> PeakFrames<'a> {
+    /// [`None`] if the header is invalid. A partial peak frame at the end of `data` is ignored.
+    pub fn new(levl: &LevlData, data: &'a [u8]) -> Option<Self> {
+        levl.peak_frame_len()?;
+        Some(Self {
+            data,
+            value_len: levl.value_len()?.try_into().unwrap(),
+            points_per_value: levl.points_per_value.get().try_into().unwrap(),
+            channels: levl.peak_channels.get().try_into().ok()?,
+        })
+    }
+
+    fn frame_len(&self) -> usize {
+        self.value_len * self.points_per_value * self.channels
+    }
+
+    pub fn len(&self) -> usize {
+        self.data.len() / self.frame_len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn value(&self, offset: usize) -> u16 {
+        match self.value_len {
+            1 => self.data[offset].into(),
+            _ => u16::from_le_bytes([self.data[offset], self.data[offset + 1]]),
+        }
+    }
+
+    /// [`None`] if `frame` or `channel` is out of range
+    pub fn get(&self, frame: usize, channel: usize) -> Option<Peak> {
+        if frame >= self.len() || channel >= self.channels {
+            return None;
+        }
+        let offset = frame * self.frame_len() + channel * self.points_per_value * self.value_len;
+        Some(Peak {
+            positive: self.value(offset),
+            negative: (self.points_per_value == 2).then(|| self.value(offset + self.value_len)),
+        })
+    }
+}
+
+/// A `levl` chunk that can be written
+#[derive(Debug, Clone, Copy)]
+pub struct Levl<'a> {
+    pub data: LevlData,
+    /// The peak frames, in the format of `data`
+    pub peaks: &'a [u8],
+}
+
+impl WriteChunk for Levl<'_> {
+    fn chunk_id(&self) -> Id {
+        LEVL_ID
+    }
+
+    fn data_len(&self) -> u32 {
+        u32::try_from(size_of::<LevlData>() + self.peaks.len()).unwrap_or(u32::MAX)
+    }
+
+    fn write_data(&self, buffer: &mut [u8]) {
+        let (data, peaks) = buffer.split_at_mut(size_of::<LevlData>());
+        data.copy_from_slice(self.data.as_bytes());
+        peaks.copy_from_slice(self.peaks);
+    }
+}
+
+/// An [`Analysis`] that makes the peak frames of a `levl` chunk. Channels after the first
+/// `CHANNELS` are ignored.
+#[derive(Debug)]
+pub struct PeakEnvelope<'a, const CHANNELS: usize = 2> {
+    data: LevlData,
+    /// Where the peak frames are put
+    buffer: &'a mut [u8],
+    /// The len of the peak frames in `buffer`
+    len: usize,
+    /// The highest and the lowest sample of each channel in the current block
+    block: [(i32, i32); CHANNELS],
+    frames_in_block: u32,
+    frames: u64,
+    peak_of_peaks: u32,
+}
+
+impl<'a, const CHANNELS: usize> PeakEnvelope<'a, CHANNELS> {
+    /// Uses the format, points per value, block size and timestamp of `data`. Use
+    /// [`LevlData::default`] for the usual format. Returns [`None`] if the format is invalid. The
+    /// peak frames are put in `buffer`, which should be at least [`PeakEnvelope::buffer_len`].
+    pub fn new(mut data: LevlData, buffer: &'a mut [u8]) -> Option<Self> {
+        data.peak_channels = u32::try_from(CHANNELS).ok()?.into();
+        data.num_peak_frames = 0.into();
+        data.pos_peak_of_peaks = u32::MAX.into();
+        data.offset_to_peaks = u32::try_from(size_of::<RiffChunkHeader>() + size_of::<LevlData>())
+            .unwrap()
+            .into();
+        data.peak_frame_len()?;
+        if data.block_size.get() == 0 {
+            return None;
+        }
+        Some(Self {
+            data,
+            buffer,
+            len: 0,
+            block: [(0, 0); CHANNELS],
+            frames_in_block: 0,
+            frames: 0,
+            peak_of_peaks: 0,
+        })
+    }
+
+    /// The len of the peak frames for `frames` frames of samples. [`None`] if the format is
+    /// invalid.
+    pub fn buffer_len(data: &LevlData, frames: u64) -> Option<usize> {
+        let mut data = *data;
+        data.peak_channels = u32::try_from(CHANNELS).ok()?.into();
+        let peak_frames = frames.div_ceil(data.block_size.get().into());
+        usize::try_from(peak_frames.checked_mul(data.peak_frame_len()?.into())?).ok()
+    }
+
+    /// If `buffer` was too small for all of the peak frames
+    pub fn truncated(&self) -> bool {
+        self.data.num_peak_frames.get() as usize * self.data.peak_frame_len().unwrap() as usize
+            > self.len
+    }
+
+    /// The chunk with the peak frames so far
+    pub fn levl(&self) -> Levl<'_> {
+        let mut data = self.data;
+        data.num_peak_frames = u32::try_from(self.len / data.peak_frame_len().unwrap() as usize)
+            .unwrap()
+            .into();
+        Levl {
+            data,
+            peaks: &self.buffer[..self.len],
+        }
+    }
+
+    fn end_block(&mut self) {
+        let value_len = self.data.value_len().unwrap() as usize;
+        let two_points = self.data.points_per_value.get() == 2;
+        let frame_len = self.data.peak_frame_len().unwrap() as usize;
+        self.data.num_peak_frames = (self.data.num_peak_frames.get() + 1).into();
+        if let Some(peak_frame) = self.buffer.get_mut(self.len..self.len + frame_len) {
+            let mut values = peak_frame.chunks_exact_mut(value_len);
+            for (highest, lowest) in self.block {
+                let points = if two_points {
+                    [highest.max(0).unsigned_abs(), lowest.min(0).unsigned_abs()]
+                } else {
+                    [highest.unsigned_abs().max(lowest.unsigned_abs()), 0]
+                };
+                for point in &points[..usize::from(two_points) + 1] {
+                    let value = match self.data.format.get() {
+                        LEVL_FORMAT_8_BIT => (point >> 24).min(u8::MAX.into()),
+                        _ => (point >> 16).min(u16::MAX.into()),
+                    };
+                    values
+                        .next()
+                        .unwrap()
+                        .copy_from_slice(&value.to_le_bytes()[..value_len]);
+                }
+            }
+            self.len += frame_len;
+        }
+        self.block = [(0, 0); CHANNELS];
+        self.frames_in_block = 0;
+    }
+}
+
+impl<const CHANNELS: usize> Analysis for PeakEnvelope<'_, CHANNELS> {
+    fn frame(&mut self, frame: Frame<'_>) {
+        for (sample, (highest, lowest)) in frame.samples().zip(&mut self.block) {
+            *highest = (*highest).max(sample);
+            *lowest = (*lowest).min(sample);
+            let magnitude = sample.unsigned_abs();
+            if magnitude > self.peak_of_peaks {
+                self.peak_of_peaks = magnitude;
+                self.data.pos_peak_of_peaks = u32::try_from(self.frames).unwrap_or(u32::MAX).into();
+            }
+        }
+        self.frames += 1;
+        self.frames_in_block += 1;
+        if self.frames_in_block == self.data.block_size.get() {
+            self.end_block();
+        }
+    }
+
+    fn finish(&mut self) {
+        if self.frames_in_block != 0 {
+            self.end_block();
+        }
+    }
+}
@@ -1,11 +1,23 @@
 //! # Usage
-//! Create a [`Parser`] with [`Parser::default`].
+//! Create a [`Parser`] with [`Parser::default`], or with [`Parser::new`] to choose the
+//! [`ParseOptions`].
 //! Call [`Parser::read_instruction`] to get where in the file you need to read.
 //! Read the file.
 //! Call [`Parser::process_data`] with the data you read.
 //! In the end you will get meta data about the format of the samples and the location of teh
 //! samples themselves.
 //!
+//! If you don't need to control the reads yourself, see [`storage::WavReader`].
+//!
+//! [`WavMetaData`] isn't specific to how the samples are played. It works the same for I2S, USB
+//! audio, streaming over a network, or analyzing the samples. The helpers for configuring an I2S
+//! peripheral are in [`i2s`].
+//!
+//! # Small RAM
+//! No state machine asks for more than [`SMALL_BUFFER_LEN`] bytes at a time, so one buffer of that
+//! len is enough for all of the parsing. The len of sample reads, and of payload reads in
+//! [`edit::ChunksChecksum`], is up to you.
+//!
 //! # Handling untrusted data
 //! This library may ask you to read data outside of the range of the file / whatever is storing
 //! the WAVE. The meta data might point to a range outside of the file. It's your responsibility
@@ -21,32 +33,106 @@
 //! that contains the actual audio samples. There can also be other chunks to describe things like
 //! the artist and title of the song, but they are not needed for simply playing audio.
 #![no_std]
+#[cfg(feature = "std")]
+extern crate std;
+
+pub mod adpcm;
+pub mod adtl;
+pub mod analysis;
+#[cfg(feature = "arbitrary")]
+mod arbitrary_impls;
+mod arithmetic;
+pub mod bext;
+pub mod cache;
+pub mod channels;
+pub mod convert;
+pub mod cue;
+#[cfg(feature = "defmt")]
+mod defmt_format;
+pub mod drive;
+pub mod dsp;
+pub mod edit;
+pub mod fact;
+pub mod fmt;
+pub mod fragment;
+pub mod frames;
+pub mod i2s;
+pub mod info;
+#[cfg(feature = "std")]
+pub mod inspect;
+pub mod levl;
+pub mod list;
+#[cfg(feature = "loudness")]
+pub mod loudness;
+pub mod md5;
+pub mod mixer;
+pub mod playback;
+pub mod position;
+pub mod probe;
+pub mod record;
+pub mod repair;
+pub mod resample;
+pub mod sample;
+pub mod scan;
+#[cfg(feature = "serde")]
+mod serde_le;
+pub mod slice;
+pub mod smpl;
+pub mod snapshot;
+pub mod stats;
+pub mod storage;
+#[cfg(feature = "test-wav")]
+pub mod test_wav;
+pub mod text;
+pub mod transform;
+pub mod units;
+pub mod writer;
+pub mod xmp;
+
+use arithmetic::{Overflow, add_in_bounds, checked_advance};
+use fact::{FACT_ID, FactData};
+use fmt::{FmtError, FmtExtension, WAVE_FORMAT_EXTENSIBLE};
 pub use pure_riff;
-use pure_riff::{
-    BUFFER_LEN, Id, ParseChunkOutput, RiffChunkHeader, SUB_CHUNKS_OFFSET, parse_chunk,
-};
+use pure_riff::{BUFFER_LEN, Id, RiffChunkHeader, SUB_CHUNKS_OFFSET};
+use scan::ScanLimits;
+use snapshot::ParserSnapshot;
+use stats::Stats;
 pub use zerocopy;
 use zerocopy::{
-    FromBytes, Immutable, KnownLayout,
+    FromBytes, FromZeros, Immutable, IntoBytes, KnownLayout,
     little_endian::{U16, U32},
     transmute_ref,
 };
 
-#[derive(Debug, Clone, Copy, FromBytes, Immutable, KnownLayout)]
+/// The most that any state machine reads at a time
+pub const SMALL_BUFFER_LEN: usize = 64;
+
+const _: () = {
+    assert!(Parser::MAX_BUFFER_LEN <= SMALL_BUFFER_LEN);
+    assert!(size_of::<FmtExtension>() <= SMALL_BUFFER_LEN);
+    assert!(edit::EditPlanner::MAX_BUFFER_LEN <= SMALL_BUFFER_LEN);
+    assert!(repair::Repair::MAX_BUFFER_LEN <= SMALL_BUFFER_LEN);
+    assert!(scan::Chunks::MAX_BUFFER_LEN <= SMALL_BUFFER_LEN);
+    assert!(adtl::AdtlReader::MAX_BUFFER_LEN <= SMALL_BUFFER_LEN);
+};
+
+#[derive(Debug, Clone, Copy, FromBytes, IntoBytes, Immutable, KnownLayout)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[repr(C)]
 pub struct FmtData {
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_le::u16"))]
     pub format_tag: U16,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_le::u16"))]
     pub n_channels: U16,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_le::u32"))]
     pub n_samples_per_sec: U32,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_le::u32"))]
     pub n_avg_bytes_per_sec: U32,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_le::u16"))]
     pub n_block_align: U16,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_le::u16"))]
     pub w_bits_per_sample: U16,
-    // The optional 20-byte version of this contains these fields
-    // pub cb_size: U16,
-    // pub w_valid_bits_per_sample: U16,
-    // The optional 40-byte version of this has these fields in addition to the 20-byte fields
-    // pub dw_channel_mask: [u8; 4],
-    // pub sub_format: [u8; 16],
+    // The optional 40-byte version of this has the fields in [`fmt::FmtExtension`]
 }
 
 enum ParseStage {
@@ -54,27 +140,86 @@ enum ParseStage {
     Fmt {
         sub_chunks_len: u32,
         position_in_sub_chunks: u32,
+        /// The chunk before this position has a pad byte
+        after_pad_byte: bool,
+    },
+    FmtExtension {
+        sub_chunks_len: u32,
+        /// The position of the chunk after the `fmt ` chunk
+        position_in_sub_chunks: u32,
+        after_pad_byte: bool,
+        fmt_data: FmtData,
+        extension_position: u32,
+        /// The len of the extension that is in the chunk, at most the size of [`FmtExtension`]
+        extension_len: u32,
     },
     Data {
         sub_chunks_len: u32,
         position_in_sub_chunks: u32,
+        after_pad_byte: bool,
         fmt_data: FmtData,
+        fmt_extension: Option<FmtExtension>,
+        /// The position of the data of a `fact` chunk, which is read before the chunk at
+        /// `position_in_sub_chunks`
+        fact_position: Option<u32>,
     },
 }
 
 pub struct Parser {
     stage: ParseStage,
+    options: ParseOptions,
+    stats: Stats,
+    warnings: Warnings,
+    fact_sample_length: Option<u32>,
 }
 
 impl Default for Parser {
     fn default() -> Self {
-        Self {
-            stage: ParseStage::Riff,
-        }
+        Self::new(Default::default())
     }
 }
 
+/// How to handle files that don't follow the spec
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub enum Strictness {
+    /// Return an error for every problem that is detected
+    Strict,
+    /// Work around problems that are common in real files, and report them in [`Warnings`]:
+    /// - A chunk before the `data` chunk that goes past the end of the `RIFF` chunk is skipped as
+    ///   if the `RIFF` len was correct ([`Warnings::riff_len_too_small`])
+    /// - If a chunk id isn't printable ASCII and the chunk before it has an odd len, the chunk
+    ///   before it is assumed to be missing its pad byte, and the chunk header is read again one
+    ///   byte earlier ([`Warnings::missing_pad_byte`])
+    /// - A `data` chunk that goes past the end of the `RIFF` chunk is trusted
+    ///   ([`Warnings::data_past_riff_end`])
+    /// - An incomplete `WAVE_FORMAT_EXTENSIBLE` extension is ignored
+    ///   ([`Warnings::truncated_extensible_fmt`])
+    #[default]
+    Lenient,
+}
+
+/// Options for the [`Parser`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub struct ParseOptions {
+    pub strictness: Strictness,
+    /// Check the `fmt ` chunk with [`FmtData::validate`], so that a garbage file fails right away
+    /// instead of giving nonsense to the output
+    pub validate_fmt: bool,
+    /// Stop skipping chunks before the `data` chunk after the limits
+    pub limits: ScanLimits,
+    /// The actual len of the file. If the `RIFF` chunk, a chunk before the `data` chunk, or the
+    /// `data` chunk goes past it, parsing fails with [`Error::FileTruncated`].
+    pub file_len: Option<u64>,
+}
+
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Error {
     /// Expected chunk id: "RIFF". Contains actual chunk id.
     UnexpectedChunkId(Id),
@@ -83,19 +228,329 @@ pub enum Error {
     /// Expected container id: "WAVE". Contains actual container id.
     UnexpectedContainerId(Id),
     FmtDataTooSmall(u32),
+    /// The len of the chunk at `position` makes the position of the chunk after it not fit in a
+    /// `u32`
+    ChunkSizeOverflow {
+        chunk_id: Id,
+        position: u64,
+    },
+    /// The chunk at `position` goes past the end of the chunk or file that it is in, such as the
+    /// `RIFF` chunk, a `LIST` chunk, or the bytes given to [`slice::parse`]
+    ChunkExtendsPastEnd {
+        chunk_id: Id,
+        position: u64,
+    },
+    /// The chunk id at `position` isn't printable ASCII. Only returned with
+    /// [`Strictness::Strict`].
+    InvalidChunkId {
+        chunk_id: Id,
+        position: u64,
+    },
+    /// The format tag is `WAVE_FORMAT_EXTENSIBLE`, but the extension is incomplete or its
+    /// `cb_size` is less than 22. Only returned with [`Strictness::Strict`].
+    InvalidFmtExtension,
+    /// The `fmt ` chunk failed [`FmtData::validate`]. Only returned with
+    /// [`ParseOptions::validate_fmt`].
+    InvalidFmt(FmtError),
+    /// A chunk that is needed was not found before the end
+    MissingChunk(Id),
+    /// Going through the chunks went past the [`ScanLimits`]. Contains the position of the next
+    /// chunk.
+    ScanLimitExceeded {
+        position: u64,
+    },
+    /// Something goes past [`ParseOptions::file_len`]. Contains the number of bytes that are
+    /// missing from the file.
+    FileTruncated {
+        missing: u64,
+    },
+    /// A `LIST` chunk has a different list type than expected. Contains the actual list type.
+    UnexpectedListType(Id),
+    /// The data given to `process_data` is not the len from the [`ReadInstruction`], for example
+    /// because of a short read
+    WrongDataLen {
+        expected: u32,
+        actual: usize,
+    },
+    /// `process_data` was called after the state machine finished, when there is nothing to read
+    NoPendingRead,
+}
+
+/// Displays a chunk id as ASCII, or as hex if it isn't printable ASCII
+pub(crate) struct DisplayId<'a>(pub &'a Id);
+
+impl core::fmt::Display for DisplayId<'_> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        if self
+            .0
+            .iter()
+            .all(|byte| byte.is_ascii_graphic() || *byte == b' ')
+        {
+            write!(f, "\"")?;
+            for &byte in self.0 {
+                write!(f, "{}", char::from(byte))?;
+            }
+            write!(f, "\"")
+        } else {
+            write!(f, "0x")?;
+            for byte in self.0 {
+                write!(f, "{byte:02x}")?;
+            }
+            Ok(())
+        }
+    }
+}
+
+impl core::fmt::Display for Error {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::UnexpectedChunkId(id) => {
+                write!(f, "expected a \"RIFF\" chunk, found {}", DisplayId(id))
+            }
+            Self::InvalidRiff => write!(f, "invalid RIFF"),
+            Self::UnexpectedContainerId(id) => {
+                write!(f, "expected a \"WAVE\" container, found {}", DisplayId(id))
+            }
+            Self::FmtDataTooSmall(len) => write!(f, "the fmt chunk is too small: {len} bytes"),
+            Self::ChunkSizeOverflow { chunk_id, position } => write!(
+                f,
+                "the len of the {} chunk at {position} makes a position overflow",
+                DisplayId(chunk_id)
+            ),
+            Self::ChunkExtendsPastEnd { chunk_id, position } => write!(
+                f,
+                "the {} chunk at {position} goes past the end of its container",
+                DisplayId(chunk_id)
+            ),
+            Self::InvalidChunkId { chunk_id, position } => write!(
+                f,
+                "the chunk id {} at {position} is not printable ASCII",
+                DisplayId(chunk_id)
+            ),
+            Self::InvalidFmt(e) => write!(f, "invalid fmt chunk: {e}"),
+            Self::MissingChunk(id) => write!(f, "no {} chunk", DisplayId(id)),
+            Self::ScanLimitExceeded { position } => {
+                write!(f, "went past the scan limits at {position}")
+            }
+            Self::FileTruncated { missing } => {
+                write!(f, "the file is truncated, {missing} bytes are missing")
+            }
+            Self::InvalidFmtExtension => {
+                write!(f, "the WAVE_FORMAT_EXTENSIBLE extension is invalid")
+            }
+            Self::UnexpectedListType(id) => write!(f, "unexpected LIST type {}", DisplayId(id)),
+            Self::WrongDataLen { expected, actual } => {
+                write!(f, "expected {expected} bytes of data, got {actual}")
+            }
+            Self::NoPendingRead => write!(f, "got data after there was nothing left to read"),
+        }
+    }
+}
+
+impl core::error::Error for Error {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        match self {
+            Self::InvalidFmt(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+/// Where in the file a state machine was when it returned an error
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ErrorLocation {
+    /// The position of the read that was being processed
+    pub position: u64,
+    /// The chunk that the read was in, if it is known. [`None`] when reading the header of a chunk
+    /// that wasn't read yet.
+    pub chunk_id: Option<Id>,
+}
+
+impl core::fmt::Display for ErrorLocation {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "at offset {} (0x{:x})", self.position, self.position)?;
+        if let Some(chunk_id) = &self.chunk_id {
+            write!(f, " in the {} chunk", DisplayId(chunk_id))?;
+        }
+        Ok(())
+    }
+}
+
+/// An error with where it happened
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Located<E = Error> {
+    pub error: E,
+    pub location: ErrorLocation,
+}
+
+impl<E: core::fmt::Display> core::fmt::Display for Located<E> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{} {}", self.error, self.location)
+    }
+}
+
+impl<E: core::error::Error + 'static> core::error::Error for Located<E> {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+/// A chunk id should be printable ASCII
+pub(crate) const fn is_plausible_id(id: &Id) -> bool {
+    let mut i = 0;
+    while i < id.len() {
+        if id[i] < 0x20 || id[i] > 0x7E {
+            return false;
+        }
+        i += 1;
+    }
+    true
+}
+
+/// Makes sure that a state machine got the data it asked for, so that it never panics on a short
+/// read
+pub(crate) fn check_data_len(expected: u32, data: &[u8]) -> Result<(), Error> {
+    if usize::try_from(expected).is_ok_and(|expected| expected == data.len()) {
+        Ok(())
+    } else {
+        Err(Error::WrongDataLen {
+            expected,
+            actual: data.len(),
+        })
+    }
+}
+
+/// Like [`check_data_len`], for state machines that are done when they have nothing to read
+pub(crate) fn check_read(read: Option<ReadInstruction>, data: &[u8]) -> Result<(), Error> {
+    check_data_len(read.ok_or(Error::NoPendingRead)?.len, data)
 }
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub struct ReadInstruction {
-    pub position: u32,
+    pub position: u64,
     pub len: u32,
 }
 
+impl ReadInstruction {
+    /// The same read for a WAVE that starts at `base`, for example in a partition of a disk image.
+    /// Returns [`None`] if the position overflows.
+    pub fn offset_by(self, base: u64) -> Option<Self> {
+        Some(Self {
+            position: self.position.checked_add(base)?,
+            len: self.len,
+        })
+    }
+}
+
+/// Problems with the file that the [`Parser`] worked around
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub struct Warnings {
+    /// The format tag is `WAVE_FORMAT_EXTENSIBLE`, but the `fmt ` chunk is too short to contain
+    /// the extension, or its `cb_size` is less than 22. The extension was ignored.
+    pub truncated_extensible_fmt: bool,
+    /// A chunk before the `data` chunk goes past the end of the `RIFF` chunk
+    pub riff_len_too_small: bool,
+    /// A chunk with an odd len has no pad byte after it
+    pub missing_pad_byte: bool,
+    /// The `data` chunk goes past the end of the `RIFF` chunk
+    pub data_past_riff_end: bool,
+}
+
+/// Everything needed to find and decode the samples, returned by the [`Parser`]
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub struct WavMetaData {
     pub fmt: FmtData,
-    pub data_position: u32,
+    /// Present if the format tag is `WAVE_FORMAT_EXTENSIBLE` and the extension is complete
+    pub fmt_extension: Option<FmtExtension>,
+    pub data_position: u64,
+    /// Only these bytes are samples. Chunks after the `data` chunk, such as `LIST` or `id3 `, are
+    /// not part of the audio. See [`scan::Chunks::after_data`] to find them.
     pub data_len: u32,
+    /// The len of the sub chunks in the `RIFF` chunk, which is where the chunks end
+    pub sub_chunks_len: u32,
+    /// The number of frames from the `fact` chunk, if there is one before the `data` chunk.
+    /// Compressed formats need it to know the number of frames.
+    pub fact_sample_length: Option<u32>,
+    /// How much the [`Parser`] had to read
+    pub stats: Stats,
+    pub warnings: Warnings,
+}
+
+impl WavMetaData {
+    /// The format tag, or the format tag of the sub format if the format is
+    /// `WAVE_FORMAT_EXTENSIBLE`. [`None`] if the sub format is not a standard one.
+    pub fn format_tag(&self) -> Option<u16> {
+        match self.fmt.format_tag.get() {
+            WAVE_FORMAT_EXTENSIBLE => self
+                .fmt_extension
+                .as_ref()
+                .and_then(|fmt_extension| fmt_extension.sub_format_tag()),
+            format_tag => Some(format_tag),
+        }
+    }
+
+    /// The encoding of the samples, if it is one that this library knows
+    pub fn codec(&self) -> Option<fmt::Codec> {
+        fmt::Codec::from_format_tag(self.format_tag()?)
+    }
+
+    /// The converter to use for playing this WAVE with 16-bit samples
+    pub fn i16_converter(&self) -> Option<convert::I16Converter> {
+        convert::I16Converter::for_meta_data(self)
+    }
+
+    /// The number of frames. For the formats in [`fmt::Codec`] this comes from the len of the
+    /// `data` chunk. Other formats are compressed, so it comes from the `fact` chunk. [`None`] if
+    /// it isn't known.
+    pub fn frame_count(&self) -> Option<u64> {
+        match self.codec() {
+            Some(_) => Some(
+                self.data_len
+                    .checked_div(self.fmt.n_block_align.get().into())?
+                    .into(),
+            ),
+            None => self.fact_sample_length.map(u64::from),
+        }
+    }
+
+    /// The number of bytes of the `data` chunk per second of audio. For the formats in
+    /// [`fmt::Codec`] this is calculated from the sample rate and the block align, because
+    /// `n_avg_bytes_per_sec` is sometimes wrong.
+    pub fn bytes_per_second(&self) -> u32 {
+        match self.codec() {
+            Some(_) => self
+                .fmt
+                .n_samples_per_sec
+                .get()
+                .saturating_mul(self.fmt.n_block_align.get().into()),
+            None => self.fmt.n_avg_bytes_per_sec.get(),
+        }
+    }
+
+    /// The duration of the audio in microseconds. If the number of frames isn't known, this is
+    /// estimated from [`WavMetaData::bytes_per_second`]. [`None`] if the sample rate or the byte
+    /// rate is 0.
+    pub fn duration_micros(&self) -> Option<u64> {
+        const MICROS_PER_SEC: u128 = 1_000_000;
+        let micros = match self.frame_count() {
+            Some(frames) => (u128::from(frames) * MICROS_PER_SEC)
+                .checked_div(self.fmt.n_samples_per_sec.get().into())?,
+            None => (u128::from(self.data_len) * MICROS_PER_SEC)
+                .checked_div(self.bytes_per_second().into())?,
+        };
+        Some(u64::try_from(micros).unwrap_or(u64::MAX))
+    }
 }
 
 pub enum ProcessDataOutput {
@@ -106,115 +561,637 @@ pub enum ProcessDataOutput {
 impl Parser {
     pub const MAX_BUFFER_LEN: usize = size_of::<RiffChunkHeader>() + size_of::<FmtData>();
 
+    pub fn new(options: ParseOptions) -> Self {
+        Self {
+            stage: ParseStage::Riff,
+            options,
+            stats: Default::default(),
+            warnings: Default::default(),
+            fact_sample_length: None,
+        }
+    }
+
     pub fn read_instruction(&self) -> ReadInstruction {
         match &self.stage {
+            // The `RIFF` header and the container id
             ParseStage::Riff => ReadInstruction {
                 position: 0,
-                len: size_of::<RiffChunkHeader>().try_into().unwrap(),
+                len: SUB_CHUNKS_OFFSET,
             },
             ParseStage::Fmt {
-                sub_chunks_len: _sub_chunks_len,
                 position_in_sub_chunks,
+                ..
             } => ReadInstruction {
-                position: SUB_CHUNKS_OFFSET + position_in_sub_chunks,
+                position: add_in_bounds(SUB_CHUNKS_OFFSET, *position_in_sub_chunks).into(),
                 len: (size_of::<RiffChunkHeader>() + size_of::<FmtData>())
                     .try_into()
                     .unwrap(),
             },
+            ParseStage::FmtExtension {
+                extension_position,
+                extension_len,
+                ..
+            } => ReadInstruction {
+                position: (*extension_position).into(),
+                len: *extension_len,
+            },
+            ParseStage::Data {
+                fact_position: Some(fact_position),
+                ..
+            } => ReadInstruction {
+                position: add_in_bounds(SUB_CHUNKS_OFFSET, *fact_position).into(),
+                len: size_of::<U32>().try_into().unwrap(),
+            },
             ParseStage::Data {
-                sub_chunks_len: _sub_chunks_len,
                 position_in_sub_chunks,
-                fmt_data: _fmt_data,
+                ..
             } => ReadInstruction {
-                position: SUB_CHUNKS_OFFSET + position_in_sub_chunks,
+                position: add_in_bounds(SUB_CHUNKS_OFFSET, *position_in_sub_chunks).into(),
                 len: size_of::<RiffChunkHeader>().try_into().unwrap(),
             },
         }
     }
 
-    pub fn process_data(self, data: &[u8]) -> Result<ProcessDataOutput, Error> {
-        match self.stage {
+    /// Where the next read is. An error from [`Parser::process_data`] has this location.
+    pub fn location(&self) -> ErrorLocation {
+        ErrorLocation {
+            position: self.read_instruction().position,
+            chunk_id: match &self.stage {
+                ParseStage::Riff => Some(*b"RIFF"),
+                ParseStage::FmtExtension { .. } => Some(*b"fmt "),
+                ParseStage::Data {
+                    fact_position: Some(_),
+                    ..
+                } => Some(FACT_ID),
+                ParseStage::Fmt { .. } | ParseStage::Data { .. } => None,
+            },
+        }
+    }
+
+    /// How much was read so far
+    pub fn stats(&self) -> Stats {
+        self.stats
+    }
+
+    /// Saves the state of the parser. See [`snapshot`].
+    pub fn snapshot(&self) -> ParserSnapshot {
+        let mut snapshot = ParserSnapshot::new_zeroed();
+        snapshot.version = snapshot::VERSION;
+        snapshot.strictness = match self.options.strictness {
+            Strictness::Strict => 0,
+            Strictness::Lenient => 1,
+        };
+        snapshot.warnings = u8::from(self.warnings.truncated_extensible_fmt)
+            | u8::from(self.warnings.riff_len_too_small) << 1
+            | u8::from(self.warnings.missing_pad_byte) << 2
+            | u8::from(self.warnings.data_past_riff_end) << 3;
+        snapshot.requests = self.stats.requests.into();
+        snapshot.chunks_skipped = self.stats.chunks_skipped.into();
+        snapshot.bytes = self.stats.bytes.into();
+        if self.options.validate_fmt {
+            snapshot.flags |= 1 << 4;
+        }
+        if let Some(fact_sample_length) = self.fact_sample_length {
+            snapshot.flags |= 1 << 3;
+            snapshot.fact_sample_length = fact_sample_length.into();
+        }
+        if let Some(max_chunks) = self.options.limits.max_chunks {
+            snapshot.flags |= 1 << 5;
+            snapshot.max_chunks = max_chunks.into();
+        }
+        if let Some(max_bytes) = self.options.limits.max_bytes {
+            snapshot.flags |= 1 << 6;
+            snapshot.max_bytes = max_bytes.into();
+        }
+        if let Some(file_len) = self.options.file_len {
+            snapshot.flags |= 1 << 7;
+            snapshot.file_len = file_len.into();
+        }
+        match &self.stage {
+            ParseStage::Riff => {}
+            ParseStage::Fmt {
+                sub_chunks_len,
+                position_in_sub_chunks,
+                after_pad_byte,
+            } => {
+                snapshot.stage = 1;
+                snapshot.sub_chunks_len = (*sub_chunks_len).into();
+                snapshot.position_in_sub_chunks = (*position_in_sub_chunks).into();
+                snapshot.flags |= u8::from(*after_pad_byte);
+            }
+            ParseStage::FmtExtension {
+                sub_chunks_len,
+                position_in_sub_chunks,
+                after_pad_byte,
+                fmt_data,
+                extension_position,
+                extension_len,
+            } => {
+                snapshot.stage = 2;
+                snapshot.sub_chunks_len = (*sub_chunks_len).into();
+                snapshot.position_in_sub_chunks = (*position_in_sub_chunks).into();
+                snapshot.flags |= u8::from(*after_pad_byte);
+                snapshot.fmt_data = *fmt_data;
+                snapshot.extension_position = (*extension_position).into();
+                snapshot.extension_len = (*extension_len).into();
+            }
+            ParseStage::Data {
+                sub_chunks_len,
+                position_in_sub_chunks,
+                after_pad_byte,
+                fmt_data,
+                fmt_extension,
+                fact_position,
+            } => {
+                snapshot.stage = 3;
+                snapshot.sub_chunks_len = (*sub_chunks_len).into();
+                snapshot.position_in_sub_chunks = (*position_in_sub_chunks).into();
+                snapshot.flags |= u8::from(*after_pad_byte)
+                    | u8::from(fmt_extension.is_some()) << 1
+                    | u8::from(fact_position.is_some()) << 2;
+                snapshot.fact_position = fact_position.unwrap_or_default().into();
+                snapshot.fmt_data = *fmt_data;
+                if let Some(fmt_extension) = fmt_extension {
+                    snapshot.fmt_extension = *fmt_extension;
+                }
+            }
+        }
+        snapshot
+    }
+
+    /// Continues parsing from a snapshot. Returns [`None`] if the snapshot is invalid.
+    pub fn restore(snapshot: &ParserSnapshot) -> Option<Self> {
+        if snapshot.version != snapshot::VERSION {
+            return None;
+        }
+        let strictness = match snapshot.strictness {
+            0 => Strictness::Strict,
+            1 => Strictness::Lenient,
+            _ => return None,
+        };
+        let sub_chunks_len = snapshot.sub_chunks_len.get();
+        let position_in_sub_chunks = snapshot.position_in_sub_chunks.get();
+        let after_pad_byte = snapshot.flags & 1 != 0;
+        let warnings = Warnings {
+            truncated_extensible_fmt: snapshot.warnings & 1 != 0,
+            riff_len_too_small: snapshot.warnings & 1 << 1 != 0,
+            missing_pad_byte: snapshot.warnings & 1 << 2 != 0,
+            data_past_riff_end: snapshot.warnings & 1 << 3 != 0,
+        };
+        // The same bounds that `process_data` keeps, so that a bad snapshot can't make it overflow
+        let read_fits = |position: u32, len: u32| {
+            SUB_CHUNKS_OFFSET
+                .checked_add(position)
+                .and_then(|position| position.checked_add(len))
+                .is_some()
+        };
+        if snapshot.stage != 0
+            && (!read_fits(
+                position_in_sub_chunks,
+                Self::MAX_BUFFER_LEN.try_into().unwrap(),
+            ) || (position_in_sub_chunks > sub_chunks_len && !warnings.riff_len_too_small)
+                || (after_pad_byte && position_in_sub_chunks == 0))
+        {
+            return None;
+        }
+        let stage = match snapshot.stage {
+            0 => ParseStage::Riff,
+            1 => ParseStage::Fmt {
+                sub_chunks_len,
+                position_in_sub_chunks,
+                after_pad_byte,
+            },
+            2 => {
+                let extension_position = snapshot.extension_position.get();
+                let extension_len = u32::try_from(size_of::<FmtExtension>())
+                    .unwrap()
+                    .min(snapshot.extension_len.get());
+                // The extension is after the header and the fixed part of a `fmt ` chunk
+                let min_position = SUB_CHUNKS_OFFSET
+                    + u32::try_from(size_of::<RiffChunkHeader>() + size_of::<FmtData>()).unwrap();
+                if extension_position < min_position
+                    || extension_position.checked_add(extension_len).is_none()
+                {
+                    return None;
+                }
+                ParseStage::FmtExtension {
+                    sub_chunks_len,
+                    position_in_sub_chunks,
+                    after_pad_byte,
+                    fmt_data: snapshot.fmt_data,
+                    extension_position,
+                    extension_len,
+                }
+            }
+            3 => ParseStage::Data {
+                sub_chunks_len,
+                position_in_sub_chunks,
+                after_pad_byte,
+                fmt_data: snapshot.fmt_data,
+                fmt_extension: (snapshot.flags & 0b10 != 0).then_some(snapshot.fmt_extension),
+                fact_position: match (snapshot.flags & 0b100 != 0)
+                    .then_some(snapshot.fact_position.get())
+                {
+                    // The data of the `fact` chunk is after its header
+                    Some(fact_position)
+                        if fact_position < size_of::<RiffChunkHeader>().try_into().unwrap()
+                            || !read_fits(fact_position, size_of::<U32>().try_into().unwrap()) =>
+                    {
+                        return None;
+                    }
+                    fact_position => fact_position,
+                },
+            },
+            _ => return None,
+        };
+        Some(Self {
+            stage,
+            options: ParseOptions {
+                strictness,
+                validate_fmt: snapshot.flags & 1 << 4 != 0,
+                limits: ScanLimits {
+                    max_chunks: (snapshot.flags & 1 << 5 != 0).then_some(snapshot.max_chunks.get()),
+                    max_bytes: (snapshot.flags & 1 << 6 != 0).then_some(snapshot.max_bytes.get()),
+                },
+                file_len: (snapshot.flags & 1 << 7 != 0).then_some(snapshot.file_len.get()),
+            },
+            stats: Stats {
+                requests: snapshot.requests.get(),
+                bytes: snapshot.bytes.get(),
+                chunks_skipped: snapshot.chunks_skipped.get(),
+            },
+            warnings,
+            fact_sample_length: (snapshot.flags & 0b1000 != 0)
+                .then_some(snapshot.fact_sample_length.get()),
+        })
+    }
+
+    /// The error has the [`Parser::location`] of the read
+    pub fn process_data(self, data: &[u8]) -> Result<ProcessDataOutput, Located<Error>> {
+        let location = self.location();
+        self.process(data)
+            .map_err(|error| Located { error, location })
+    }
+
+    fn process(self, data: &[u8]) -> Result<ProcessDataOutput, Error> {
+        check_data_len(self.read_instruction().len, data)?;
+        let options = self.options;
+        let mut stats = self.stats;
+        stats.add_request(data.len());
+        let mut warnings = self.warnings;
+        let mut fact_sample_length = self.fact_sample_length;
+        let stage = match self.stage {
             ParseStage::Riff => {
-                let data = <&[u8; size_of::<RiffChunkHeader>()]>::try_from(data).unwrap();
-                let riff_chunk: &RiffChunkHeader = transmute_ref!(data);
+                let (riff_chunk, container_id) = RiffChunkHeader::read_from_prefix(data).unwrap();
                 if &riff_chunk.chunk_id != b"RIFF" {
                     return Err(Error::UnexpectedChunkId(riff_chunk.chunk_id));
                 }
+                let container_id = Id::try_from(container_id).unwrap();
+                if &container_id != b"WAVE" {
+                    return Err(Error::UnexpectedContainerId(container_id));
+                }
                 let sub_chunks_len = riff_chunk
                     .container_info()
                     .unwrap()
                     .map_err(|_| Error::InvalidRiff)?
                     .sub_chunks_len;
-                Ok(ProcessDataOutput::InProgress(Self {
-                    stage: ParseStage::Fmt {
-                        sub_chunks_len,
-                        position_in_sub_chunks: 0,
-                    },
-                }))
+                options.check_file_len(u64::from(SUB_CHUNKS_OFFSET) + u64::from(sub_chunks_len))?;
+                ParseStage::Fmt {
+                    sub_chunks_len,
+                    position_in_sub_chunks: 0,
+                    after_pad_byte: false,
+                }
             }
             ParseStage::Fmt {
                 sub_chunks_len,
                 position_in_sub_chunks,
+                after_pad_byte,
             } => {
-                let ParseChunkOutput {
-                    parsed_chunk,
-                    next_chunk_relative_position,
-                } = parse_chunk(data[..BUFFER_LEN].try_into().unwrap());
-                if &parsed_chunk.chunk_id == b"fmt " {
-                    let fmt_data_len = parsed_chunk.chunk_len.get();
-                    if fmt_data_len < size_of::<FmtData>().try_into().unwrap() {
-                        return Err(Error::FmtDataTooSmall(fmt_data_len));
+                let parsed_chunk = RiffChunkHeader::read_from_bytes(&data[..BUFFER_LEN]).unwrap();
+                if let Some(position_in_sub_chunks) = options.check_chunk_id(
+                    &mut warnings,
+                    position_in_sub_chunks,
+                    after_pad_byte,
+                    &parsed_chunk,
+                )? {
+                    ParseStage::Fmt {
+                        sub_chunks_len,
+                        position_in_sub_chunks,
+                        after_pad_byte: false,
                     }
-                    let data = <&[u8; size_of::<FmtData>()]>::try_from(
-                        &data[size_of::<RiffChunkHeader>()..],
-                    )
-                    .unwrap();
-                    Ok(ProcessDataOutput::InProgress(Self {
-                        stage: ParseStage::Data {
-                            sub_chunks_len,
-                            position_in_sub_chunks: position_in_sub_chunks
-                                + next_chunk_relative_position,
-                            fmt_data: *transmute_ref!(data),
-                        },
-                    }))
                 } else {
-                    Ok(ProcessDataOutput::InProgress(Self {
-                        stage: ParseStage::Fmt {
+                    let next_position_in_sub_chunks = options.next_chunk_position(
+                        &mut warnings,
+                        position_in_sub_chunks,
+                        sub_chunks_len,
+                        &parsed_chunk,
+                    )?;
+                    let after_pad_byte = parsed_chunk.chunk_len.get() % 2 == 1;
+                    if &parsed_chunk.chunk_id == b"fmt " {
+                        let fmt_data_len = parsed_chunk.chunk_len.get();
+                        if fmt_data_len < size_of::<FmtData>().try_into().unwrap() {
+                            return Err(Error::FmtDataTooSmall(fmt_data_len));
+                        }
+                        let data = <&[u8; size_of::<FmtData>()]>::try_from(
+                            &data[size_of::<RiffChunkHeader>()..],
+                        )
+                        .unwrap();
+                        let fmt_data: FmtData = *transmute_ref!(data);
+                        if options.validate_fmt {
+                            fmt_data.validate().map_err(Error::InvalidFmt)?;
+                        }
+                        let extension_len = (fmt_data_len
+                            - u32::try_from(size_of::<FmtData>()).unwrap())
+                        .min(size_of::<FmtExtension>().try_into().unwrap());
+                        if fmt_data.format_tag.get() != WAVE_FORMAT_EXTENSIBLE {
+                            ParseStage::Data {
+                                sub_chunks_len,
+                                position_in_sub_chunks: next_position_in_sub_chunks,
+                                after_pad_byte,
+                                fmt_data,
+                                fmt_extension: None,
+                                fact_position: None,
+                            }
+                        } else if extension_len == 0 {
+                            if options.strictness == Strictness::Strict {
+                                return Err(Error::FmtDataTooSmall(fmt_data_len));
+                            }
+                            warnings.truncated_extensible_fmt = true;
+                            ParseStage::Data {
+                                sub_chunks_len,
+                                position_in_sub_chunks: next_position_in_sub_chunks,
+                                after_pad_byte,
+                                fmt_data,
+                                fmt_extension: None,
+                                fact_position: None,
+                            }
+                        } else {
+                            ParseStage::FmtExtension {
+                                sub_chunks_len,
+                                position_in_sub_chunks: next_position_in_sub_chunks,
+                                after_pad_byte,
+                                fmt_data,
+                                extension_position: add_in_bounds(
+                                    add_in_bounds(SUB_CHUNKS_OFFSET, position_in_sub_chunks),
+                                    u32::try_from(
+                                        size_of::<RiffChunkHeader>() + size_of::<FmtData>(),
+                                    )
+                                    .unwrap(),
+                                ),
+                                extension_len,
+                            }
+                        }
+                    } else {
+                        stats.add_chunk_skipped();
+                        ParseStage::Fmt {
                             sub_chunks_len,
-                            position_in_sub_chunks: position_in_sub_chunks
-                                + next_chunk_relative_position,
-                        },
-                    }))
+                            position_in_sub_chunks: next_position_in_sub_chunks,
+                            after_pad_byte,
+                        }
+                    }
+                }
+            }
+            ParseStage::FmtExtension {
+                sub_chunks_len,
+                position_in_sub_chunks,
+                after_pad_byte,
+                fmt_data,
+                extension_position: _extension_position,
+                extension_len: _extension_len,
+            } => {
+                let fmt_extension =
+                    FmtExtension::read_from_bytes(data)
+                        .ok()
+                        .filter(|fmt_extension| {
+                            usize::from(fmt_extension.cb_size.get())
+                                >= size_of::<FmtExtension>() - size_of::<U16>()
+                        });
+                if fmt_extension.is_none() {
+                    if options.strictness == Strictness::Strict {
+                        return Err(Error::InvalidFmtExtension);
+                    }
+                    warnings.truncated_extensible_fmt = true;
+                }
+                ParseStage::Data {
+                    sub_chunks_len,
+                    position_in_sub_chunks,
+                    after_pad_byte,
+                    fmt_data,
+                    fmt_extension,
+                    fact_position: None,
+                }
+            }
+            ParseStage::Data {
+                sub_chunks_len,
+                position_in_sub_chunks,
+                after_pad_byte,
+                fmt_data,
+                fmt_extension,
+                fact_position: Some(_),
+            } => {
+                fact_sample_length = Some(U32::read_from_bytes(data).unwrap().get());
+                ParseStage::Data {
+                    sub_chunks_len,
+                    position_in_sub_chunks,
+                    after_pad_byte,
+                    fmt_data,
+                    fmt_extension,
+                    fact_position: None,
                 }
             }
             ParseStage::Data {
                 sub_chunks_len,
                 position_in_sub_chunks,
+                after_pad_byte,
                 fmt_data,
+                fmt_extension,
+                fact_position: None,
             } => {
-                let ParseChunkOutput {
-                    parsed_chunk,
-                    next_chunk_relative_position,
-                } = parse_chunk(data.try_into().unwrap());
-                if &parsed_chunk.chunk_id == b"data" {
-                    Ok(ProcessDataOutput::Done(WavMetaData {
+                let parsed_chunk = RiffChunkHeader::read_from_bytes(data).unwrap();
+                if let Some(position_in_sub_chunks) = options.check_chunk_id(
+                    &mut warnings,
+                    position_in_sub_chunks,
+                    after_pad_byte,
+                    &parsed_chunk,
+                )? {
+                    ParseStage::Data {
+                        sub_chunks_len,
+                        position_in_sub_chunks,
+                        after_pad_byte: false,
+                        fmt_data,
+                        fmt_extension,
+                        fact_position: None,
+                    }
+                } else if &parsed_chunk.chunk_id == b"data" {
+                    let data_position = add_in_bounds(
+                        add_in_bounds(SUB_CHUNKS_OFFSET, position_in_sub_chunks),
+                        u32::try_from(size_of::<RiffChunkHeader>()).unwrap(),
+                    );
+                    let data_len = parsed_chunk.chunk_len.get();
+                    options.check_file_len(u64::from(data_position) + u64::from(data_len))?;
+                    if u64::from(data_position) + u64::from(data_len)
+                        > u64::from(SUB_CHUNKS_OFFSET) + u64::from(sub_chunks_len)
+                    {
+                        match options.strictness {
+                            Strictness::Strict => {
+                                return Err(Error::ChunkExtendsPastEnd {
+                                    chunk_id: parsed_chunk.chunk_id,
+                                    position: add_in_bounds(
+                                        SUB_CHUNKS_OFFSET,
+                                        position_in_sub_chunks,
+                                    )
+                                    .into(),
+                                });
+                            }
+                            Strictness::Lenient => warnings.data_past_riff_end = true,
+                        }
+                    }
+                    return Ok(ProcessDataOutput::Done(WavMetaData {
                         fmt: fmt_data,
-                        data_position: SUB_CHUNKS_OFFSET
-                            + position_in_sub_chunks
-                            + u32::try_from(size_of::<RiffChunkHeader>()).unwrap(),
-                        data_len: parsed_chunk.chunk_len.get(),
-                    }))
+                        fmt_extension,
+                        data_position: data_position.into(),
+                        data_len,
+                        sub_chunks_len,
+                        fact_sample_length,
+                        stats,
+                        warnings,
+                    }));
                 } else {
-                    Ok(ProcessDataOutput::InProgress(Self {
-                        stage: ParseStage::Data {
+                    let read_fact = parsed_chunk.chunk_id == FACT_ID
+                        && usize::try_from(parsed_chunk.chunk_len.get())
+                            .is_ok_and(|len| len >= size_of::<FactData>())
+                        && fact_sample_length.is_none();
+                    if !read_fact {
+                        stats.add_chunk_skipped();
+                    }
+                    ParseStage::Data {
+                        sub_chunks_len,
+                        position_in_sub_chunks: options.next_chunk_position(
+                            &mut warnings,
+                            position_in_sub_chunks,
                             sub_chunks_len,
-                            position_in_sub_chunks: position_in_sub_chunks
-                                + next_chunk_relative_position,
-                            fmt_data,
-                        },
-                    }))
+                            &parsed_chunk,
+                        )?,
+                        after_pad_byte: parsed_chunk.chunk_len.get() % 2 == 1,
+                        fmt_data,
+                        fmt_extension,
+                        fact_position: read_fact.then(|| {
+                            add_in_bounds(
+                                position_in_sub_chunks,
+                                size_of::<RiffChunkHeader>().try_into().unwrap(),
+                            )
+                        }),
+                    }
+                }
+            }
+        };
+        if let ParseStage::Fmt {
+            position_in_sub_chunks,
+            ..
+        }
+        | ParseStage::Data {
+            position_in_sub_chunks,
+            ..
+        } = stage
+        {
+            options.limits.check(
+                stats.chunks_skipped,
+                position_in_sub_chunks.into(),
+                add_in_bounds(SUB_CHUNKS_OFFSET, position_in_sub_chunks).into(),
+            )?;
+        }
+        Ok(ProcessDataOutput::InProgress(Self {
+            stage,
+            options,
+            stats,
+            warnings,
+            fact_sample_length,
+        }))
+    }
+}
+
+impl ParseOptions {
+    /// Makes sure that something that ends at `end` is in the file
+    fn check_file_len(&self, end: u64) -> Result<(), Error> {
+        match self.file_len {
+            Some(file_len) if end > file_len => Err(Error::FileTruncated {
+                missing: end - file_len,
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Returns the position to read the chunk header at again if `chunk` doesn't have a valid id
+    /// and it looks like the chunk before it is missing its pad byte
+    fn check_chunk_id(
+        &self,
+        warnings: &mut Warnings,
+        position_in_sub_chunks: u32,
+        after_pad_byte: bool,
+        chunk: &RiffChunkHeader,
+    ) -> Result<Option<u32>, Error> {
+        if is_plausible_id(&chunk.chunk_id) {
+            return Ok(None);
+        }
+        match self.strictness {
+            Strictness::Strict => Err(Error::InvalidChunkId {
+                chunk_id: chunk.chunk_id,
+                position: add_in_bounds(SUB_CHUNKS_OFFSET, position_in_sub_chunks).into(),
+            }),
+            Strictness::Lenient if after_pad_byte => {
+                warnings.missing_pad_byte = true;
+                Ok(Some(position_in_sub_chunks - 1))
+            }
+            Strictness::Lenient => Ok(None),
+        }
+    }
+
+    /// Returns the position of the chunk after `chunk`, which is at `position_in_sub_chunks`.
+    /// Also makes sure that `chunk` doesn't go past the end of the sub chunks, and that the
+    /// [`ReadInstruction`] for the next chunk will not overflow.
+    fn next_chunk_position(
+        &self,
+        warnings: &mut Warnings,
+        position_in_sub_chunks: u32,
+        sub_chunks_len: u32,
+        chunk: &RiffChunkHeader,
+    ) -> Result<u32, Error> {
+        let overflow = |_: Overflow| Error::ChunkSizeOverflow {
+            chunk_id: chunk.chunk_id,
+            position: add_in_bounds(SUB_CHUNKS_OFFSET, position_in_sub_chunks).into(),
+        };
+        let data_position = checked_advance(
+            position_in_sub_chunks,
+            size_of::<RiffChunkHeader>().try_into().unwrap(),
+            false,
+        )
+        .map_err(overflow)?;
+        self.check_file_len(
+            u64::from(SUB_CHUNKS_OFFSET)
+                + u64::from(data_position)
+                + u64::from(chunk.chunk_len.get()),
+        )?;
+        if checked_advance(data_position, chunk.chunk_len.get(), false).map_err(overflow)?
+            > sub_chunks_len
+        {
+            match self.strictness {
+                Strictness::Strict => {
+                    return Err(Error::ChunkExtendsPastEnd {
+                        chunk_id: chunk.chunk_id,
+                        position: add_in_bounds(SUB_CHUNKS_OFFSET, position_in_sub_chunks).into(),
+                    });
                 }
+                Strictness::Lenient => warnings.riff_len_too_small = true,
             }
         }
+        let position =
+            checked_advance(data_position, chunk.chunk_len.get(), true).map_err(overflow)?;
+        checked_advance(
+            checked_advance(SUB_CHUNKS_OFFSET, position, false).map_err(overflow)?,
+            Parser::MAX_BUFFER_LEN.try_into().unwrap(),
+            false,
+        )
+        .map_err(overflow)?;
+        Ok(position)
     }
 }
@@ -0,0 +1,117 @@
+//! Going through the sub chunks of a `LIST` chunk.
+//!
+//! The data of a `LIST` chunk starts with a list type, such as `INFO` or `adtl`, followed by sub
+//! chunks that have the same headers and padding as the chunks in the `RIFF` chunk. [`ListChunks`]
+//! goes through them for any list type. [`InfoReader`](crate::info::InfoReader) and
+//! [`AdtlReader`](crate::adtl::AdtlReader) are built on it.
+
+use pure_riff::{Id, RiffChunkHeader};
+use zerocopy::FromBytes;
+
+use crate::{Error, ReadInstruction, arithmetic::checked_advance, check_read, scan::ChunkInfo};
+
+pub const LIST_ID: Id = *b"LIST";
+
+const HEADER_LEN: u32 = size_of::<RiffChunkHeader>() as u32;
+
+enum ListStage {
+    ListType,
+    SubChunk { position: u64 },
+}
+
+/// Goes through the sub chunks of a `LIST` chunk
+pub struct ListChunks {
+    stage: ListStage,
+    list_position: u64,
+    end: u64,
+    prefix_len: u32,
+}
+
+/// Something that [`ListChunks`] found
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ListItem<'a> {
+    /// Always the first item
+    ListType(Id),
+    SubChunk {
+        chunk: ChunkInfo,
+        /// The start of the data of the sub chunk. It is shorter than the prefix len if the sub
+        /// chunk is shorter, or if it goes past the end of the `LIST` chunk.
+        prefix: &'a [u8],
+    },
+}
+
+impl ListChunks {
+    /// Goes through the sub chunks of `list`. The first `prefix_len` bytes of the data of each sub
+    /// chunk are read with its header, which is enough for fixed-size fields.
+    pub fn new(list: &ChunkInfo, prefix_len: u32) -> Self {
+        Self {
+            stage: ListStage::ListType,
+            list_position: list.data_position(),
+            end: list.data_position().saturating_add(list.len.into()),
+            prefix_len,
+        }
+    }
+
+    /// [`None`] if there are no more sub chunks
+    pub fn read_instruction(&self) -> Option<ReadInstruction> {
+        match self.stage {
+            ListStage::ListType => Some(ReadInstruction {
+                position: self.list_position,
+                len: size_of::<Id>().try_into().unwrap(),
+            }),
+            ListStage::SubChunk { position } => {
+                (position.saturating_add(HEADER_LEN.into()) <= self.end).then(|| ReadInstruction {
+                    position,
+                    len: (self.end - position)
+                        .min(HEADER_LEN.saturating_add(self.prefix_len).into())
+                        .try_into()
+                        .unwrap(),
+                })
+            }
+        }
+    }
+
+    /// Returns what was found and the reader for the rest of the sub chunks
+    pub fn process_data(self, data: &[u8]) -> Result<(ListItem<'_>, Self), Error> {
+        check_read(self.read_instruction(), data)?;
+        match self.stage {
+            ListStage::ListType => Ok((
+                ListItem::ListType(Id::try_from(data).unwrap()),
+                Self {
+                    stage: ListStage::SubChunk {
+                        position: self.list_position + u64::try_from(size_of::<Id>()).unwrap(),
+                    },
+                    ..self
+                },
+            )),
+            ListStage::SubChunk { position } => {
+                let (header, prefix) = RiffChunkHeader::read_from_prefix(data).unwrap();
+                let chunk = ChunkInfo {
+                    chunk_id: header.chunk_id,
+                    position,
+                    len: header.chunk_len.get(),
+                };
+                // Only the part that is in the sub chunk
+                let prefix =
+                    &prefix[..prefix.len().min(chunk.len.try_into().unwrap_or(usize::MAX))];
+                Ok((
+                    ListItem::SubChunk { chunk, prefix },
+                    Self {
+                        stage: ListStage::SubChunk {
+                            position: checked_advance(
+                                chunk.data_position(),
+                                chunk.len.into(),
+                                true,
+                            )
+                            .map_err(|_| Error::ChunkSizeOverflow {
+                                chunk_id: chunk.chunk_id,
+                                position: chunk.position,
+                            })?,
+                        },
+                        ..self
+                    },
+                ))
+            }
+        }
+    }
+}
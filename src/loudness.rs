@@ -0,0 +1,295 @@
+//! Measuring loudness like EBU R128, which uses ITU-R BS.1770.
+//!
+//! # Usage
+//! Create a [`Loudness`] with the sample rate, and analyze the samples with an
+//! [`Analyzer`](crate::analysis::Analyzer). Then use [`Loudness::integrated`] and
+//! [`Loudness::range`]. To make the tracks of a playlist equally loud, play each track with the
+//! [`Gain`] from [`Loudness::normalize`].
+//!
+//! The gated loudness is computed from histograms with a resolution of 0.1 LU, so that nothing has
+//! to be stored for every block. This is the same as the histogram mode of libebur128.
+
+use crate::{
+    analysis::Analysis,
+    dsp::{Gain, UNITY_GAIN},
+    frames::Frame,
+};
+
+/// Loudness below this isn't measured, in LUFS
+const ABSOLUTE_GATE: f64 = -70.0;
+/// The loudness of the last bin, in LUFS. Louder blocks go in the last bin.
+const MAX_LOUDNESS: f64 = 5.0;
+const BIN_WIDTH: f64 = 0.1;
+const BINS: usize = ((MAX_LOUDNESS - ABSOLUTE_GATE) / BIN_WIDTH) as usize;
+
+/// Blocks are made of sub blocks of 100 ms
+const SUB_BLOCKS_PER_SECOND: u32 = 10;
+/// The 400 ms blocks for momentary and integrated loudness
+const MOMENTARY_SUB_BLOCKS: usize = 4;
+/// The 3 s blocks for short-term loudness and loudness range
+const SHORT_TERM_SUB_BLOCKS: usize = 30;
+
+fn loudness(power: f64) -> f64 {
+    -0.691 + 10.0 * libm::log10(power)
+}
+
+fn power(loudness: f64) -> f64 {
+    libm::pow(10.0, (loudness + 0.691) / 10.0)
+}
+
+/// A biquad filter in transposed direct form II
+#[derive(Debug, Clone, Copy)]
+struct Biquad {
+    b: [f64; 3],
+    a: [f64; 2],
+    state: [f64; 2],
+}
+
+impl Biquad {
+    fn process(&mut self, x: f64) -> f64 {
+        let y = self.b[0] * x + self.state[0];
+        self.state[0] = self.b[1] * x - self.a[0] * y + self.state[1];
+        self.state[1] = self.b[2] * x - self.a[1] * y;
+        y
+    }
+}
+
+/// The two stages of the K-weighting filter, which are a high shelf and a high pass
+fn k_weighting(sample_rate: u32) -> [Biquad; 2] {
+    let sample_rate = f64::from(sample_rate);
+    let shelf = {
+        let k = libm::tan(core::f64::consts::PI * 1681.974450955533 / sample_rate);
+        let q = 0.7071752369554196;
+        let vh = libm::pow(10.0, 3.999843853973347 / 20.0);
+        let vb = libm::pow(vh, 0.4996667741545416);
+        let a0 = 1.0 + k / q + k * k;
+        Biquad {
+            b: [
+                (vh + vb * k / q + k * k) / a0,
+                2.0 * (k * k - vh) / a0,
+                (vh - vb * k / q + k * k) / a0,
+            ],
+            a: [2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+            state: [0.0; 2],
+        }
+    };
+    let high_pass = {
+        let k = libm::tan(core::f64::consts::PI * 38.13547087602444 / sample_rate);
+        let q = 0.5003270373238773;
+        let a0 = 1.0 + k / q + k * k;
+        Biquad {
+            b: [1.0, -2.0, 1.0],
+            a: [2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+            state: [0.0; 2],
+        }
+    };
+    [shelf, high_pass]
+}
+
+/// The number of blocks in each 0.1 LU from [`ABSOLUTE_GATE`] to [`MAX_LOUDNESS`]
+#[derive(Debug, Clone)]
+struct Histogram {
+    counts: [u32; BINS],
+    blocks: u64,
+    /// The sum of the power of the blocks
+    power: f64,
+}
+
+impl Histogram {
+    fn bin(loudness: f64) -> usize {
+        // `as` saturates
+        (((loudness - ABSOLUTE_GATE) / BIN_WIDTH) as usize).min(BINS - 1)
+    }
+
+    /// The loudness in the middle of a bin
+    fn bin_loudness(bin: usize) -> f64 {
+        ABSOLUTE_GATE + (bin as f64 + 0.5) * BIN_WIDTH
+    }
+
+    fn add(&mut self, power: f64) {
+        if loudness(power) < ABSOLUTE_GATE {
+            return;
+        }
+        self.counts[Self::bin(loudness(power))] += 1;
+        self.blocks += 1;
+        self.power += power;
+    }
+
+    /// The bins that are louder than the relative gate, which is `offset` LU from the loudness of
+    /// all of the blocks. [`None`] if there are no blocks.
+    fn gated(&self, offset: f64) -> Option<(usize, &[u32])> {
+        if self.blocks == 0 {
+            return None;
+        }
+        let gate = loudness(self.power / self.blocks as f64) + offset;
+        let start = Self::bin(gate.max(ABSOLUTE_GATE));
+        Some((start, &self.counts[start..]))
+    }
+}
+
+impl Default for Histogram {
+    fn default() -> Self {
+        Self {
+            counts: [0; BINS],
+            blocks: 0,
+            power: 0.0,
+        }
+    }
+}
+
+/// An [`Analysis`] that measures the loudness. Channels after the first `CHANNELS` are ignored.
+#[derive(Debug, Clone)]
+pub struct Loudness<const CHANNELS: usize = 2> {
+    filters: [[Biquad; 2]; CHANNELS],
+    weights: [f64; CHANNELS],
+    sub_block_len: u32,
+    frames_in_sub_block: u32,
+    /// The weighted sum of the squares of the filtered samples in the current sub block
+    sum_of_squares: f64,
+    /// The power of the last sub blocks, as a ring buffer
+    sub_blocks: [f64; SHORT_TERM_SUB_BLOCKS],
+    /// The number of sub blocks so far
+    sub_block_count: u64,
+    momentary: Histogram,
+    short_term: Histogram,
+    max_momentary: f64,
+    max_short_term: f64,
+}
+
+impl<const CHANNELS: usize> Loudness<CHANNELS> {
+    /// [`None`] if the sample rate is less than 10 Hz
+    pub fn new(sample_rate: u32) -> Option<Self> {
+        let sub_block_len = sample_rate / SUB_BLOCKS_PER_SECOND;
+        (sub_block_len != 0).then(|| Self {
+            filters: [k_weighting(sample_rate); CHANNELS],
+            weights: [1.0; CHANNELS],
+            sub_block_len,
+            frames_in_sub_block: 0,
+            sum_of_squares: 0.0,
+            sub_blocks: [0.0; SHORT_TERM_SUB_BLOCKS],
+            sub_block_count: 0,
+            momentary: Default::default(),
+            short_term: Default::default(),
+            max_momentary: 0.0,
+            max_short_term: 0.0,
+        })
+    }
+
+    /// How much each channel counts. The default is 1.0 for every channel. For 5.1, use 1.0 for
+    /// the front channels, 0.0 for the LFE channel and 1.41 for the surround channels.
+    pub fn weights(mut self, weights: [f32; CHANNELS]) -> Self {
+        self.weights = weights.map(f64::from);
+        self
+    }
+
+    /// The mean power of the last `len` sub blocks
+    fn mean_power(&self, len: usize) -> f64 {
+        let end = (self.sub_block_count % SHORT_TERM_SUB_BLOCKS as u64) as usize;
+        (1..=len)
+            .map(|i| self.sub_blocks[(end + SHORT_TERM_SUB_BLOCKS - i) % SHORT_TERM_SUB_BLOCKS])
+            .sum::<f64>()
+            / len as f64
+    }
+
+    fn end_sub_block(&mut self) {
+        let index = (self.sub_block_count % SHORT_TERM_SUB_BLOCKS as u64) as usize;
+        self.sub_blocks[index] = self.sum_of_squares / f64::from(self.sub_block_len);
+        self.sub_block_count += 1;
+        self.sum_of_squares = 0.0;
+        self.frames_in_sub_block = 0;
+        if self.sub_block_count >= MOMENTARY_SUB_BLOCKS as u64 {
+            let power = self.mean_power(MOMENTARY_SUB_BLOCKS);
+            self.momentary.add(power);
+            self.max_momentary = self.max_momentary.max(power);
+        }
+        if self.sub_block_count >= SHORT_TERM_SUB_BLOCKS as u64 {
+            let power = self.mean_power(SHORT_TERM_SUB_BLOCKS);
+            self.short_term.add(power);
+            self.max_short_term = self.max_short_term.max(power);
+        }
+    }
+
+    /// The integrated loudness in LUFS. [`None`] if every block was quieter than -70 LUFS, or if
+    /// there were less than 400 ms of samples.
+    pub fn integrated(&self) -> Option<f32> {
+        let (start, counts) = self.momentary.gated(-10.0)?;
+        let (power, blocks) =
+            counts
+                .iter()
+                .enumerate()
+                .fold((0.0, 0), |(power, blocks), (i, &count)| {
+                    (
+                        power + f64::from(count) * self.power_of_bin(start + i),
+                        blocks + u64::from(count),
+                    )
+                });
+        (blocks != 0).then(|| loudness(power / blocks as f64) as f32)
+    }
+
+    fn power_of_bin(&self, bin: usize) -> f64 {
+        power(Histogram::bin_loudness(bin))
+    }
+
+    /// The loudness range in LU, which is how much the short-term loudness changes. [`None`] if
+    /// there were less than 3 s of samples that were louder than -70 LUFS.
+    pub fn range(&self) -> Option<f32> {
+        let (_, counts) = self.short_term.gated(-20.0)?;
+        let blocks = counts.iter().map(|&count| u64::from(count)).sum::<u64>();
+        let percentile = |percent: u64| {
+            // The bin of the block at `percent` percent, counting from the quietest
+            let index = blocks * percent / 100;
+            let mut below = 0;
+            counts.iter().position(|&count| {
+                below += u64::from(count);
+                below > index
+            })
+        };
+        let low = percentile(10)?;
+        let high = percentile(95)?;
+        Some(((high - low) as f64 * BIN_WIDTH) as f32)
+    }
+
+    /// The loudest 400 ms in LUFS. [`None`] if there were less than 400 ms of samples.
+    pub fn max_momentary(&self) -> Option<f32> {
+        (self.sub_block_count >= MOMENTARY_SUB_BLOCKS as u64)
+            .then(|| loudness(self.max_momentary) as f32)
+    }
+
+    /// The loudest 3 s in LUFS. [`None`] if there were less than 3 s of samples.
+    pub fn max_short_term(&self) -> Option<f32> {
+        (self.sub_block_count >= SHORT_TERM_SUB_BLOCKS as u64)
+            .then(|| loudness(self.max_short_term) as f32)
+    }
+
+    /// The gain that makes the integrated loudness `target` LUFS, such as -23.0 for EBU R128 or
+    /// -14.0 for streaming. It is unity if the loudness couldn't be measured.
+    pub fn normalize_gain(&self, target: f32) -> i32 {
+        self.integrated().map_or(UNITY_GAIN, |integrated| {
+            let gain = libm::pow(10.0, f64::from(target - integrated) / 20.0);
+            // `as` saturates
+            (gain * f64::from(UNITY_GAIN)) as i32
+        })
+    }
+
+    /// A [`Gain`] stage that makes the integrated loudness `target` LUFS when playing the samples
+    pub fn normalize(&self, target: f32) -> Gain {
+        Gain::new(self.normalize_gain(target))
+    }
+}
+
+impl<const CHANNELS: usize> Analysis for Loudness<CHANNELS> {
+    fn frame(&mut self, frame: Frame<'_>) {
+        for ((sample, filters), weight) in frame.samples().zip(&mut self.filters).zip(&self.weights)
+        {
+            let sample = f64::from(sample) / 2147483648.0;
+            let filtered = filters
+                .iter_mut()
+                .fold(sample, |sample, filter| filter.process(sample));
+            self.sum_of_squares += weight * filtered * filtered;
+        }
+        self.frames_in_sub_block += 1;
+        if self.frames_in_sub_block == self.sub_block_len {
+            self.end_sub_block();
+        }
+    }
+}
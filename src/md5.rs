@@ -0,0 +1,199 @@
+//! The `md5 ` chunk, which has the MD5 digest of the data of the `data` chunk.
+//!
+//! SoX writes this chunk, so that copies of a file can be checked.
+//!
+//! # Usage
+//! Create a [`DataMd5`] and drive it like the [`Parser`](crate::Parser) with
+//! [`DataMd5::read_instruction`] and [`DataMd5::process_data`]. It reads the `data` chunk from
+//! start to end, and gives you the digest. To check a file, find the `md5 ` chunk with
+//! [`scan`](crate::scan), read its [`Md5Data`], and compare the digests with [`Md5Data::matches`].
+//! To add the chunk to a file, write the [`Md5Data`] with
+//! [`Writer::write_chunk`](crate::writer::Writer::write_chunk).
+
+use pure_riff::Id;
+use zerocopy::{FromBytes, Immutable, IntoBytes, KnownLayout};
+
+use crate::{Error, ReadInstruction, WavMetaData, check_data_len, writer::WriteChunk};
+
+pub const MD5_ID: Id = *b"md5 ";
+
+/// The data of an `md5 ` chunk
+#[derive(Debug, Clone, Copy, PartialEq, Eq, FromBytes, IntoBytes, Immutable, KnownLayout)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[repr(C)]
+pub struct Md5Data {
+    pub digest: [u8; 16],
+}
+
+impl Md5Data {
+    pub fn matches(&self, digest: &[u8; 16]) -> bool {
+        &self.digest == digest
+    }
+}
+
+impl WriteChunk for Md5Data {
+    fn chunk_id(&self) -> Id {
+        MD5_ID
+    }
+
+    fn data_len(&self) -> u32 {
+        size_of::<Self>().try_into().unwrap()
+    }
+
+    fn write_data(&self, buffer: &mut [u8]) {
+        buffer.copy_from_slice(self.as_bytes());
+    }
+}
+
+const S: [u32; 64] = [
+    7, 12, 17, 22, 7, 12, 17, 22, 7, 12, 17, 22, 7, 12, 17, 22, 5, 9, 14, 20, 5, 9, 14, 20, 5, 9,
+    14, 20, 5, 9, 14, 20, 4, 11, 16, 23, 4, 11, 16, 23, 4, 11, 16, 23, 4, 11, 16, 23, 6, 10, 15,
+    21, 6, 10, 15, 21, 6, 10, 15, 21, 6, 10, 15, 21,
+];
+
+/// `floor(abs(sin(i + 1)) * 2^32)`
+const K: [u32; 64] = [
+    0xd76aa478, 0xe8c7b756, 0x242070db, 0xc1bdceee, 0xf57c0faf, 0x4787c62a, 0xa8304613, 0xfd469501,
+    0x698098d8, 0x8b44f7af, 0xffff5bb1, 0x895cd7be, 0x6b901122, 0xfd987193, 0xa679438e, 0x49b40821,
+    0xf61e2562, 0xc040b340, 0x265e5a51, 0xe9b6c7aa, 0xd62f105d, 0x02441453, 0xd8a1e681, 0xe7d3fbc8,
+    0x21e1cde6, 0xc33707d6, 0xf4d50d87, 0x455a14ed, 0xa9e3e905, 0xfcefa3f8, 0x676f02d9, 0x8d2a4c8a,
+    0xfffa3942, 0x8771f681, 0x6d9d6122, 0xfde5380c, 0xa4beea44, 0x4bdecfa9, 0xf6bb4b60, 0xbebfbc70,
+    0x289b7ec6, 0xeaa127fa, 0xd4ef3085, 0x04881d05, 0xd9d4d039, 0xe6db99e5, 0x1fa27cf8, 0xc4ac5665,
+    0xf4292244, 0x432aff97, 0xab9423a7, 0xfc93a039, 0x655b59c3, 0x8f0ccc92, 0xffeff47d, 0x85845dd1,
+    0x6fa87e4f, 0xfe2ce6e0, 0xa3014314, 0x4e0811a1, 0xf7537e82, 0xbd3af235, 0x2ad7d2bb, 0xeb86d391,
+];
+
+const BLOCK_LEN: usize = 64;
+
+/// Computes an MD5 digest of bytes that are given a part at a time
+#[derive(Debug, Clone)]
+pub struct Md5 {
+    state: [u32; 4],
+    /// Bytes that don't make a whole block yet
+    buffer: [u8; BLOCK_LEN],
+    len: u64,
+}
+
+impl Default for Md5 {
+    fn default() -> Self {
+        Self {
+            state: [0x67452301, 0xefcdab89, 0x98badcfe, 0x10325476],
+            buffer: [0; BLOCK_LEN],
+            len: 0,
+        }
+    }
+}
+
+impl Md5 {
+    pub fn update(&mut self, mut data: &[u8]) {
+        let buffered = (self.len % BLOCK_LEN as u64) as usize;
+        self.len = self.len.wrapping_add(data.len() as u64);
+        if buffered != 0 {
+            let len = data.len().min(BLOCK_LEN - buffered);
+            self.buffer[buffered..][..len].copy_from_slice(&data[..len]);
+            data = &data[len..];
+            if buffered + len < BLOCK_LEN {
+                return;
+            }
+            let block = self.buffer;
+            self.block(&block);
+        }
+        let mut blocks = data.chunks_exact(BLOCK_LEN);
+        for block in &mut blocks {
+            self.block(block.try_into().unwrap());
+        }
+        let rest = blocks.remainder();
+        self.buffer[..rest.len()].copy_from_slice(rest);
+    }
+
+    pub fn finish(mut self) -> [u8; 16] {
+        let bits = self.len.wrapping_mul(8);
+        self.update(&[0x80]);
+        while self.len % BLOCK_LEN as u64 != (BLOCK_LEN - size_of::<u64>()) as u64 {
+            self.update(&[0]);
+        }
+        self.update(&bits.to_le_bytes());
+        let mut digest = [0; 16];
+        for (bytes, word) in digest.chunks_exact_mut(size_of::<u32>()).zip(self.state) {
+            bytes.copy_from_slice(&word.to_le_bytes());
+        }
+        digest
+    }
+
+    fn block(&mut self, block: &[u8; BLOCK_LEN]) {
+        let mut m = [0; 16];
+        for (word, bytes) in m.iter_mut().zip(block.chunks_exact(size_of::<u32>())) {
+            *word = u32::from_le_bytes(bytes.try_into().unwrap());
+        }
+        let [mut a, mut b, mut c, mut d] = self.state;
+        for i in 0..64 {
+            let (f, g) = match i / 16 {
+                0 => ((b & c) | (!b & d), i),
+                1 => ((d & b) | (!d & c), (5 * i + 1) % 16),
+                2 => (b ^ c ^ d, (3 * i + 5) % 16),
+                _ => (c ^ (b | !d), (7 * i) % 16),
+            };
+            let f = f.wrapping_add(a).wrapping_add(K[i]).wrapping_add(m[g]);
+            a = d;
+            d = c;
+            c = b;
+            b = b.wrapping_add(f.rotate_left(S[i]));
+        }
+        for (state, value) in self.state.iter_mut().zip([a, b, c, d]) {
+            *state = state.wrapping_add(value);
+        }
+    }
+}
+
+/// Reads the `data` chunk in order and computes its MD5 digest
+#[derive(Debug, Clone)]
+pub struct DataMd5 {
+    md5: Md5,
+    data_position: u64,
+    data_len: u32,
+    /// The offset in the `data` chunk of the next read
+    offset: u32,
+    read_len: u32,
+}
+
+pub enum Md5ProcessDataOutput {
+    Done([u8; 16]),
+    InProgress(DataMd5),
+}
+
+impl DataMd5 {
+    /// The `data` chunk is read `read_len` bytes at a time
+    pub fn new(meta_data: &WavMetaData, read_len: u32) -> Self {
+        Self {
+            md5: Default::default(),
+            data_position: meta_data.data_position,
+            data_len: meta_data.data_len,
+            offset: 0,
+            read_len: read_len.max(1),
+        }
+    }
+
+    pub fn read_instruction(&self) -> ReadInstruction {
+        ReadInstruction {
+            position: self.data_position.saturating_add(self.offset.into()),
+            len: (self.data_len - self.offset).min(self.read_len),
+        }
+    }
+
+    /// The number of bytes of the `data` chunk that were read so far
+    pub fn offset(&self) -> u32 {
+        self.offset
+    }
+
+    pub fn process_data(mut self, data: &[u8]) -> Result<Md5ProcessDataOutput, Error> {
+        check_data_len(self.read_instruction().len, data)?;
+        self.md5.update(data);
+        self.offset += u32::try_from(data.len()).unwrap();
+        Ok(if self.offset == self.data_len {
+            Md5ProcessDataOutput::Done(self.md5.finish())
+        } else {
+            Md5ProcessDataOutput::InProgress(self)
+        })
+    }
+}
@@ -0,0 +1,252 @@
+//! Mixing two WAVE files into one output, for example background music and a notification sound on
+//! one I2S output.
+//!
+//! # Usage
+//! Make a [`MixerSource`] for each file. It streams the `data` chunk, converts the samples to
+//! signed 16-bit frames with `CHANNELS` channels, and resamples them to the output rate. Give the
+//! sources to a [`Mixer`], and then:
+//! 1. While [`Mixer::read_instruction`] returns something, read the bytes from the file of that
+//!    [`MixerInput`] and call [`Mixer::process_data`]
+//! 2. Call [`Mixer::mix`] to get the summed frames for the output
+//! 3. Stop when [`Mixer::is_done`] returns `true`
+//!
+//! A source can be started or replaced at any time with [`Mixer::play`], and its volume can be
+//! ramped with [`MixerSource::gain`], for example to duck the music while the notification plays.
+
+use crate::{
+    Error, ReadInstruction, WavMetaData,
+    convert::I16Converter,
+    dsp::Gain,
+    playback::{StreamData, StreamDataProcessDataOutput},
+    resample::{Interpolate, Resampler},
+};
+
+/// One of the files that a [`Mixer`] mixes. `LEN` is the number of frames that are buffered.
+#[derive(Debug, Clone)]
+pub struct MixerSource<I, const CHANNELS: usize = 2, const LEN: usize = 256> {
+    /// [`None`] after the last read
+    stream: Option<StreamData>,
+    converter: I16Converter,
+    /// The same sample is put in every channel
+    mono: bool,
+    resampler: Resampler<I, CHANNELS>,
+    gain: Gain,
+    /// Frames that weren't resampled yet
+    input: [[i16; CHANNELS]; LEN],
+    input_start: usize,
+    input_end: usize,
+    /// Resampled frames that weren't mixed yet
+    output: [[i16; CHANNELS]; LEN],
+    output_start: usize,
+    output_end: usize,
+}
+
+impl<I: Interpolate<CHANNELS>, const CHANNELS: usize, const LEN: usize>
+    MixerSource<I, CHANNELS, LEN>
+{
+    /// Returns [`None`] if there are no frames, if the sample format isn't supported by
+    /// [`I16Converter`], or if the WAVE isn't mono and doesn't have `CHANNELS` channels
+    pub fn new(meta_data: &WavMetaData, output_rate: u32) -> Option<Self> {
+        let channels = usize::from(meta_data.fmt.n_channels.get());
+        if channels != 1 && channels != CHANNELS {
+            return None;
+        }
+        let converter = I16Converter::for_meta_data(meta_data)?;
+        let read_len = u32::try_from(LEN)
+            .ok()?
+            .checked_mul(meta_data.fmt.n_block_align.get().into())?;
+        Some(Self {
+            stream: Some(StreamData::new(meta_data, read_len)?),
+            converter,
+            mono: channels == 1,
+            resampler: Resampler::new(meta_data.fmt.n_samples_per_sec.get(), output_rate)?,
+            gain: Default::default(),
+            input: [[0; CHANNELS]; LEN],
+            input_start: 0,
+            input_end: 0,
+            output: [[0; CHANNELS]; LEN],
+            output_start: 0,
+            output_end: 0,
+        })
+    }
+
+    /// The volume of this source. Changes apply to frames that are resampled after the change.
+    pub fn gain(&mut self) -> &mut Gain {
+        &mut self.gain
+    }
+
+    /// If every frame was mixed
+    pub fn is_done(&self) -> bool {
+        self.stream.is_none() && self.input_start == self.input_end && self.available() == 0
+    }
+
+    fn available(&self) -> usize {
+        self.output_end - self.output_start
+    }
+
+    fn read_instruction(&self) -> Option<ReadInstruction> {
+        self.stream
+            .as_ref()
+            .filter(|_| self.input_start == self.input_end)
+            .map(StreamData::read_instruction)
+    }
+
+    fn process_data(&mut self, data: &[u8]) -> Result<(), Error> {
+        let stream = self.stream.take().unwrap();
+        let frames = match stream.process_data(data)? {
+            StreamDataProcessDataOutput::Done(frames) => frames,
+            StreamDataProcessDataOutput::InProgress(frames, stream) => {
+                self.stream = Some(stream);
+                frames
+            }
+        };
+        let samples = self.input.as_flattened_mut();
+        let len = self.converter.convert(frames, samples);
+        self.input_end = if self.mono {
+            // Going backwards doesn't overwrite samples that weren't copied yet
+            for i in (0..len).rev() {
+                let sample = samples[i];
+                samples[i * CHANNELS..][..CHANNELS].fill(sample);
+            }
+            len
+        } else {
+            len / CHANNELS
+        };
+        self.input_start = 0;
+        self.resample();
+        Ok(())
+    }
+
+    /// Resamples as many frames as fit in the output buffer
+    fn resample(&mut self) {
+        self.output
+            .copy_within(self.output_start..self.output_end, 0);
+        self.output_end -= self.output_start;
+        self.output_start = 0;
+        let resampled = self.resampler.process(
+            &self.input[self.input_start..self.input_end],
+            &mut self.output[self.output_end..],
+        );
+        self.gain.apply(
+            self.output[self.output_end..][..resampled.output_frames].as_flattened_mut(),
+            CHANNELS,
+        );
+        self.input_start += resampled.input_frames;
+        self.output_end += resampled.output_frames;
+    }
+}
+
+/// Which source of a [`Mixer`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum MixerInput {
+    First,
+    Second,
+}
+
+impl MixerInput {
+    fn index(self) -> usize {
+        match self {
+            Self::First => 0,
+            Self::Second => 1,
+        }
+    }
+}
+
+/// Read from the file of `input`
+#[derive(Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct MixerReadInstruction {
+    pub input: MixerInput,
+    pub read: ReadInstruction,
+}
+
+/// Sums the frames of two sources with saturation
+#[derive(Debug, Clone)]
+pub struct Mixer<I, const CHANNELS: usize = 2, const LEN: usize = 256> {
+    sources: [Option<MixerSource<I, CHANNELS, LEN>>; 2],
+}
+
+impl<I: Interpolate<CHANNELS>, const CHANNELS: usize, const LEN: usize> Mixer<I, CHANNELS, LEN> {
+    /// Either source can be [`None`], which is silence
+    pub fn new(
+        first: Option<MixerSource<I, CHANNELS, LEN>>,
+        second: Option<MixerSource<I, CHANNELS, LEN>>,
+    ) -> Self {
+        Self {
+            sources: [first, second],
+        }
+    }
+
+    /// Replaces a source. [`None`] stops it.
+    pub fn play(&mut self, input: MixerInput, source: Option<MixerSource<I, CHANNELS, LEN>>) {
+        self.sources[input.index()] = source;
+    }
+
+    /// [`None`] if the source is done or was never started
+    pub fn source(&mut self, input: MixerInput) -> Option<&mut MixerSource<I, CHANNELS, LEN>> {
+        self.sources[input.index()].as_mut()
+    }
+
+    /// [`None`] if the sources don't need to be read until some frames are mixed
+    pub fn read_instruction(&self) -> Option<MixerReadInstruction> {
+        // Read for the source with the fewest frames, so that neither one runs out
+        [MixerInput::First, MixerInput::Second]
+            .into_iter()
+            .filter_map(|input| {
+                let source = self.sources[input.index()].as_ref()?;
+                Some((source.available(), input, source.read_instruction()?))
+            })
+            .min_by_key(|(available, ..)| *available)
+            .map(|(_, input, read)| MixerReadInstruction { input, read })
+    }
+
+    /// `data` is the bytes of the [`MixerReadInstruction`] for `input`
+    pub fn process_data(&mut self, input: MixerInput, data: &[u8]) -> Result<(), Error> {
+        match self.sources[input.index()]
+            .as_mut()
+            .filter(|source| source.read_instruction().is_some())
+        {
+            Some(source) => source.process_data(data),
+            None => Err(Error::NoPendingRead),
+        }
+    }
+
+    /// Mixes as many frames as possible into `output`. Returns the number of frames. It is 0 if a
+    /// source needs to be read first.
+    pub fn mix(&mut self, output: &mut [[i16; CHANNELS]]) -> usize {
+        let mut frames = output.len();
+        for source in self.sources.iter_mut().flatten() {
+            source.resample();
+            // A source that ended is silence
+            if !source.is_done() {
+                frames = frames.min(source.available());
+            }
+        }
+        let output = &mut output[..frames];
+        output.fill([0; CHANNELS]);
+        for source in self.sources.iter_mut().flatten() {
+            let len = frames.min(source.available());
+            for (output, frame) in output
+                .iter_mut()
+                .zip(&source.output[source.output_start..][..len])
+            {
+                for (output, sample) in output.iter_mut().zip(frame) {
+                    *output = output.saturating_add(*sample);
+                }
+            }
+            source.output_start += len;
+        }
+        for source in &mut self.sources {
+            if source.as_ref().is_some_and(MixerSource::is_done) {
+                *source = None;
+            }
+        }
+        frames
+    }
+
+    /// If both sources are done
+    pub fn is_done(&self) -> bool {
+        self.sources.iter().all(Option::is_none)
+    }
+}
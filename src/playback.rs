@@ -0,0 +1,771 @@
+//! Streaming the samples to an output, such as an I2S peripheral with DMA.
+//!
+//! # Double buffering
+//! While the output plays one buffer, the other buffer is filled with the next samples.
+//! [`DoubleBuffer`] keeps track of which buffer is which:
+//! 1. While [`DoubleBuffer::fill_instruction`] returns something, read the samples into that
+//!    buffer and call [`DoubleBuffer::filled`]
+//! 2. When the output is ready for a buffer, give it the one from
+//!    [`DoubleBuffer::play_instruction`] and call [`DoubleBuffer::playing`]. Call
+//!    [`DoubleBuffer::played`] when the output is done with it.
+//! 3. Stop when [`DoubleBuffer::is_done`] returns `true`
+//!
+//! # Streaming without double buffering
+//! [`StreamData`] reads the `data` chunk in whole frames, so a frame is never split between two
+//! reads. To fast-forward or rewind, use [`StreamData::seek_to_frame`] or
+//! [`StreamData::seek_to_ns`] and keep going from the new [`ReadInstruction`].
+//!
+//! # Loops
+//! [`LoopPlayback`] plays a loop from the `smpl` chunk, and then the rest of the `data` chunk.
+//!
+//! # Gapless playback
+//! [`Gapless`] plays one track after another. The metadata of the next track is parsed while the
+//! current track is still playing, and the first frames of the next track go in the same buffer as
+//! the last frames of the current track, so there is no silence between them.
+//!
+//! # Storage latency
+//! [`Prefetch`] only reads when the buffered samples could run out before a read finishes, so
+//! storage that uses less power when idle stays idle most of the time.
+
+use crate::{
+    Error, FmtData, Located, Parser, ProcessDataOutput, ReadInstruction, WavMetaData,
+    check_data_len,
+    position::PlaybackPosition,
+    smpl::{LOOP_TYPE_FORWARD, SampleLoop},
+    snapshot::{self, StreamDataSnapshot},
+    units::{Rounding, Units},
+};
+
+/// Reads the `data` chunk a few frames at a time
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct StreamData {
+    data_position: u64,
+    data_len: u32,
+    block_align: u32,
+    n_samples_per_sec: u32,
+    offset: u32,
+    read_len: u32,
+    frames_played: u64,
+}
+
+pub enum StreamDataProcessDataOutput<'a> {
+    /// The last frames
+    Done(&'a [u8]),
+    /// Whole frames to give to the output
+    InProgress(&'a [u8], StreamData),
+}
+
+impl StreamData {
+    /// The `data` chunk is read `read_len` bytes at a time, rounded down to whole frames. A
+    /// partial frame at the end of the `data` chunk is not read. Returns [`None`] if there are no
+    /// frames.
+    pub fn new(meta_data: &WavMetaData, read_len: u32) -> Option<Self> {
+        let block_align = u32::from(meta_data.fmt.n_block_align.get());
+        if block_align == 0 || meta_data.data_len < block_align {
+            return None;
+        }
+        Some(Self {
+            data_position: meta_data.data_position,
+            data_len: meta_data.data_len - meta_data.data_len % block_align,
+            block_align,
+            n_samples_per_sec: meta_data.fmt.n_samples_per_sec.get(),
+            offset: 0,
+            read_len: (read_len - read_len % block_align).max(block_align),
+            frames_played: 0,
+        })
+    }
+
+    /// Continue streaming from a frame. Seeking past the end makes the next read have a len of 0.
+    pub fn seek_to_frame(&mut self, frame: u64) {
+        self.offset = self
+            .units()
+            .sample_to_byte(frame)
+            .min(self.data_len.into())
+            .try_into()
+            .unwrap();
+    }
+
+    /// Continue streaming from the frame at a time, rounded down
+    pub fn seek_to_ns(&mut self, ns: u64) {
+        self.seek_to_frame(self.units().time_to_sample(ns, Rounding::Down));
+    }
+
+    /// Conversions for the format of the stream
+    pub fn units(&self) -> Units {
+        Units::from_parts(self.block_align, self.n_samples_per_sec).unwrap()
+    }
+
+    /// The frame that the next read starts at
+    pub fn frame(&self) -> u32 {
+        self.offset / self.block_align
+    }
+
+    pub fn read_instruction(&self) -> ReadInstruction {
+        ReadInstruction {
+            position: self.data_position.saturating_add(self.offset.into()),
+            len: (self.data_len - self.offset).min(self.read_len),
+        }
+    }
+
+    /// The number of bytes of the `data` chunk that were streamed so far
+    pub fn offset(&self) -> u32 {
+        self.offset
+    }
+
+    pub fn position(&self) -> PlaybackPosition {
+        PlaybackPosition {
+            frame: self.frame(),
+            total_frames: self.data_len / self.block_align,
+            frames_played: self.frames_played,
+            n_samples_per_sec: self.n_samples_per_sec,
+        }
+    }
+
+    /// Saves the state of the stream. See [`snapshot`].
+    pub fn snapshot(&self) -> StreamDataSnapshot {
+        StreamDataSnapshot {
+            version: snapshot::VERSION,
+            reserved: [0; 3],
+            data_len: self.data_len.into(),
+            block_align: self.block_align.into(),
+            n_samples_per_sec: self.n_samples_per_sec.into(),
+            offset: self.offset.into(),
+            read_len: self.read_len.into(),
+            data_position: self.data_position.into(),
+            frames_played: self.frames_played.into(),
+        }
+    }
+
+    /// Continues streaming from a snapshot. Returns [`None`] if the snapshot is invalid.
+    pub fn restore(snapshot: &StreamDataSnapshot) -> Option<Self> {
+        let stream = Self {
+            data_position: snapshot.data_position.get(),
+            data_len: snapshot.data_len.get(),
+            block_align: snapshot.block_align.get(),
+            n_samples_per_sec: snapshot.n_samples_per_sec.get(),
+            offset: snapshot.offset.get(),
+            read_len: snapshot.read_len.get(),
+            frames_played: snapshot.frames_played.get(),
+        };
+        (snapshot.version == snapshot::VERSION
+            && stream.block_align != 0
+            && stream.data_len.is_multiple_of(stream.block_align)
+            && stream.offset.is_multiple_of(stream.block_align)
+            && stream.offset <= stream.data_len
+            && stream.read_len.is_multiple_of(stream.block_align)
+            && stream.read_len != 0)
+            .then_some(stream)
+    }
+
+    /// `len` must be whole frames
+    fn advance(&mut self, len: u32) {
+        self.offset += len;
+        self.frames_played += u64::from(len / self.block_align);
+    }
+
+    /// `data` must be the len of the [`ReadInstruction`]
+    pub fn process_data(mut self, data: &[u8]) -> Result<StreamDataProcessDataOutput<'_>, Error> {
+        check_data_len(self.read_instruction().len, data)?;
+        self.advance(data.len().try_into().unwrap());
+        Ok(if self.offset == self.data_len {
+            StreamDataProcessDataOutput::Done(data)
+        } else {
+            StreamDataProcessDataOutput::InProgress(data, self)
+        })
+    }
+}
+
+/// Streams the `data` chunk up to the end of a loop, jumps back to the start of the loop, and
+/// after the last time through the loop plays the rest of the `data` chunk (the release).
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct LoopPlayback {
+    stream: StreamData,
+    /// The offset in the `data` chunk of the first frame of the loop
+    loop_start: u32,
+    /// The offset in the `data` chunk right after the last frame of the loop
+    loop_end: u32,
+    /// The number of times to jump back, or [`None`] to loop until [`LoopPlayback::release`]
+    jumps_left: Option<u32>,
+}
+
+pub enum LoopPlaybackProcessDataOutput<'a> {
+    /// The last frames
+    Done(&'a [u8]),
+    /// Whole frames to give to the output
+    InProgress(&'a [u8], LoopPlayback),
+}
+
+impl LoopPlayback {
+    /// Starts from wherever `stream` is. Uses the `play_count` of the loop, where 0 means to loop
+    /// until [`LoopPlayback::release`] is called. Returns [`None`] if the loop is not a forward
+    /// loop, or is not inside the `data` chunk.
+    pub fn new(stream: StreamData, sample_loop: &SampleLoop) -> Option<Self> {
+        let play_count = sample_loop.play_count.get();
+        Self::with_play_count(stream, sample_loop, (play_count != 0).then_some(play_count))
+    }
+
+    /// Like [`LoopPlayback::new`], but plays the loop `play_count` times instead, or until
+    /// [`LoopPlayback::release`] is called if it's [`None`]
+    pub fn with_play_count(
+        stream: StreamData,
+        sample_loop: &SampleLoop,
+        play_count: Option<u32>,
+    ) -> Option<Self> {
+        if sample_loop.loop_type.get() != LOOP_TYPE_FORWARD {
+            return None;
+        }
+        let start = sample_loop.start.get();
+        let end = sample_loop.end.get();
+        if start > end || end >= stream.data_len / stream.block_align {
+            return None;
+        }
+        let units = stream.units();
+        Some(Self {
+            loop_start: units.sample_to_byte(start.into()).try_into().unwrap(),
+            loop_end: units.sample_to_byte(u64::from(end) + 1).try_into().unwrap(),
+            stream,
+            jumps_left: play_count.map(|play_count| play_count.saturating_sub(1)),
+        })
+    }
+
+    /// Finish the current time through the loop, and then play the release
+    pub fn release(&mut self) {
+        self.jumps_left = Some(0);
+    }
+
+    fn is_looping(&self) -> bool {
+        self.jumps_left != Some(0) && self.stream.offset < self.loop_end
+    }
+
+    /// Never crosses the end of the loop while looping
+    pub fn read_instruction(&self) -> ReadInstruction {
+        let mut read_instruction = self.stream.read_instruction();
+        if self.is_looping() {
+            read_instruction.len = read_instruction.len.min(self.loop_end - self.stream.offset);
+        }
+        read_instruction
+    }
+
+    /// The frame that the next read starts at
+    pub fn frame(&self) -> u32 {
+        self.stream.frame()
+    }
+
+    pub fn position(&self) -> PlaybackPosition {
+        self.stream.position()
+    }
+
+    /// `data` must be the len of the [`ReadInstruction`]
+    pub fn process_data(mut self, data: &[u8]) -> Result<LoopPlaybackProcessDataOutput<'_>, Error> {
+        check_data_len(self.read_instruction().len, data)?;
+        let was_looping = self.is_looping();
+        self.stream.advance(data.len().try_into().unwrap());
+        if was_looping && self.stream.offset == self.loop_end {
+            self.jumps_left = self.jumps_left.map(|jumps_left| jumps_left - 1);
+            self.stream.offset = self.loop_start;
+        } else if self.stream.offset == self.stream.data_len {
+            return Ok(LoopPlaybackProcessDataOutput::Done(data));
+        }
+        Ok(LoopPlaybackProcessDataOutput::InProgress(data, self))
+    }
+}
+
+enum NextTrack {
+    None,
+    Parsing(Parser),
+    Ready(FmtData, StreamData),
+    Invalid(Located),
+}
+
+/// What to read for [`Gapless`]. Tracks are numbered in the order that they are queued, starting
+/// with 0 for the track given to [`Gapless::new`].
+#[derive(Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum GaplessReadInstruction {
+    /// Read part of the next track into a buffer of at least [`Parser::MAX_BUFFER_LEN`] bytes
+    MetaData {
+        track: u32,
+        read_instruction: ReadInstruction,
+    },
+    /// Read samples into the playback buffer, starting at `buffer_offset`
+    Samples {
+        track: u32,
+        read_instruction: ReadInstruction,
+        buffer_offset: u32,
+    },
+}
+
+/// A playback buffer that is ready to be played
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Buffer {
+    /// The number of bytes in the buffer
+    pub len: u32,
+    /// The track of the first frame in the buffer. The buffer can end with frames from the tracks
+    /// after it.
+    pub track: u32,
+}
+
+pub enum GaplessProcessDataOutput {
+    /// Keep filling the same buffer
+    InProgress(Gapless),
+    /// Play the buffer, and then fill it again from the start. If [`Gapless::fmt`] is different
+    /// than before, the output needs to be reconfigured before playing the next buffer.
+    BufferReady(Buffer, Gapless),
+    /// There are no more tracks. Play the last buffer, if there is one.
+    Done(Option<Buffer>),
+}
+
+/// Plays tracks one after another without gaps. See the [module docs](self).
+///
+/// Call [`Gapless::queue`] before the current track ends to play another track after it. Only
+/// tracks with the same format are joined in the same buffer. A track with a different format
+/// starts in a new buffer.
+pub struct Gapless {
+    fmt: FmtData,
+    track: u32,
+    current: StreamData,
+    next: NextTrack,
+    buffer_len: u32,
+    buffer_offset: u32,
+    buffer_track: u32,
+}
+
+fn same_format(a: &FmtData, b: &FmtData) -> bool {
+    a.format_tag == b.format_tag
+        && a.n_channels == b.n_channels
+        && a.n_samples_per_sec == b.n_samples_per_sec
+        && a.n_block_align == b.n_block_align
+        && a.w_bits_per_sample == b.w_bits_per_sample
+}
+
+impl Gapless {
+    /// `buffer_len` is the len of the playback buffer. Returns [`None`] if the first track has no
+    /// frames, or if a frame doesn't fit in the buffer.
+    pub fn new(meta_data: &WavMetaData, buffer_len: u32) -> Option<Self> {
+        Some(Self {
+            fmt: meta_data.fmt,
+            track: 0,
+            current: Self::stream(meta_data, buffer_len)?,
+            next: NextTrack::None,
+            buffer_len,
+            buffer_offset: 0,
+            buffer_track: 0,
+        })
+    }
+
+    /// Play another track after the current one. Returns the number of the queued track, or
+    /// [`None`] if a track is already queued. If the metadata of the queued track can't be parsed,
+    /// it has no frames, or a frame doesn't fit in the buffer, it is skipped.
+    pub fn queue(&mut self) -> Option<u32> {
+        match self.next {
+            NextTrack::None | NextTrack::Invalid(_) => {
+                self.next = NextTrack::Parsing(Default::default());
+                Some(self.track + 1)
+            }
+            NextTrack::Parsing(_) | NextTrack::Ready(..) => None,
+        }
+    }
+
+    /// The error from parsing the last queued track, with where in its file it happened, if it
+    /// was skipped because of an error
+    pub fn next_track_error(&self) -> Option<&Located> {
+        match &self.next {
+            NextTrack::Invalid(error) => Some(error),
+            _ => None,
+        }
+    }
+
+    /// The format of the current track
+    pub fn fmt(&self) -> &FmtData {
+        &self.fmt
+    }
+
+    /// The number of the track that is being read
+    pub fn track(&self) -> u32 {
+        self.track
+    }
+
+    /// The position in the track that is being read
+    pub fn position(&self) -> PlaybackPosition {
+        self.current.position()
+    }
+
+    /// Every read has to be at least one frame, or the buffer would never fill
+    fn stream(meta_data: &WavMetaData, buffer_len: u32) -> Option<StreamData> {
+        StreamData::new(meta_data, buffer_len).filter(|stream| stream.block_align <= buffer_len)
+    }
+
+    fn max_samples_len(&self) -> u32 {
+        self.buffer_len - self.buffer_offset
+    }
+
+    fn samples_read_instruction(&self) -> ReadInstruction {
+        let mut read_instruction = self.current.read_instruction();
+        let max_len = self.max_samples_len();
+        read_instruction.len = read_instruction
+            .len
+            .min(max_len - max_len % self.current.block_align);
+        read_instruction
+    }
+
+    pub fn read_instruction(&self) -> GaplessReadInstruction {
+        match &self.next {
+            NextTrack::Parsing(parser) => GaplessReadInstruction::MetaData {
+                track: self.track + 1,
+                read_instruction: parser.read_instruction(),
+            },
+            _ => GaplessReadInstruction::Samples {
+                track: self.track,
+                read_instruction: self.samples_read_instruction(),
+                buffer_offset: self.buffer_offset,
+            },
+        }
+    }
+
+    fn take_buffer(&mut self) -> Buffer {
+        let buffer = Buffer {
+            len: self.buffer_offset,
+            track: self.buffer_track,
+        };
+        self.buffer_offset = 0;
+        self.buffer_track = self.track;
+        buffer
+    }
+
+    /// `data` must be the len of the [`GaplessReadInstruction`]. An error from parsing the next
+    /// track doesn't fail, see [`Gapless::next_track_error`].
+    pub fn process_data(mut self, data: &[u8]) -> Result<GaplessProcessDataOutput, Error> {
+        if let NextTrack::Parsing(parser) = self.next {
+            check_data_len(parser.read_instruction().len, data)?;
+            self.next = match parser.process_data(data) {
+                Ok(ProcessDataOutput::Done(meta_data)) => {
+                    match Self::stream(&meta_data, self.buffer_len) {
+                        Some(stream) => NextTrack::Ready(meta_data.fmt, stream),
+                        None => NextTrack::None,
+                    }
+                }
+                Ok(ProcessDataOutput::InProgress(parser)) => NextTrack::Parsing(parser),
+                Err(error) => NextTrack::Invalid(error),
+            };
+            return Ok(GaplessProcessDataOutput::InProgress(self));
+        }
+        check_data_len(self.samples_read_instruction().len, data)?;
+        let len = u32::try_from(data.len()).unwrap();
+        self.current.advance(len);
+        self.buffer_offset += len;
+        if self.current.offset == self.current.data_len {
+            let NextTrack::Ready(fmt, stream) = core::mem::replace(&mut self.next, NextTrack::None)
+            else {
+                let buffer = (self.buffer_offset != 0).then(|| self.take_buffer());
+                return Ok(GaplessProcessDataOutput::Done(buffer));
+            };
+            let joined = same_format(&fmt, &self.fmt);
+            self.fmt = fmt;
+            self.current = stream;
+            self.track += 1;
+            if !joined {
+                return Ok(GaplessProcessDataOutput::BufferReady(
+                    self.take_buffer(),
+                    self,
+                ));
+            }
+        }
+        Ok(if self.max_samples_len() < self.current.block_align {
+            GaplessProcessDataOutput::BufferReady(self.take_buffer(), self)
+        } else {
+            GaplessProcessDataOutput::InProgress(self)
+        })
+    }
+}
+
+/// Decides when to read the next samples into a ring buffer, based on how long reads take. See
+/// the [module docs](self).
+///
+/// Every time [`Prefetch::read_instruction`] returns something, start that read and call
+/// [`Prefetch::read_completed`] when it finishes. No other read is returned until then. Call
+/// [`Prefetch::played`] as the output takes samples from the ring buffer.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Prefetch {
+    stream: StreamData,
+    capacity: u32,
+    latency_us: u32,
+    /// The number of bytes of the `data` chunk that the output took
+    played: u32,
+    /// A read was returned and didn't complete yet
+    in_flight: bool,
+}
+
+impl Prefetch {
+    /// `capacity` is the len of the ring buffer. `latency_us` is how long in microseconds a read is
+    /// expected to take. It is updated with the time that each read takes.
+    pub fn new(stream: StreamData, capacity: u32, latency_us: u32) -> Self {
+        Self {
+            played: stream.offset,
+            stream,
+            capacity,
+            latency_us,
+            in_flight: false,
+        }
+    }
+
+    fn bytes_per_sec(&self) -> u64 {
+        u64::from(self.stream.n_samples_per_sec) * u64::from(self.stream.block_align)
+    }
+
+    /// How many bytes have to be buffered when a read starts, so that the output doesn't run out
+    /// of samples before the read finishes
+    pub fn lead_len(&self) -> u32 {
+        let during_read = (u64::from(self.latency_us) * self.bytes_per_sec()).div_ceil(1_000_000);
+        let block_align = u64::from(self.stream.block_align);
+        let during_read = during_read.next_multiple_of(block_align);
+        u32::try_from(during_read)
+            .unwrap_or(u32::MAX)
+            .saturating_add(self.stream.read_len)
+    }
+
+    /// `true` if the ring buffer can hold [`Prefetch::lead_len`] bytes and one more read
+    pub fn is_capacity_enough(&self) -> bool {
+        self.lead_len()
+            .checked_add(self.stream.read_len)
+            .is_some_and(|len| len <= self.capacity)
+    }
+
+    /// The number of bytes that were read but not played yet
+    pub fn headroom(&self) -> u32 {
+        self.stream.offset - self.played
+    }
+
+    /// The read to start now, if one needs to be started. Returns [`None`] while a read is in
+    /// flight.
+    pub fn read_instruction(&mut self) -> Option<ReadInstruction> {
+        let read_instruction = self.stream.read_instruction();
+        let headroom = self.headroom();
+        let start = !self.in_flight
+            && read_instruction.len != 0
+            && headroom <= self.lead_len()
+            && headroom.saturating_add(read_instruction.len) <= self.capacity;
+        self.in_flight |= start;
+        start.then_some(read_instruction)
+    }
+
+    /// Call this when a read finishes, with the number of bytes that were read and the time in
+    /// microseconds that it took
+    pub fn read_completed(&mut self, len: u32, latency_us: u32) {
+        self.in_flight = false;
+        let len = len.min(self.stream.read_instruction().len);
+        self.stream.advance(len - len % self.stream.block_align);
+        // A slow read raises the estimate right away, so the next reads start early enough. It
+        // goes back down by 1/8 of the difference per read, so one slow read doesn't count forever.
+        self.latency_us = if latency_us >= self.latency_us {
+            latency_us
+        } else {
+            self.latency_us - (self.latency_us - latency_us).div_ceil(8)
+        };
+    }
+
+    /// Call this when the output takes bytes from the ring buffer
+    pub fn played(&mut self, len: u32) {
+        self.played = self.played.saturating_add(len).min(self.stream.offset);
+    }
+
+    /// How long a read is expected to take, from the times of the recent reads
+    pub fn latency_us(&self) -> u32 {
+        self.latency_us
+    }
+
+    /// `true` when every sample was read and played
+    pub fn is_done(&self) -> bool {
+        self.stream.offset == self.stream.data_len && self.played == self.stream.offset
+    }
+
+    pub fn position(&self) -> PlaybackPosition {
+        self.stream.position()
+    }
+}
+
+/// Read samples into a buffer
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct FillInstruction {
+    /// The index of the buffer, 0 or 1
+    pub buffer: usize,
+    pub position: u64,
+    /// Only the first `len` bytes of the buffer are filled. This is less than the buffer len for
+    /// the last buffer.
+    pub len: u32,
+}
+
+/// Play the first `len` bytes of a buffer
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct PlayInstruction {
+    pub buffer: usize,
+    pub len: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+enum BufferState {
+    Empty,
+    /// Filled with this many bytes, and waiting to be played
+    Filled(u32),
+    Playing,
+}
+
+/// Decides which of two buffers to fill and play. See the [module docs](self).
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct DoubleBuffer {
+    data_position: u64,
+    data_len: u32,
+    /// The offset in the `data` chunk of the next fill
+    offset: u32,
+    buffer_len: u32,
+    buffers: [BufferState; 2],
+    /// The buffer to fill next. Buffers are filled and played in turns.
+    next_fill: usize,
+    next_play: usize,
+}
+
+impl DoubleBuffer {
+    /// `buffer_len` is the len of each buffer. It is rounded down to whole frames. Returns
+    /// [`None`] if a buffer can't fit one frame.
+    pub fn new(meta_data: &WavMetaData, buffer_len: u32) -> Option<Self> {
+        let block_align = u32::from(meta_data.fmt.n_block_align.get()).max(1);
+        let buffer_len = buffer_len - buffer_len % block_align;
+        if buffer_len == 0 {
+            return None;
+        }
+        Some(Self {
+            data_position: meta_data.data_position,
+            // A partial frame at the end is not played
+            data_len: meta_data.data_len - meta_data.data_len % block_align,
+            offset: 0,
+            buffer_len,
+            buffers: [BufferState::Empty; 2],
+            next_fill: 0,
+            next_play: 0,
+        })
+    }
+
+    /// The buffer to fill next, if it is empty and there are samples left
+    pub fn fill_instruction(&self) -> Option<FillInstruction> {
+        if self.buffers[self.next_fill] != BufferState::Empty || self.offset >= self.data_len {
+            return None;
+        }
+        Some(FillInstruction {
+            buffer: self.next_fill,
+            position: self.data_position.saturating_add(self.offset.into()),
+            len: (self.data_len - self.offset).min(self.buffer_len),
+        })
+    }
+
+    /// Call this when the read from [`DoubleBuffer::fill_instruction`] is done
+    pub fn filled(&mut self) {
+        if let Some(FillInstruction { buffer, len, .. }) = self.fill_instruction() {
+            self.offset += len;
+            self.next_fill = 1 - self.next_fill;
+            self.buffers[buffer] = BufferState::Filled(len);
+        }
+    }
+
+    /// The buffer to play next, if it is filled
+    pub fn play_instruction(&self) -> Option<PlayInstruction> {
+        match self.buffers[self.next_play] {
+            BufferState::Filled(len) => Some(PlayInstruction {
+                buffer: self.next_play,
+                len,
+            }),
+            _ => None,
+        }
+    }
+
+    /// Call this when you give the buffer from [`DoubleBuffer::play_instruction`] to the output
+    pub fn playing(&mut self) {
+        if let Some(PlayInstruction { buffer, .. }) = self.play_instruction() {
+            self.buffers[buffer] = BufferState::Playing;
+            self.next_play = 1 - self.next_play;
+        }
+    }
+
+    /// Call this when the output is done with a buffer, so it can be filled again
+    pub fn played(&mut self, buffer: usize) {
+        if let Some(state @ BufferState::Playing) = self.buffers.get_mut(buffer) {
+            *state = BufferState::Empty;
+        }
+    }
+
+    /// `true` when every sample was read and played
+    pub fn is_done(&self) -> bool {
+        self.offset >= self.data_len && self.buffers == [BufferState::Empty; 2]
+    }
+
+    /// The number of bytes of the `data` chunk that were read so far
+    pub fn offset(&self) -> u32 {
+        self.offset
+    }
+}
+
+#[cfg(all(test, feature = "test-wav"))]
+mod tests {
+    use super::*;
+    use crate::{
+        fmt::{Codec, FmtDataBuilder},
+        slice,
+        test_wav::TestWav,
+    };
+
+    /// 10 frames of 16 bit stereo
+    fn meta_data() -> WavMetaData {
+        let fmt = FmtDataBuilder::new(Codec::Pcm, 8000, 2, 16)
+            .build()
+            .unwrap();
+        let mut buffer = [0; 128];
+        let len = TestWav::new(fmt, 10).write(&mut buffer).unwrap();
+        slice::parse(&buffer[..len]).unwrap()
+    }
+
+    fn is_wrong_data_len(error: Option<Error>, len: usize) -> bool {
+        matches!(error, Some(Error::WrongDataLen { expected: 16, actual }) if actual == len)
+    }
+
+    #[test]
+    fn stream_data_wrong_data_len() {
+        let stream = StreamData::new(&meta_data(), 16).unwrap();
+        for len in [12, 20] {
+            assert!(is_wrong_data_len(
+                stream.clone().process_data(&[0; 20][..len]).err(),
+                len
+            ));
+        }
+        let Ok(StreamDataProcessDataOutput::InProgress(frames, stream)) =
+            stream.process_data(&[0; 16])
+        else {
+            panic!();
+        };
+        assert_eq!(frames.len(), 16);
+        assert_eq!(stream.offset(), 16);
+    }
+
+    #[test]
+    fn gapless_wrong_data_len() {
+        let gapless = || Gapless::new(&meta_data(), 16).unwrap();
+        for len in [12, 20] {
+            assert!(is_wrong_data_len(
+                gapless().process_data(&[0; 20][..len]).err(),
+                len
+            ));
+        }
+        assert!(matches!(
+            gapless().process_data(&[0; 16]),
+            Ok(GaplessProcessDataOutput::BufferReady(
+                Buffer { len: 16, track: 0 },
+                _
+            ))
+        ));
+    }
+}
@@ -0,0 +1,102 @@
+//! Keeping track of where playback is.
+
+use crate::{FmtData, units::frames_to_ns};
+
+/// A clock derived from the number of frames that were actually delivered to the output (for
+/// example, the number of frames that the I2S DMA finished sending), not the number of bytes that
+/// were read from the file. Useful for syncing LED animations or video to the audio.
+///
+/// The reported position never goes backwards, even if the output latency is increased.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct PlaybackClock {
+    n_samples_per_sec: u32,
+    frames_delivered: u64,
+    output_latency_ns: u64,
+    min_position_ns: u64,
+}
+
+impl PlaybackClock {
+    pub fn new(fmt: &FmtData) -> Self {
+        Self {
+            n_samples_per_sec: fmt.n_samples_per_sec.get(),
+            frames_delivered: 0,
+            output_latency_ns: 0,
+            min_position_ns: 0,
+        }
+    }
+
+    /// Call this whenever frames finished playing.
+    pub fn add_frames_delivered(&mut self, frames: u64) {
+        self.frames_delivered = self.frames_delivered.saturating_add(frames);
+    }
+
+    pub fn frames_delivered(&self) -> u64 {
+        self.frames_delivered
+    }
+
+    /// The time between a frame being delivered and it actually being heard. This gets subtracted
+    /// from the position.
+    pub fn set_output_latency_ns(&mut self, output_latency_ns: u64) {
+        self.min_position_ns = self.position_ns();
+        self.output_latency_ns = output_latency_ns;
+    }
+
+    pub fn output_latency_ns(&self) -> u64 {
+        self.output_latency_ns
+    }
+
+    /// The position of the audio that is currently being heard, in nanoseconds.
+    pub fn position_ns(&self) -> u64 {
+        frames_to_ns(self.frames_delivered, self.n_samples_per_sec)
+            .saturating_sub(self.output_latency_ns)
+            .max(self.min_position_ns)
+    }
+}
+
+/// Where streaming is in a file, for showing a progress bar. This is the position of the frames
+/// that were read, which is ahead of what is heard by the buffered frames. Use [`PlaybackClock`]
+/// for the position of what is heard.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct PlaybackPosition {
+    /// The frame in the `data` chunk that will be read next
+    pub frame: u32,
+    /// The number of frames in the `data` chunk
+    pub total_frames: u32,
+    /// The number of frames that were read, counting frames in loops every time they were read
+    /// and not counting frames that were skipped by seeking. Resampling doesn't change this.
+    pub frames_played: u64,
+    pub n_samples_per_sec: u32,
+}
+
+impl PlaybackPosition {
+    /// The time of [`PlaybackPosition::frame`] in milliseconds
+    pub fn position_ms(&self) -> u64 {
+        frames_to_ms(self.frame.into(), self.n_samples_per_sec)
+    }
+
+    /// The time of [`PlaybackPosition::frames_played`] in milliseconds
+    pub fn elapsed_ms(&self) -> u64 {
+        frames_to_ms(self.frames_played, self.n_samples_per_sec)
+    }
+
+    /// The time of the whole `data` chunk in milliseconds
+    pub fn duration_ms(&self) -> u64 {
+        frames_to_ms(self.total_frames.into(), self.n_samples_per_sec)
+    }
+
+    /// How far [`PlaybackPosition::frame`] is through the file, from 0 to 100
+    pub fn percent(&self) -> u8 {
+        (u64::from(self.frame) * 100)
+            .checked_div(self.total_frames.into())
+            .unwrap_or(100)
+            .min(100)
+            .try_into()
+            .unwrap()
+    }
+}
+
+fn frames_to_ms(frames: u64, n_samples_per_sec: u32) -> u64 {
+    frames_to_ns(frames, n_samples_per_sec) / 1_000_000
+}
@@ -0,0 +1,52 @@
+//! Finding out what kind of file something is from its first 12 bytes.
+//!
+//! Many formats use RIFF. If a generic "open media file" path gets a file that isn't a WAVE, use
+//! [`probe`] to tell the user what it actually is instead of failing with
+//! [`Error::UnexpectedContainerId`](crate::Error::UnexpectedContainerId).
+
+use pure_riff::Id;
+
+/// The len of the bytes that [`probe`] needs
+pub const PROBE_LEN: usize = 12;
+
+/// What kind of file something is
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Container {
+    /// A `RIFF` file with `WAVE`, which the [`Parser`](crate::Parser) can parse
+    Wave,
+    /// An `RF64` file with `WAVE`, for files bigger than 4 GiB
+    Rf64,
+    /// A `BW64` file with `WAVE`, which is like RF64
+    Bw64,
+    /// A `RIFF` file with `AVI `, which is video
+    Avi,
+    /// A `RIFF` file with `WEBP`, which is an image
+    Webp,
+    /// A `RIFF` file with a different form type. Contains the form type.
+    OtherRiff(Id),
+    /// Not a RIFF file
+    Unknown,
+}
+
+impl Container {
+    /// If the file is audio in the WAVE format, which might be bigger than 4 GiB
+    pub const fn is_wave(&self) -> bool {
+        matches!(self, Self::Wave | Self::Rf64 | Self::Bw64)
+    }
+}
+
+/// Classifies a file from its first [`PROBE_LEN`] bytes
+pub const fn probe(header: &[u8; PROBE_LEN]) -> Container {
+    let form_type = [header[8], header[9], header[10], header[11]];
+    match (&[header[0], header[1], header[2], header[3]], &form_type) {
+        (b"RIFF", b"WAVE") => Container::Wave,
+        (b"RF64", b"WAVE") => Container::Rf64,
+        (b"BW64", b"WAVE") => Container::Bw64,
+        (b"RIFF", b"AVI ") => Container::Avi,
+        (b"RIFF", b"WEBP") => Container::Webp,
+        (b"RIFF", _) => Container::OtherRiff(form_type),
+        _ => Container::Unknown,
+    }
+}
@@ -0,0 +1,109 @@
+//! A higher level way of recording, for example from an I2S microphone.
+
+use crate::writer::{WriteError, WriteInstruction, WriteInstructions, Writer};
+
+#[derive(Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum RecordError {
+    Write(WriteError),
+    /// The len of the samples must be a multiple of `n_block_align`, so that frames are never
+    /// split
+    NotBlockAligned {
+        len: u32,
+        block_align: u16,
+    },
+}
+
+impl core::fmt::Display for RecordError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Write(error) => write!(f, "write error: {error}"),
+            Self::NotBlockAligned { len, block_align } => write!(
+                f,
+                "{len} bytes of samples is not a multiple of the block align {block_align}"
+            ),
+        }
+    }
+}
+
+impl core::error::Error for RecordError {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        match self {
+            Self::Write(error) => Some(error),
+            Self::NotBlockAligned { .. } => None,
+        }
+    }
+}
+
+impl From<WriteError> for RecordError {
+    fn from(value: WriteError) -> Self {
+        Self::Write(value)
+    }
+}
+
+/// A recording session. It uses a [`Writer`] and makes sure that only whole frames are written.
+///
+/// # Usage
+/// 1. Call [`RecordWav::open`] and do the write
+/// 2. Every time you receive samples (for example from the I2S RX DMA), call
+///    [`RecordWav::append_samples`] and write the samples at the returned position
+/// 3. Optionally call [`RecordWav::flush`] once in a while, so that a file is still valid if
+///    recording stops unexpectedly (for example a power loss)
+/// 4. Call [`RecordWav::finalize`] and do all the writes
+pub struct RecordWav {
+    writer: Writer,
+}
+
+impl RecordWav {
+    /// If you want to write chunks such as [`InfoTags`](crate::info::InfoTags) before the samples,
+    /// call [`Writer::write_header`] and [`Writer::write_chunk`] before creating this.
+    pub fn new(writer: Writer) -> Self {
+        Self { writer }
+    }
+
+    /// Writes the header (if it wasn't written yet) and starts the `data` chunk
+    pub fn open<'a>(&mut self, buffer: &'a mut [u8]) -> Result<WriteInstruction<'a>, RecordError> {
+        if self.writer.header_written() {
+            Ok(self.writer.start_data(buffer)?)
+        } else {
+            let header_len = self.writer.write_header(buffer)?.data.len();
+            let (header, data_header) = buffer.split_at_mut(header_len);
+            let data_header_len = self.writer.start_data(data_header)?.data.len();
+            Ok(WriteInstruction {
+                position: 0,
+                data: &header[..header_len + data_header_len],
+            })
+        }
+    }
+
+    /// Returns the position to write `len` bytes of samples at
+    pub fn append_samples(&mut self, len: u32) -> Result<u64, RecordError> {
+        let block_align = self.writer.fmt().base.n_block_align.get();
+        if block_align != 0 && !len.is_multiple_of(u32::from(block_align)) {
+            return Err(RecordError::NotBlockAligned { len, block_align });
+        }
+        Ok(self.writer.write_samples(len)?)
+    }
+
+    /// The len of the samples written so far
+    pub fn bytes_written(&self) -> u64 {
+        self.writer.data_len()
+    }
+
+    /// See [`Writer::flush`]
+    pub fn flush<'a>(
+        &mut self,
+        buffer: &'a mut [u8],
+    ) -> Result<WriteInstructions<'a>, RecordError> {
+        Ok(self.writer.flush(buffer)?)
+    }
+
+    /// See [`Writer::finalize`]
+    pub fn finalize(mut self, buffer: &mut [u8]) -> Result<WriteInstructions<'_>, RecordError> {
+        Ok(self.writer.finalize(buffer)?)
+    }
+
+    pub fn writer(&self) -> &Writer {
+        &self.writer
+    }
+}
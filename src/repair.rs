@@ -0,0 +1,205 @@
+//! Fixing the sizes in files from recorders that stopped before writing the final sizes.
+//!
+//! # Usage
+//! Create a [`Repair`] with the actual len of the file. Drive it like the
+//! [`Parser`](crate::Parser) with [`Repair::read_instruction`] and [`Repair::process_data`].
+//! You get a [`RepairPlan`]. Call [`RepairPlan::write`] and do all the writes.
+//!
+//! These problems are fixed:
+//! - The RIFF len doesn't match the file len (for example it is 0)
+//! - The `data` chunk goes past the end of the file, or is followed by bytes that are not a chunk,
+//!   for example because its len is 0. The `data` chunk is changed to go to the end of the file.
+//! - Any other chunk goes past the end of the file. It is shortened to end at the end of the file.
+
+use pure_riff::{Id, RiffChunkHeader};
+use zerocopy::FromBytes;
+
+use crate::{
+    Error, ReadInstruction,
+    arithmetic::checked_advance,
+    check_data_len, is_plausible_id,
+    writer::{WriteError, WriteInstructions, WriteInstructionsBuilder},
+};
+
+const HEADER_LEN: u32 = size_of::<RiffChunkHeader>() as u32;
+const RIFF_HEADER_LEN: u32 = HEADER_LEN + size_of::<Id>() as u32;
+
+enum RepairStage {
+    Riff,
+    Header {
+        position: u64,
+    },
+    /// Checking if there is a valid chunk after the `data` chunk
+    AfterData {
+        data_position: u64,
+        data_len: u64,
+        next_position: u64,
+    },
+}
+
+pub struct Repair {
+    file_len: u64,
+    stage: RepairStage,
+    riff_len: u64,
+}
+
+/// The writes needed to repair the file
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct RepairPlan {
+    /// The correct RIFF len, if it is wrong
+    pub riff_len: Option<u64>,
+    /// The position of a chunk header and the correct len of the chunk, if it is wrong
+    pub chunk_len: Option<(u64, u64)>,
+}
+
+pub enum RepairProcessDataOutput {
+    Done(RepairPlan),
+    InProgress(Repair),
+}
+
+impl Repair {
+    pub const MAX_BUFFER_LEN: usize = RIFF_HEADER_LEN as usize;
+
+    pub fn new(file_len: u64) -> Self {
+        Self {
+            file_len,
+            stage: RepairStage::Riff,
+            riff_len: 0,
+        }
+    }
+
+    pub fn read_instruction(&self) -> ReadInstruction {
+        match self.stage {
+            RepairStage::Riff => ReadInstruction {
+                position: 0,
+                len: RIFF_HEADER_LEN,
+            },
+            RepairStage::Header { position }
+            | RepairStage::AfterData {
+                next_position: position,
+                ..
+            } => ReadInstruction {
+                position,
+                len: HEADER_LEN,
+            },
+        }
+    }
+
+    pub fn process_data(mut self, data: &[u8]) -> Result<RepairProcessDataOutput, Error> {
+        check_data_len(self.read_instruction().len, data)?;
+        match self.stage {
+            RepairStage::Riff => {
+                let riff_chunk =
+                    RiffChunkHeader::read_from_bytes(&data[..HEADER_LEN as usize]).unwrap();
+                if &riff_chunk.chunk_id != b"RIFF" {
+                    return Err(Error::UnexpectedChunkId(riff_chunk.chunk_id));
+                }
+                let container_id = Id::try_from(&data[HEADER_LEN as usize..]).unwrap();
+                if &container_id != b"WAVE" {
+                    return Err(Error::UnexpectedContainerId(container_id));
+                }
+                self.riff_len = riff_chunk.chunk_len.get().into();
+                Ok(self.next_or_done(RIFF_HEADER_LEN.into()))
+            }
+            RepairStage::Header { position } => {
+                let header = RiffChunkHeader::read_from_bytes(data).unwrap();
+                let data_position = position + u64::from(HEADER_LEN);
+                let available = self.file_len - data_position;
+                let chunk_len = u64::from(header.chunk_len.get());
+                if &header.chunk_id == b"data" {
+                    if chunk_len > available {
+                        return Ok(self.done(Some((position, available))));
+                    }
+                    let next_position =
+                        checked_advance(data_position, chunk_len, true).map_err(|_| {
+                            Error::ChunkSizeOverflow {
+                                chunk_id: header.chunk_id,
+                                position,
+                            }
+                        })?;
+                    if next_position.saturating_add(HEADER_LEN.into()) <= self.file_len {
+                        self.stage = RepairStage::AfterData {
+                            data_position,
+                            data_len: chunk_len,
+                            next_position,
+                        };
+                        Ok(RepairProcessDataOutput::InProgress(self))
+                    } else if next_position < self.file_len {
+                        // The left over bytes can't be a chunk
+                        Ok(self.done(Some((position, available))))
+                    } else {
+                        Ok(self.done(None))
+                    }
+                } else if chunk_len > available {
+                    Ok(self.done(Some((position, available))))
+                } else {
+                    let next_position =
+                        checked_advance(data_position, chunk_len, true).map_err(|_| {
+                            Error::ChunkSizeOverflow {
+                                chunk_id: header.chunk_id,
+                                position,
+                            }
+                        })?;
+                    Ok(self.next_or_done(next_position))
+                }
+            }
+            RepairStage::AfterData {
+                data_position,
+                data_len,
+                next_position,
+            } => {
+                let header = RiffChunkHeader::read_from_bytes(data).unwrap();
+                if is_plausible_id(&header.chunk_id) {
+                    self.stage = RepairStage::Header {
+                        position: next_position,
+                    };
+                    Ok(RepairProcessDataOutput::InProgress(self))
+                } else {
+                    let available = self.file_len - data_position;
+                    debug_assert!(available > data_len);
+                    Ok(self.done(Some((data_position - u64::from(HEADER_LEN), available))))
+                }
+            }
+        }
+    }
+
+    fn next_or_done(mut self, position: u64) -> RepairProcessDataOutput {
+        if position.saturating_add(HEADER_LEN.into()) <= self.file_len {
+            self.stage = RepairStage::Header { position };
+            RepairProcessDataOutput::InProgress(self)
+        } else {
+            self.done(None)
+        }
+    }
+
+    fn done(&self, chunk_len: Option<(u64, u64)>) -> RepairProcessDataOutput {
+        let riff_len = self.file_len.saturating_sub(HEADER_LEN.into());
+        RepairProcessDataOutput::Done(RepairPlan {
+            riff_len: (riff_len != self.riff_len).then_some(riff_len),
+            chunk_len,
+        })
+    }
+}
+
+impl RepairPlan {
+    /// `true` if the file doesn't need to be repaired
+    pub fn is_empty(&self) -> bool {
+        self.riff_len.is_none() && self.chunk_len.is_none()
+    }
+
+    /// A buffer of 8 bytes is enough. Returns [`WriteError::TooLarge`] if a len doesn't fit in the
+    /// 32 bits of a chunk header, because the file is bigger than 4 GiB.
+    pub fn write<'a>(&self, buffer: &'a mut [u8]) -> Result<WriteInstructions<'a>, WriteError> {
+        let mut instructions = WriteInstructionsBuilder::new(buffer);
+        let header_len = |len: u64| u32::try_from(len).map_err(|_| WriteError::TooLarge);
+        let id_len = u64::try_from(size_of::<Id>()).unwrap();
+        if let Some(riff_len) = self.riff_len {
+            instructions.push(id_len, &header_len(riff_len)?.to_le_bytes())?;
+        }
+        if let Some((position, len)) = self.chunk_len {
+            instructions.push(position + id_len, &header_len(len)?.to_le_bytes())?;
+        }
+        Ok(instructions.build())
+    }
+}
@@ -0,0 +1,506 @@
+//! Changing the sample rate, for when the output runs at a different rate than the WAVE.
+//!
+//! The quality is picked with a type parameter of [`Resampler`], so only the interpolations that
+//! you use end up in the binary:
+//! - [`ZeroOrderHold`] repeats samples. It is the cheapest, and is good enough for clicks and beeps.
+//! - [`Linear`] draws a straight line between samples.
+//! - `Polyphase` uses a windowed sinc filter with 8 to 32 taps. It is the best for music, but
+//!   costs the most. It requires the `polyphase` feature, because its tables take up space.
+//!
+//! Samples are frames of `CHANNELS` signed 16-bit samples.
+
+use crate::WavMetaData;
+
+/// The fractional part of the phase has this many bits
+const PHASE_BITS: u32 = 16;
+
+/// A way of calculating samples between input frames
+pub trait Interpolate<const CHANNELS: usize>: Default {
+    /// The number of frames that need to be pushed before the first output frame
+    const PRIMING_FRAMES: u32;
+
+    /// Adds the next input frame
+    fn push(&mut self, frame: [i16; CHANNELS]);
+
+    /// The frame at `phase` between the two current frames, where `phase` is a fraction with 16
+    /// bits
+    fn interpolate(&self, phase: u32) -> [i16; CHANNELS];
+}
+
+/// Outputs the most recent input frame
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct ZeroOrderHold<const CHANNELS: usize> {
+    frame: [i16; CHANNELS],
+}
+
+impl<const CHANNELS: usize> Default for ZeroOrderHold<CHANNELS> {
+    fn default() -> Self {
+        Self {
+            frame: [0; CHANNELS],
+        }
+    }
+}
+
+impl<const CHANNELS: usize> Interpolate<CHANNELS> for ZeroOrderHold<CHANNELS> {
+    const PRIMING_FRAMES: u32 = 1;
+
+    fn push(&mut self, frame: [i16; CHANNELS]) {
+        self.frame = frame;
+    }
+
+    fn interpolate(&self, _phase: u32) -> [i16; CHANNELS] {
+        self.frame
+    }
+}
+
+/// Linear interpolation between the two most recent input frames
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Linear<const CHANNELS: usize> {
+    previous: [i16; CHANNELS],
+    current: [i16; CHANNELS],
+}
+
+impl<const CHANNELS: usize> Default for Linear<CHANNELS> {
+    fn default() -> Self {
+        Self {
+            previous: [0; CHANNELS],
+            current: [0; CHANNELS],
+        }
+    }
+}
+
+impl<const CHANNELS: usize> Interpolate<CHANNELS> for Linear<CHANNELS> {
+    const PRIMING_FRAMES: u32 = 2;
+
+    fn push(&mut self, frame: [i16; CHANNELS]) {
+        self.previous = self.current;
+        self.current = frame;
+    }
+
+    fn interpolate(&self, phase: u32) -> [i16; CHANNELS] {
+        let phase = i64::from(phase);
+        core::array::from_fn(|channel| {
+            let previous = i64::from(self.previous[channel]);
+            let current = i64::from(self.current[channel]);
+            // The result is between `previous` and `current`, so it fits
+            (previous + (((current - previous) * phase) >> PHASE_BITS)) as i16
+        })
+    }
+}
+
+#[cfg(feature = "polyphase")]
+const POLYPHASE_PHASE_BITS: u32 = 5;
+#[cfg(feature = "polyphase")]
+const POLYPHASE_PHASES: usize = 1 << POLYPHASE_PHASE_BITS;
+
+// Blackman-windowed sinc coefficients in Q15, for every phase. Every row adds up to 32768.
+#[cfg(feature = "polyphase")]
+static POLYPHASE_COEFFICIENTS_8: [[i32; 8]; POLYPHASE_PHASES] = [
+    [0, 0, 0, 32768, 0, 0, 0, 0],
+    [-21, 165, -754, 32706, 830, -183, 25, 0],
+    [-38, 312, -1432, 32523, 1733, -383, 53, 0],
+    [-52, 440, -2034, 32222, 2707, -600, 85, 0],
+    [-63, 549, -2560, 31803, 3750, -831, 121, -1],
+    [-71, 641, -3011, 31269, 4857, -1076, 161, -2],
+    [-76, 715, -3389, 30625, 6024, -1332, 204, -3],
+    [-78, 773, -3696, 29875, 7246, -1597, 250, -5],
+    [-79, 814, -3935, 29029, 8516, -1869, 299, -7],
+    [-78, 841, -4109, 28087, 9830, -2144, 351, -10],
+    [-75, 854, -4223, 27062, 11178, -2419, 404, -13],
+    [-72, 855, -4278, 25958, 12555, -2691, 458, -17],
+    [-67, 845, -4281, 24782, 13952, -2955, 513, -21],
+    [-62, 825, -4236, 23548, 15360, -3208, 567, -26],
+    [-56, 796, -4146, 22261, 16771, -3446, 620, -32],
+    [-50, 760, -4018, 20930, 18176, -3663, 671, -38],
+    [-44, 718, -3855, 19565, 19565, -3855, 718, -44],
+    [-38, 671, -3663, 18176, 20930, -4018, 760, -50],
+    [-32, 620, -3446, 16771, 22261, -4146, 796, -56],
+    [-26, 567, -3208, 15360, 23548, -4236, 825, -62],
+    [-21, 513, -2955, 13952, 24782, -4281, 845, -67],
+    [-17, 458, -2691, 12555, 25958, -4278, 855, -72],
+    [-13, 404, -2419, 11178, 27062, -4223, 854, -75],
+    [-10, 351, -2144, 9830, 28087, -4109, 841, -78],
+    [-7, 299, -1869, 8516, 29029, -3935, 814, -79],
+    [-5, 250, -1597, 7246, 29875, -3696, 773, -78],
+    [-3, 204, -1332, 6024, 30625, -3389, 715, -76],
+    [-2, 161, -1076, 4857, 31269, -3011, 641, -71],
+    [-1, 121, -831, 3750, 31803, -2560, 549, -63],
+    [0, 85, -600, 2707, 32222, -2034, 440, -52],
+    [0, 53, -383, 1733, 32523, -1432, 312, -38],
+    [0, 25, -183, 830, 32706, -754, 165, -21],
+];
+#[cfg(feature = "polyphase")]
+static POLYPHASE_COEFFICIENTS_16: [[i32; 16]; POLYPHASE_PHASES] = [
+    [0, 0, 0, 0, 0, 0, 0, 32768, 0, 0, 0, 0, 0, 0, 0, 0],
+    [
+        -2, 11, -34, 85, -185, 386, -927, 32715, 994, -405, 193, -89, 36, -12, 2, 0,
+    ],
+    [
+        -4, 21, -66, 164, -359, 751, -1783, 32549, 2053, -826, 394, -182, 75, -24, 5, 0,
+    ],
+    [
+        -5, 30, -95, 238, -522, 1091, -2566, 32278, 3171, -1258, 600, -278, 114, -38, 8, 0,
+    ],
+    [
+        -6, 37, -121, 306, -673, 1405, -3274, 31901, 4345, -1699, 809, -376, 155, -52, 11, 0,
+    ],
+    [
+        -7, 44, -144, 367, -809, 1691, -3906, 31417, 5570, -2145, 1020, -475, 197, -66, 14, 0,
+    ],
+    [
+        -8, 50, -165, 421, -932, 1947, -4461, 30835, 6840, -2591, 1230, -574, 239, -81, 18, 0,
+    ],
+    [
+        -8, 54, -182, 468, -1039, 2172, -4940, 30156, 8148, -3032, 1437, -672, 282, -97, 22, -1,
+    ],
+    [
+        -8, 58, -196, 508, -1131, 2365, -5342, 29383, 9489, -3465, 1639, -768, 323, -112, 26, -1,
+    ],
+    [
+        -8, 61, -208, 540, -1208, 2527, -5668, 28521, 10856, -3883, 1833, -861, 364, -127, 30, -1,
+    ],
+    [
+        -8, 62, -216, 565, -1269, 2656, -5921, 27580, 12241, -4284, 2018, -949, 403, -142, 34, -2,
+    ],
+    [
+        -8, 63, -222, 584, -1314, 2753, -6102, 26558, 13639, -4661, 2190, -1032, 440, -156, 38, -2,
+    ],
+    [
+        -7, 63, -225, 595, -1344, 2818, -6213, 25465, 15040, -5009, 2347, -1108, 475, -169, 42, -2,
+    ],
+    [
+        -7, 62, -225, 599, -1360, 2853, -6257, 24312, 16438, -5325, 2487, -1176, 506, -182, 46, -3,
+    ],
+    [
+        -6, 61, -223, 597, -1361, 2858, -6238, 23098, 17824, -5602, 2608, -1235, 534, -193, 50, -4,
+    ],
+    [
+        -6, 59, -218, 590, -1348, 2834, -6159, 21835, 19191, -5836, 2707, -1284, 557, -203, 53, -4,
+    ],
+    [
+        -5, 56, -212, 576, -1322, 2783, -6023, 20531, 20531, -6023, 2783, -1322, 576, -212, 56, -5,
+    ],
+    [
+        -4, 53, -203, 557, -1284, 2707, -5836, 19191, 21835, -6159, 2834, -1348, 590, -218, 59, -6,
+    ],
+    [
+        -4, 50, -193, 534, -1235, 2608, -5602, 17824, 23098, -6238, 2858, -1361, 597, -223, 61, -6,
+    ],
+    [
+        -3, 46, -182, 506, -1176, 2487, -5325, 16438, 24312, -6257, 2853, -1360, 599, -225, 62, -7,
+    ],
+    [
+        -2, 42, -169, 475, -1108, 2347, -5009, 15040, 25465, -6213, 2818, -1344, 595, -225, 63, -7,
+    ],
+    [
+        -2, 38, -156, 440, -1032, 2190, -4661, 13639, 26558, -6102, 2753, -1314, 584, -222, 63, -8,
+    ],
+    [
+        -2, 34, -142, 403, -949, 2018, -4284, 12241, 27580, -5921, 2656, -1269, 565, -216, 62, -8,
+    ],
+    [
+        -1, 30, -127, 364, -861, 1833, -3883, 10856, 28521, -5668, 2527, -1208, 540, -208, 61, -8,
+    ],
+    [
+        -1, 26, -112, 323, -768, 1639, -3465, 9489, 29383, -5342, 2365, -1131, 508, -196, 58, -8,
+    ],
+    [
+        -1, 22, -97, 282, -672, 1437, -3032, 8148, 30156, -4940, 2172, -1039, 468, -182, 54, -8,
+    ],
+    [
+        0, 18, -81, 239, -574, 1230, -2591, 6840, 30835, -4461, 1947, -932, 421, -165, 50, -8,
+    ],
+    [
+        0, 14, -66, 197, -475, 1020, -2145, 5570, 31417, -3906, 1691, -809, 367, -144, 44, -7,
+    ],
+    [
+        0, 11, -52, 155, -376, 809, -1699, 4345, 31901, -3274, 1405, -673, 306, -121, 37, -6,
+    ],
+    [
+        0, 8, -38, 114, -278, 600, -1258, 3171, 32278, -2566, 1091, -522, 238, -95, 30, -5,
+    ],
+    [
+        0, 5, -24, 75, -182, 394, -826, 2053, 32549, -1783, 751, -359, 164, -66, 21, -4,
+    ],
+    [
+        0, 2, -12, 36, -89, 193, -405, 994, 32715, -927, 386, -185, 85, -34, 11, -2,
+    ],
+];
+#[cfg(feature = "polyphase")]
+static POLYPHASE_COEFFICIENTS_32: [[i32; 32]; POLYPHASE_PHASES] = [
+    [
+        0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 32768, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+        0, 0, 0,
+    ],
+    [
+        0, 1, -3, 6, -10, 17, -28, 43, -64, 93, -135, 195, -291, 471, -975, 32715, 1040, -488, 299,
+        -200, 138, -96, 66, -44, 29, -18, 11, -6, 3, -1, 0, 0,
+    ],
+    [
+        0, 2, -5, 11, -20, 34, -55, 84, -126, 184, -265, 384, -572, 922, -1881, 32555, 2141, -990,
+        604, -403, 278, -193, 132, -89, 58, -36, 21, -12, 6, -2, 1, 0,
+    ],
+    [
+        -1, 3, -8, 16, -29, 50, -80, 124, -185, 270, -390, 565, -840, 1349, -2716, 32292, 3298,
+        -1499, 911, -607, 418, -290, 199, -134, 87, -55, 32, -18, 9, -4, 1, 0,
+    ],
+    [
+        -1, 4, -10, 20, -38, 65, -104, 161, -241, 352, -509, 736, -1093, 1748, -3478, 31927, 4507,
+        -2013, 1217, -810, 558, -387, 265, -179, 117, -73, 43, -24, 12, -5, 1, 0,
+    ],
+    [
+        -1, 4, -12, 25, -46, 78, -126, 196, -293, 429, -620, 896, -1329, 2118, -4163, 31455, 5762,
+        -2527, 1520, -1009, 695, -482, 331, -223, 146, -91, 54, -30, 15, -6, 2, 0,
+    ],
+    [
+        -1, 5, -13, 28, -53, 91, -147, 228, -341, 500, -722, 1044, -1546, 2455, -4772, 30886, 7058,
+        -3035, 1817, -1204, 828, -574, 395, -266, 174, -109, 65, -36, 18, -7, 2, 0,
+    ],
+    [
+        -1, 5, -15, 32, -59, 102, -165, 257, -385, 564, -815, 1178, -1742, 2758, -5303, 30227,
+        8388, -3533, 2103, -1392, 957, -663, 456, -308, 201, -127, 76, -42, 21, -9, 2, 0,
+    ],
+    [
+        -1, 6, -16, 35, -65, 112, -182, 282, -424, 621, -898, 1298, -1917, 3025, -5756, 29470,
+        9747, -4015, 2378, -1570, 1079, -748, 515, -347, 228, -144, 86, -48, 24, -10, 3, 0,
+    ],
+    [
+        -1, 6, -17, 37, -70, 120, -196, 305, -458, 671, -971, 1402, -2069, 3254, -6131, 28631,
+        11126, -4476, 2636, -1737, 1193, -827, 569, -384, 252, -159, 96, -53, 27, -11, 3, 0,
+    ],
+    [
+        -1, 6, -18, 39, -74, 127, -208, 323, -486, 714, -1032, 1491, -2197, 3445, -6430, 27708,
+        12521, -4912, 2877, -1892, 1298, -900, 620, -419, 275, -174, 105, -59, 30, -13, 4, 0,
+    ],
+    [
+        -1, 6, -18, 40, -77, 133, -217, 338, -510, 748, -1082, 1563, -2301, 3597, -6653, 26711,
+        13922, -5318, 3095, -2031, 1392, -965, 665, -450, 296, -187, 113, -63, 32, -14, 4, 0,
+    ],
+    [
+        -1, 6, -19, 41, -79, 137, -224, 350, -527, 775, -1121, 1619, -2380, 3710, -6802, 25639,
+        15323, -5687, 3290, -2154, 1476, -1023, 705, -477, 314, -199, 120, -68, 35, -15, 4, 0,
+    ],
+    [
+        -1, 6, -19, 42, -80, 140, -229, 358, -540, 793, -1148, 1657, -2434, 3783, -6879, 24505,
+        16717, -6016, 3458, -2259, 1546, -1072, 739, -501, 330, -209, 126, -71, 37, -16, 5, 0,
+    ],
+    [
+        -1, 6, -19, 42, -81, 141, -231, 362, -546, 803, -1163, 1679, -2464, 3819, -6887, 23313,
+        18096, -6299, 3596, -2344, 1603, -1111, 766, -520, 343, -218, 132, -75, 38, -17, 5, 0,
+    ],
+    [
+        -1, 6, -19, 42, -80, 141, -231, 362, -547, 806, -1167, 1684, -2469, 3816, -6830, 22067,
+        19452, -6532, 3704, -2408, 1645, -1140, 787, -534, 352, -224, 136, -77, 40, -18, 6, -1,
+    ],
+    [
+        -1, 6, -18, 41, -79, 139, -229, 359, -543, 800, -1159, 1673, -2450, 3777, -6710, 20778,
+        20778, -6710, 3777, -2450, 1673, -1159, 800, -543, 359, -229, 139, -79, 41, -18, 6, -1,
+    ],
+    [
+        -1, 6, -18, 40, -77, 136, -224, 352, -534, 787, -1140, 1645, -2408, 3704, -6532, 19452,
+        22067, -6830, 3816, -2469, 1684, -1167, 806, -547, 362, -231, 141, -80, 42, -19, 6, -1,
+    ],
+    [
+        0, 5, -17, 38, -75, 132, -218, 343, -520, 766, -1111, 1603, -2344, 3596, -6299, 18096,
+        23313, -6887, 3819, -2464, 1679, -1163, 803, -546, 362, -231, 141, -81, 42, -19, 6, -1,
+    ],
+    [
+        0, 5, -16, 37, -71, 126, -209, 330, -501, 739, -1072, 1546, -2259, 3458, -6016, 16717,
+        24505, -6879, 3783, -2434, 1657, -1148, 793, -540, 358, -229, 140, -80, 42, -19, 6, -1,
+    ],
+    [
+        0, 4, -15, 35, -68, 120, -199, 314, -477, 705, -1023, 1476, -2154, 3290, -5687, 15323,
+        25639, -6802, 3710, -2380, 1619, -1121, 775, -527, 350, -224, 137, -79, 41, -19, 6, -1,
+    ],
+    [
+        0, 4, -14, 32, -63, 113, -187, 296, -450, 665, -965, 1392, -2031, 3095, -5318, 13922,
+        26711, -6653, 3597, -2301, 1563, -1082, 748, -510, 338, -217, 133, -77, 40, -18, 6, -1,
+    ],
+    [
+        0, 4, -13, 30, -59, 105, -174, 275, -419, 620, -900, 1298, -1892, 2877, -4912, 12521,
+        27708, -6430, 3445, -2197, 1491, -1032, 714, -486, 323, -208, 127, -74, 39, -18, 6, -1,
+    ],
+    [
+        0, 3, -11, 27, -53, 96, -159, 252, -384, 569, -827, 1193, -1737, 2636, -4476, 11126, 28631,
+        -6131, 3254, -2069, 1402, -971, 671, -458, 305, -196, 120, -70, 37, -17, 6, -1,
+    ],
+    [
+        0, 3, -10, 24, -48, 86, -144, 228, -347, 515, -748, 1079, -1570, 2378, -4015, 9747, 29470,
+        -5756, 3025, -1917, 1298, -898, 621, -424, 282, -182, 112, -65, 35, -16, 6, -1,
+    ],
+    [
+        0, 2, -9, 21, -42, 76, -127, 201, -308, 456, -663, 957, -1392, 2103, -3533, 8388, 30227,
+        -5303, 2758, -1742, 1178, -815, 564, -385, 257, -165, 102, -59, 32, -15, 5, -1,
+    ],
+    [
+        0, 2, -7, 18, -36, 65, -109, 174, -266, 395, -574, 828, -1204, 1817, -3035, 7058, 30886,
+        -4772, 2455, -1546, 1044, -722, 500, -341, 228, -147, 91, -53, 28, -13, 5, -1,
+    ],
+    [
+        0, 2, -6, 15, -30, 54, -91, 146, -223, 331, -482, 695, -1009, 1520, -2527, 5762, 31455,
+        -4163, 2118, -1329, 896, -620, 429, -293, 196, -126, 78, -46, 25, -12, 4, -1,
+    ],
+    [
+        0, 1, -5, 12, -24, 43, -73, 117, -179, 265, -387, 558, -810, 1217, -2013, 4507, 31927,
+        -3478, 1748, -1093, 736, -509, 352, -241, 161, -104, 65, -38, 20, -10, 4, -1,
+    ],
+    [
+        0, 1, -4, 9, -18, 32, -55, 87, -134, 199, -290, 418, -607, 911, -1499, 3298, 32292, -2716,
+        1349, -840, 565, -390, 270, -185, 124, -80, 50, -29, 16, -8, 3, -1,
+    ],
+    [
+        0, 1, -2, 6, -12, 21, -36, 58, -89, 132, -193, 278, -403, 604, -990, 2141, 32555, -1881,
+        922, -572, 384, -265, 184, -126, 84, -55, 34, -20, 11, -5, 2, 0,
+    ],
+    [
+        0, 0, -1, 3, -6, 11, -18, 29, -44, 66, -96, 138, -200, 299, -488, 1040, 32715, -975, 471,
+        -291, 195, -135, 93, -64, 43, -28, 17, -10, 6, -3, 1, 0,
+    ],
+];
+
+/// Interpolation with a windowed sinc filter with `TAPS` taps, which can be 8, 16 or 32. More taps
+/// sound better but cost more. The output is delayed by `TAPS / 2 - 1` frames more than with
+/// [`Linear`]. Requires the `polyphase` feature.
+///
+/// The filter doesn't remove frequencies above the output Nyquist frequency, so when lowering the
+/// sample rate by a lot, there can be some aliasing.
+#[cfg(feature = "polyphase")]
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Polyphase<const CHANNELS: usize, const TAPS: usize = 8> {
+    /// The oldest frame is first
+    history: [[i16; CHANNELS]; TAPS],
+}
+
+#[cfg(feature = "polyphase")]
+impl<const CHANNELS: usize, const TAPS: usize> Polyphase<CHANNELS, TAPS> {
+    fn coefficients(phase: usize) -> &'static [i32] {
+        const {
+            assert!(matches!(TAPS, 8 | 16 | 32), "TAPS must be 8, 16 or 32");
+        }
+        match TAPS {
+            8 => &POLYPHASE_COEFFICIENTS_8[phase],
+            16 => &POLYPHASE_COEFFICIENTS_16[phase],
+            _ => &POLYPHASE_COEFFICIENTS_32[phase],
+        }
+    }
+}
+
+#[cfg(feature = "polyphase")]
+impl<const CHANNELS: usize, const TAPS: usize> Default for Polyphase<CHANNELS, TAPS> {
+    fn default() -> Self {
+        Self {
+            history: [[0; CHANNELS]; TAPS],
+        }
+    }
+}
+
+#[cfg(feature = "polyphase")]
+impl<const CHANNELS: usize, const TAPS: usize> Interpolate<CHANNELS> for Polyphase<CHANNELS, TAPS> {
+    const PRIMING_FRAMES: u32 = TAPS as u32 / 2 + 1;
+
+    fn push(&mut self, frame: [i16; CHANNELS]) {
+        self.history.copy_within(1.., 0);
+        self.history[TAPS - 1] = frame;
+    }
+
+    fn interpolate(&self, phase: u32) -> [i16; CHANNELS] {
+        let coefficients = Self::coefficients(
+            usize::try_from(phase >> (PHASE_BITS - POLYPHASE_PHASE_BITS)).unwrap(),
+        );
+        core::array::from_fn(|channel| {
+            let sum = self
+                .history
+                .iter()
+                .zip(coefficients)
+                .map(|(frame, &coefficient)| i64::from(frame[channel]) * i64::from(coefficient))
+                .sum::<i64>();
+            (sum >> 15).clamp(i16::MIN.into(), i16::MAX.into()) as i16
+        })
+    }
+}
+
+/// How much a [`Resampler`] used and produced
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Resampled {
+    pub input_frames: usize,
+    pub output_frames: usize,
+}
+
+/// Changes the sample rate of a stream of frames. The position between input frames is kept
+/// between calls to [`Resampler::process`], so the input can be split up any way.
+///
+/// The position is tracked as an exact fraction, so the output never drifts from the input, even
+/// with rates like 44.1 kHz to 48 kHz.
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Resampler<I, const CHANNELS: usize> {
+    interpolate: I,
+    input_rate: u32,
+    output_rate: u32,
+    /// The position of the next output frame, in units of `1 / output_rate` input frames. Whenever
+    /// it is at least `output_rate`, an input frame is needed.
+    phase: u64,
+}
+
+/// The resampler that is usually good enough for 44.1 kHz files on a 48 kHz output
+pub type LinearResampler<const CHANNELS: usize> = Resampler<Linear<CHANNELS>, CHANNELS>;
+
+impl<I: Interpolate<CHANNELS>, const CHANNELS: usize> Resampler<I, CHANNELS> {
+    /// Returns [`None`] if a rate is 0
+    pub fn new(input_rate: u32, output_rate: u32) -> Option<Self> {
+        if input_rate == 0 || output_rate == 0 {
+            return None;
+        }
+        Some(Self {
+            interpolate: Default::default(),
+            input_rate,
+            output_rate,
+            phase: u64::from(I::PRIMING_FRAMES) * u64::from(output_rate),
+        })
+    }
+
+    /// Resamples from the sample rate of the WAVE. Returns [`None`] if a rate is 0 or the number
+    /// of channels is not `CHANNELS`.
+    pub fn for_meta_data(meta_data: &WavMetaData, output_rate: u32) -> Option<Self> {
+        if usize::from(meta_data.fmt.n_channels.get()) != CHANNELS {
+            return None;
+        }
+        Self::new(meta_data.fmt.n_samples_per_sec.get(), output_rate)
+    }
+
+    /// Resamples as many frames as possible, stopping when the output is full or the input is used
+    /// up
+    pub fn process(
+        &mut self,
+        input: &[[i16; CHANNELS]],
+        output: &mut [[i16; CHANNELS]],
+    ) -> Resampled {
+        let output_rate = u64::from(self.output_rate);
+        let mut input_frames = 0;
+        let mut output_frames = 0;
+        while output_frames < output.len() {
+            if self.phase >= output_rate {
+                let Some(&frame) = input.get(input_frames) else {
+                    break;
+                };
+                self.interpolate.push(frame);
+                self.phase -= output_rate;
+                input_frames += 1;
+            } else {
+                // Fits in `PHASE_BITS` bits because `phase` is less than `output_rate`
+                let phase = ((self.phase << PHASE_BITS) / output_rate) as u32;
+                output[output_frames] = self.interpolate.interpolate(phase);
+                self.phase += u64::from(self.input_rate);
+                output_frames += 1;
+            }
+        }
+        Resampled {
+            input_frames,
+            output_frames,
+        }
+    }
+}
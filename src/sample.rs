@@ -0,0 +1,195 @@
+//! A trait for writing code that works with any sample type.
+//!
+//! Integer samples are scaled by their bit depth, so converting an `i16` to an `i32` shifts it
+//! left by 16 bits. Float samples are from `-1.0` to `1.0`.
+
+/// A signed 24-bit sample
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct I24(i32);
+
+impl I24 {
+    pub const MIN: Self = Self(-(1 << 23));
+    pub const MAX: Self = Self((1 << 23) - 1);
+
+    /// Returns [`None`] if the value doesn't fit in 24 bits
+    pub const fn new(value: i32) -> Option<Self> {
+        if value >= Self::MIN.0 && value <= Self::MAX.0 {
+            Some(Self(value))
+        } else {
+            None
+        }
+    }
+
+    pub const fn get(self) -> i32 {
+        self.0
+    }
+
+    pub const fn from_le_bytes(bytes: [u8; 3]) -> Self {
+        Self(i32::from_le_bytes([0, bytes[0], bytes[1], bytes[2]]) >> 8)
+    }
+
+    pub const fn to_le_bytes(self) -> [u8; 3] {
+        let bytes = self.0.to_le_bytes();
+        [bytes[0], bytes[1], bytes[2]]
+    }
+}
+
+pub trait Sample: Copy + PartialOrd + Default {
+    const MIN: Self;
+    const MAX: Self;
+    /// Silence
+    const EQUILIBRIUM: Self;
+    /// The len of the sample in a WAVE
+    const BYTES: usize;
+
+    /// Lossless for every integer sample type. Floats are clamped.
+    fn to_i32(self) -> i32;
+
+    /// Drops the bits that don't fit
+    fn from_i32(sample: i32) -> Self;
+
+    fn to_f32(self) -> f32 {
+        self.to_i32() as f32 / 2147483648.0
+    }
+
+    /// Integer samples are clamped to `-1.0..=1.0`, and NaN becomes silence
+    fn from_f32(sample: f32) -> Self {
+        // `as` saturates
+        Self::from_i32((sample * 2147483648.0) as i32)
+    }
+
+    /// Reads the sample from the first [`Sample::BYTES`] bytes, which are little-endian
+    fn read_le(bytes: &[u8]) -> Self;
+
+    /// Writes the sample into the first [`Sample::BYTES`] bytes as little-endian
+    fn write_le(self, bytes: &mut [u8]);
+
+    /// Converts to another sample type, dropping bits that don't fit
+    fn convert<T: Sample>(self) -> T {
+        T::from_i32(self.to_i32())
+    }
+
+    /// Multiplies by `gain`. Integer samples saturate at [`Sample::MIN`] and [`Sample::MAX`].
+    fn scale(self, gain: f32) -> Self {
+        Self::from_f32(self.to_f32() * gain)
+    }
+}
+
+macro_rules! impl_int_sample {
+    ($sample:ty) => {
+        impl Sample for $sample {
+            const MIN: Self = <$sample>::MIN;
+            const MAX: Self = <$sample>::MAX;
+            const EQUILIBRIUM: Self = 0;
+            const BYTES: usize = size_of::<$sample>();
+
+            fn to_i32(self) -> i32 {
+                i32::from(self) << (i32::BITS - <$sample>::BITS)
+            }
+
+            fn from_i32(sample: i32) -> Self {
+                (sample >> (i32::BITS - <$sample>::BITS)) as $sample
+            }
+
+            fn read_le(bytes: &[u8]) -> Self {
+                <$sample>::from_le_bytes(bytes[..Self::BYTES].try_into().unwrap())
+            }
+
+            fn write_le(self, bytes: &mut [u8]) {
+                bytes[..Self::BYTES].copy_from_slice(&self.to_le_bytes());
+            }
+        }
+    };
+}
+
+impl_int_sample!(i8);
+impl_int_sample!(i16);
+impl_int_sample!(i32);
+
+/// 8-bit samples in a WAVE are unsigned
+impl Sample for u8 {
+    const MIN: Self = u8::MIN;
+    const MAX: Self = u8::MAX;
+    const EQUILIBRIUM: Self = 0x80;
+    const BYTES: usize = 1;
+
+    fn to_i32(self) -> i32 {
+        i32::from(self as i8 ^ i8::MIN) << 24
+    }
+
+    fn from_i32(sample: i32) -> Self {
+        ((sample >> 24) as i8 ^ i8::MIN) as u8
+    }
+
+    fn read_le(bytes: &[u8]) -> Self {
+        bytes[0]
+    }
+
+    fn write_le(self, bytes: &mut [u8]) {
+        bytes[0] = self;
+    }
+}
+
+impl Sample for I24 {
+    const MIN: Self = I24::MIN;
+    const MAX: Self = I24::MAX;
+    const EQUILIBRIUM: Self = Self(0);
+    const BYTES: usize = 3;
+
+    fn to_i32(self) -> i32 {
+        self.0 << 8
+    }
+
+    fn from_i32(sample: i32) -> Self {
+        Self(sample >> 8)
+    }
+
+    fn read_le(bytes: &[u8]) -> Self {
+        Self::from_le_bytes(bytes[..Self::BYTES].try_into().unwrap())
+    }
+
+    fn write_le(self, bytes: &mut [u8]) {
+        bytes[..Self::BYTES].copy_from_slice(&self.to_le_bytes());
+    }
+}
+
+impl Sample for f32 {
+    const MIN: Self = -1.0;
+    const MAX: Self = 1.0;
+    const EQUILIBRIUM: Self = 0.0;
+    const BYTES: usize = 4;
+
+    fn to_i32(self) -> i32 {
+        // `as` saturates
+        (self * 2147483648.0) as i32
+    }
+
+    fn from_i32(sample: i32) -> Self {
+        sample as f32 / 2147483648.0
+    }
+
+    fn to_f32(self) -> f32 {
+        self
+    }
+
+    fn from_f32(sample: f32) -> Self {
+        sample
+    }
+
+    fn read_le(bytes: &[u8]) -> Self {
+        f32::from_le_bytes(bytes[..Self::BYTES].try_into().unwrap())
+    }
+
+    fn write_le(self, bytes: &mut [u8]) {
+        bytes[..Self::BYTES].copy_from_slice(&self.to_le_bytes());
+    }
+
+    fn convert<T: Sample>(self) -> T {
+        T::from_f32(self)
+    }
+
+    fn scale(self, gain: f32) -> Self {
+        self * gain
+    }
+}
@@ -0,0 +1,320 @@
+//! Finding chunks without parsing them.
+//!
+//! Many tools put chunks such as `LIST` or `id3 ` after the `data` chunk. The [`Parser`](crate::Parser)
+//! stops at the `data` chunk so playback can start as soon as possible. If you want the chunks
+//! after it, use [`Chunks::after_data`], which continues from the offsets in the [`WavMetaData`]
+//! without reading anything again. [`Chunks::all`] goes through every chunk.
+//!
+//! To read the data of some of the chunks, use [`ScanChunks`].
+//!
+//! A malformed file can have a huge number of tiny chunks. Use [`ScanLimits`] to stop going
+//! through them with [`Error::ScanLimitExceeded`].
+
+use pure_riff::{Id, RiffChunkHeader, SUB_CHUNKS_OFFSET};
+use zerocopy::FromBytes;
+
+use crate::{Error, ReadInstruction, WavMetaData, arithmetic::checked_advance, check_read};
+
+const HEADER_LEN: u32 = size_of::<RiffChunkHeader>() as u32;
+
+/// A chunk that was found
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ChunkInfo {
+    pub chunk_id: Id,
+    /// The position of the chunk header
+    pub position: u64,
+    /// The len of the data of the chunk, not including the header or the pad byte
+    pub len: u32,
+}
+
+impl ChunkInfo {
+    pub const fn id(&self) -> Id {
+        self.chunk_id
+    }
+
+    /// The position of the data of the chunk in the file
+    pub const fn data_position(&self) -> u64 {
+        self.position.saturating_add(HEADER_LEN as u64)
+    }
+
+    /// The len of the whole chunk, including the header and the pad byte
+    pub const fn size(&self) -> u64 {
+        HEADER_LEN as u64 + self.len as u64 + self.len as u64 % 2
+    }
+
+    /// The position right after the chunk, where the next chunk starts
+    pub const fn end(&self) -> u64 {
+        self.position.saturating_add(self.size())
+    }
+}
+
+/// Limits on how far chunks are gone through. [`None`] means no limit.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub struct ScanLimits {
+    /// The most chunks that are gone through. For the [`Parser`](crate::Parser), only skipped
+    /// chunks count.
+    pub max_chunks: Option<u32>,
+    /// The most bytes that are gone through, from where going through the chunks started
+    pub max_bytes: Option<u64>,
+}
+
+impl ScanLimits {
+    /// Returns an error if `chunks` chunks and `bytes` bytes are more than the limits. `position`
+    /// is the position of the next chunk.
+    pub(crate) fn check(&self, chunks: u32, bytes: u64, position: u64) -> Result<(), Error> {
+        if self
+            .max_chunks
+            .is_some_and(|max_chunks| chunks > max_chunks)
+            || self.max_bytes.is_some_and(|max_bytes| bytes > max_bytes)
+        {
+            return Err(Error::ScanLimitExceeded { position });
+        }
+        Ok(())
+    }
+}
+
+/// Goes through chunks, one chunk at a time. Only chunk headers are read. Read the data of the
+/// chunks you want with [`ChunkInfo::data_position`].
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Chunks {
+    position: u64,
+    /// Where the `RIFF` chunk ends
+    end: u64,
+    /// Where going through the chunks started
+    start: u64,
+    /// The number of chunks gone through
+    chunks: u32,
+    limits: ScanLimits,
+}
+
+impl Chunks {
+    pub const MAX_BUFFER_LEN: usize = HEADER_LEN as usize;
+
+    /// The chunks after the `data` chunk
+    pub fn after_data(meta_data: &WavMetaData) -> Self {
+        Self::new(
+            checked_advance(meta_data.data_position, meta_data.data_len.into(), true)
+                .unwrap_or(u64::MAX),
+            meta_data,
+        )
+    }
+
+    /// Every chunk in the `RIFF` chunk, including `fmt ` and `data`
+    pub fn all(meta_data: &WavMetaData) -> Self {
+        Self::new(SUB_CHUNKS_OFFSET.into(), meta_data)
+    }
+
+    /// The chunks from `position` up to `end`, without a [`WavMetaData`]. For example, to go
+    /// through a file that doesn't parse, use [`SUB_CHUNKS_OFFSET`] and the len of the file.
+    pub fn between(position: u64, end: u64) -> Self {
+        Self {
+            position,
+            end,
+            start: position,
+            chunks: 0,
+            limits: Default::default(),
+        }
+    }
+
+    fn new(position: u64, meta_data: &WavMetaData) -> Self {
+        Self::between(
+            position,
+            SUB_CHUNKS_OFFSET
+                .saturating_add(meta_data.sub_chunks_len)
+                .into(),
+        )
+    }
+
+    /// Stop with [`Error::ScanLimitExceeded`] instead of going past the limits
+    pub fn limits(mut self, limits: ScanLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// [`None`] if there are no more chunks
+    pub fn read_instruction(&self) -> Option<ReadInstruction> {
+        (self.position.saturating_add(HEADER_LEN.into()) <= self.end).then_some(ReadInstruction {
+            position: self.position,
+            len: HEADER_LEN,
+        })
+    }
+
+    /// Returns the chunk and the scanner for the chunks after it
+    pub fn process_data(self, data: &[u8]) -> Result<(ChunkInfo, Self), Error> {
+        check_read(self.read_instruction(), data)?;
+        self.limits.check(
+            self.chunks.saturating_add(1),
+            self.position.saturating_sub(self.start),
+            self.position,
+        )?;
+        let header = RiffChunkHeader::read_from_bytes(data).unwrap();
+        let chunk = ChunkInfo {
+            chunk_id: header.chunk_id,
+            position: self.position,
+            len: header.chunk_len.get(),
+        };
+        let next = Self {
+            position: checked_advance(chunk.data_position(), chunk.len.into(), true).map_err(
+                |_| Error::ChunkSizeOverflow {
+                    chunk_id: chunk.chunk_id,
+                    position: chunk.position,
+                },
+            )?,
+            chunks: self.chunks + 1,
+            ..self
+        };
+        Ok((chunk, next))
+    }
+}
+
+/// Goes through chunks like [`Chunks`], and lets you decide for every chunk whether to read its
+/// data or skip it. Use this for chunks that this library doesn't know, such as proprietary ones.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct ScanChunks {
+    stage: ScanStage,
+    read_len: u32,
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+enum ScanStage {
+    Header(Chunks),
+    Payload {
+        chunk: ChunkInfo,
+        /// How much of the data was read
+        offset: u32,
+        /// The len of the data that is in the `RIFF` chunk
+        len: u32,
+        next: Chunks,
+    },
+}
+
+/// A chunk header was found. Decide whether to read the data of the chunk.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct ChunkDecision {
+    chunk: ChunkInfo,
+    next: Chunks,
+    read_len: u32,
+}
+
+pub enum ScanChunksOutput<'a> {
+    Chunk(ChunkInfo, ChunkDecision),
+    /// Part of the data of a chunk that you decided to read
+    Payload {
+        chunk: ChunkInfo,
+        /// The offset of `data` in the data of the chunk
+        offset: u32,
+        data: &'a [u8],
+        next: ScanChunks,
+    },
+}
+
+impl ScanChunks {
+    /// Goes through `chunks`, reading chunk data `read_len` bytes at a time
+    pub fn new(chunks: Chunks, read_len: u32) -> Self {
+        Self {
+            stage: ScanStage::Header(chunks),
+            read_len: read_len.max(1),
+        }
+    }
+
+    /// [`None`] if there are no more chunks
+    pub fn read_instruction(&self) -> Option<ReadInstruction> {
+        match &self.stage {
+            ScanStage::Header(chunks) => chunks.read_instruction(),
+            ScanStage::Payload {
+                chunk, offset, len, ..
+            } => Some(ReadInstruction {
+                position: chunk.data_position().saturating_add((*offset).into()),
+                len: (len - offset).min(self.read_len),
+            }),
+        }
+    }
+
+    /// If the next read is of the data of a chunk
+    pub(crate) fn reading_payload(&self) -> bool {
+        matches!(self.stage, ScanStage::Payload { .. })
+    }
+
+    pub fn process_data(self, data: &[u8]) -> Result<ScanChunksOutput<'_>, Error> {
+        check_read(self.read_instruction(), data)?;
+        match self.stage {
+            ScanStage::Header(chunks) => {
+                let (chunk, next) = chunks.process_data(data)?;
+                Ok(ScanChunksOutput::Chunk(
+                    chunk,
+                    ChunkDecision {
+                        chunk,
+                        next,
+                        read_len: self.read_len,
+                    },
+                ))
+            }
+            ScanStage::Payload {
+                chunk,
+                offset,
+                len,
+                next,
+            } => {
+                let new_offset = offset + u32::try_from(data.len()).unwrap();
+                let stage = if new_offset == len {
+                    ScanStage::Header(next)
+                } else {
+                    ScanStage::Payload {
+                        chunk,
+                        offset: new_offset,
+                        len,
+                        next,
+                    }
+                };
+                Ok(ScanChunksOutput::Payload {
+                    chunk,
+                    offset,
+                    data,
+                    next: Self { stage, ..self },
+                })
+            }
+        }
+    }
+}
+
+impl ChunkDecision {
+    /// Read the data of the chunk. Only the part of the data that is in the `RIFF` chunk is read.
+    pub fn read_payload(self) -> ScanChunks {
+        let len = self
+            .next
+            .end
+            .saturating_sub(self.chunk.data_position())
+            .min(self.chunk.len.into())
+            .try_into()
+            .unwrap();
+        ScanChunks {
+            stage: if len == 0 {
+                ScanStage::Header(self.next)
+            } else {
+                ScanStage::Payload {
+                    chunk: self.chunk,
+                    offset: 0,
+                    len,
+                    next: self.next,
+                }
+            },
+            read_len: self.read_len,
+        }
+    }
+
+    /// Go to the next chunk without reading the data of this chunk
+    pub fn skip(self) -> ScanChunks {
+        ScanChunks {
+            stage: ScanStage::Header(self.next),
+            read_len: self.read_len,
+        }
+    }
+}
@@ -0,0 +1,79 @@
+//! Serializing the little endian fields of chunk structs as plain numbers, and long byte arrays as
+//! bytes. Used with `#[serde(with = "...")]`.
+
+use core::fmt;
+
+use serde::{
+    Deserialize, Deserializer, Serialize, Serializer,
+    de::{Error, Visitor},
+};
+
+macro_rules! little_endian {
+    ($name:ident, $little_endian:ty, $native:ty) => {
+        pub(crate) mod $name {
+            use super::*;
+
+            pub(crate) fn serialize<S: Serializer>(
+                value: &$little_endian,
+                serializer: S,
+            ) -> Result<S::Ok, S::Error> {
+                value.get().serialize(serializer)
+            }
+
+            pub(crate) fn deserialize<'de, D: Deserializer<'de>>(
+                deserializer: D,
+            ) -> Result<$little_endian, D::Error> {
+                <$native>::deserialize(deserializer).map(Into::into)
+            }
+        }
+    };
+}
+
+little_endian!(u16, zerocopy::little_endian::U16, u16);
+little_endian!(u32, zerocopy::little_endian::U32, u32);
+little_endian!(i16, zerocopy::little_endian::I16, i16);
+
+/// For byte arrays that are too long for serde's array impls
+pub(crate) mod bytes {
+    use super::*;
+
+    pub(crate) fn serialize<S: Serializer, const N: usize>(
+        value: &[u8; N],
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.serialize_bytes(value)
+    }
+
+    struct BytesVisitor<const N: usize>;
+
+    impl<'de, const N: usize> Visitor<'de> for BytesVisitor<N> {
+        type Value = [u8; N];
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            write!(formatter, "{N} bytes")
+        }
+
+        fn visit_bytes<E: Error>(self, v: &[u8]) -> Result<Self::Value, E> {
+            v.try_into().map_err(|_| E::invalid_length(v.len(), &self))
+        }
+
+        fn visit_seq<A: serde::de::SeqAccess<'de>>(
+            self,
+            mut seq: A,
+        ) -> Result<Self::Value, A::Error> {
+            let mut value = [0; N];
+            for (i, byte) in value.iter_mut().enumerate() {
+                *byte = seq
+                    .next_element()?
+                    .ok_or_else(|| A::Error::invalid_length(i, &self))?;
+            }
+            Ok(value)
+        }
+    }
+
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>, const N: usize>(
+        deserializer: D,
+    ) -> Result<[u8; N], D::Error> {
+        deserializer.deserialize_bytes(BytesVisitor)
+    }
+}
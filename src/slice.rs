@@ -0,0 +1,306 @@
+//! Parsing a WAVE that is already in memory, such as one included with `include_bytes!`.
+//!
+//! There is nothing to read, so there are no [`ReadInstruction`](crate::ReadInstruction)s. The
+//! functions are `const`, so the metadata of a WAVE in flash can be computed at compile time:
+//! ```ignore
+//! const META_DATA: WavMetaData = match slice::parse(include_bytes!("beep.wav")) {
+//!     Ok(meta_data) => meta_data,
+//!     Err(_) => panic!("invalid WAVE"),
+//! };
+//! ```
+//!
+//! [`chunks`] goes through the chunks and gives you their data as slices.
+
+use pure_riff::{Id, SUB_CHUNKS_OFFSET};
+use zerocopy::little_endian::{U16, U32};
+
+use crate::{
+    Error, FmtData, Warnings, WavMetaData,
+    fact::{FACT_ID, FactData},
+    fmt::{FmtExtension, WAVE_FORMAT_EXTENSIBLE},
+    is_plausible_id,
+    scan::ChunkInfo,
+    stats::Stats,
+};
+
+const HEADER_LEN: usize = 8;
+const FMT_DATA_LEN: usize = size_of::<FmtData>();
+const FMT_EXTENSION_LEN: usize = size_of::<FmtExtension>();
+
+const fn read_u16(bytes: &[u8], position: usize) -> u16 {
+    u16::from_le_bytes([bytes[position], bytes[position + 1]])
+}
+
+const fn read_u32(bytes: &[u8], position: usize) -> u32 {
+    u32::from_le_bytes([
+        bytes[position],
+        bytes[position + 1],
+        bytes[position + 2],
+        bytes[position + 3],
+    ])
+}
+
+const fn read_id(bytes: &[u8], position: usize) -> Id {
+    [
+        bytes[position],
+        bytes[position + 1],
+        bytes[position + 2],
+        bytes[position + 3],
+    ]
+}
+
+const fn id_eq(a: &Id, b: &Id) -> bool {
+    a[0] == b[0] && a[1] == b[1] && a[2] == b[2] && a[3] == b[3]
+}
+
+const fn read_fmt_data(bytes: &[u8], position: usize) -> FmtData {
+    FmtData {
+        format_tag: U16::new(read_u16(bytes, position)),
+        n_channels: U16::new(read_u16(bytes, position + 2)),
+        n_samples_per_sec: U32::new(read_u32(bytes, position + 4)),
+        n_avg_bytes_per_sec: U32::new(read_u32(bytes, position + 8)),
+        n_block_align: U16::new(read_u16(bytes, position + 12)),
+        w_bits_per_sample: U16::new(read_u16(bytes, position + 14)),
+    }
+}
+
+const fn read_fmt_extension(bytes: &[u8], position: usize) -> FmtExtension {
+    let mut sub_format = [0; 16];
+    let mut i = 0;
+    while i < sub_format.len() {
+        sub_format[i] = bytes[position + 8 + i];
+        i += 1;
+    }
+    FmtExtension {
+        cb_size: U16::new(read_u16(bytes, position)),
+        w_valid_bits_per_sample: U16::new(read_u16(bytes, position + 2)),
+        dw_channel_mask: U32::new(read_u32(bytes, position + 4)),
+        sub_format,
+    }
+}
+
+/// Checks the `RIFF` header and returns the len of the sub chunks
+const fn riff_sub_chunks_len(bytes: &[u8]) -> Result<u32, Error> {
+    if bytes.len() < SUB_CHUNKS_OFFSET as usize {
+        return Err(Error::InvalidRiff);
+    }
+    let riff_id = read_id(bytes, 0);
+    if !id_eq(&riff_id, b"RIFF") {
+        return Err(Error::UnexpectedChunkId(riff_id));
+    }
+    let Some(sub_chunks_len) = read_u32(bytes, 4).checked_sub(size_of::<Id>() as u32) else {
+        return Err(Error::InvalidRiff);
+    };
+    let container_id = read_id(bytes, HEADER_LEN);
+    if !id_eq(&container_id, b"WAVE") {
+        return Err(Error::UnexpectedContainerId(container_id));
+    }
+    Ok(sub_chunks_len)
+}
+
+/// The chunk at `position`, or [`None`] if its header doesn't fit in `bytes`. The data of the
+/// chunk might not fit.
+const fn chunk_at(bytes: &[u8], position: usize) -> Option<ChunkInfo> {
+    if position > bytes.len() || bytes.len() - position < HEADER_LEN {
+        return None;
+    }
+    Some(ChunkInfo {
+        chunk_id: read_id(bytes, position),
+        position: position as u64,
+        len: read_u32(bytes, position + 4),
+    })
+}
+
+/// The position of the chunk after `chunk`, including the pad byte
+const fn next_position(chunk: &ChunkInfo) -> Option<usize> {
+    match (chunk.position as usize + HEADER_LEN).checked_add(chunk.len as usize) {
+        Some(end) => end.checked_add(chunk.len as usize % 2),
+        None => None,
+    }
+}
+
+/// Like the [`Parser`](crate::Parser) with the default [`ParseOptions`](crate::ParseOptions),
+/// but chunks must also fit in `bytes`. Nothing is read, so the [`Stats`] only count the chunks
+/// that were skipped.
+pub const fn parse(bytes: &[u8]) -> Result<WavMetaData, Error> {
+    let sub_chunks_len = match riff_sub_chunks_len(bytes) {
+        Ok(sub_chunks_len) => sub_chunks_len,
+        Err(e) => return Err(e),
+    };
+    let riff_end = SUB_CHUNKS_OFFSET as u64 + sub_chunks_len as u64;
+    let mut warnings = Warnings {
+        truncated_extensible_fmt: false,
+        riff_len_too_small: false,
+        missing_pad_byte: false,
+        data_past_riff_end: false,
+    };
+    let mut stats = Stats {
+        requests: 0,
+        bytes: 0,
+        chunks_skipped: 0,
+    };
+    let mut fmt = None;
+    let mut fact_sample_length = None;
+    let mut position = SUB_CHUNKS_OFFSET as usize;
+    let mut after_pad_byte = false;
+    while let Some(mut chunk) = chunk_at(bytes, position) {
+        // Like the Parser, the chunk before is missing its pad byte
+        if after_pad_byte
+            && !is_plausible_id(&chunk.chunk_id)
+            && let Some(earlier) = chunk_at(bytes, position - 1)
+        {
+            warnings.missing_pad_byte = true;
+            position -= 1;
+            chunk = earlier;
+        }
+        let data_position = position + HEADER_LEN;
+        let len = chunk.len as usize;
+        let in_bytes = bytes.len() - data_position >= len;
+        let end = data_position as u64 + chunk.len as u64;
+        match (
+            &fmt,
+            id_eq(&chunk.chunk_id, b"fmt "),
+            id_eq(&chunk.chunk_id, b"data"),
+        ) {
+            (None, true, _) => {
+                if len < FMT_DATA_LEN {
+                    return Err(Error::FmtDataTooSmall(chunk.len));
+                }
+                if !in_bytes {
+                    return Err(Error::ChunkExtendsPastEnd {
+                        chunk_id: chunk.chunk_id,
+                        position: chunk.position,
+                    });
+                }
+                let fmt_data = read_fmt_data(bytes, data_position);
+                let fmt_extension = if fmt_data.format_tag.get() != WAVE_FORMAT_EXTENSIBLE {
+                    None
+                } else if len >= FMT_DATA_LEN + FMT_EXTENSION_LEN
+                    && read_u16(bytes, data_position + FMT_DATA_LEN) as usize
+                        >= FMT_EXTENSION_LEN - size_of::<U16>()
+                {
+                    Some(read_fmt_extension(bytes, data_position + FMT_DATA_LEN))
+                } else {
+                    warnings.truncated_extensible_fmt = true;
+                    None
+                };
+                fmt = Some((fmt_data, fmt_extension));
+            }
+            (Some((fmt_data, fmt_extension)), _, true) => {
+                if !in_bytes {
+                    return Err(Error::ChunkExtendsPastEnd {
+                        chunk_id: chunk.chunk_id,
+                        position: chunk.position,
+                    });
+                }
+                if end > riff_end {
+                    warnings.data_past_riff_end = true;
+                }
+                return Ok(WavMetaData {
+                    fmt: *fmt_data,
+                    fmt_extension: *fmt_extension,
+                    data_position: data_position as u64,
+                    data_len: chunk.len,
+                    sub_chunks_len,
+                    fact_sample_length,
+                    stats,
+                    warnings,
+                });
+            }
+            (Some(_), _, _)
+                if id_eq(&chunk.chunk_id, &FACT_ID)
+                    && len >= size_of::<FactData>()
+                    && in_bytes
+                    && fact_sample_length.is_none() =>
+            {
+                fact_sample_length = Some(read_u32(bytes, data_position));
+            }
+            _ => stats.chunks_skipped = stats.chunks_skipped.saturating_add(1),
+        }
+        if end > riff_end {
+            warnings.riff_len_too_small = true;
+        }
+        after_pad_byte = chunk.len % 2 == 1;
+        position = match next_position(&chunk) {
+            Some(position) => position,
+            None => {
+                return Err(Error::ChunkSizeOverflow {
+                    chunk_id: chunk.chunk_id,
+                    position: chunk.position,
+                });
+            }
+        };
+    }
+    Err(Error::MissingChunk(if fmt.is_none() {
+        *b"fmt "
+    } else {
+        *b"data"
+    }))
+}
+
+/// Finds the first chunk with an id, in the whole `RIFF` chunk. The data of the chunk is
+/// `bytes[chunk.data_position()..][..chunk.len]`, which might not fit in `bytes`.
+pub const fn find_chunk(bytes: &[u8], chunk_id: &Id) -> Result<Option<ChunkInfo>, Error> {
+    if let Err(e) = riff_sub_chunks_len(bytes) {
+        return Err(e);
+    }
+    let mut position = SUB_CHUNKS_OFFSET as usize;
+    while let Some(chunk) = chunk_at(bytes, position) {
+        if id_eq(&chunk.chunk_id, chunk_id) {
+            return Ok(Some(chunk));
+        }
+        position = match next_position(&chunk) {
+            Some(position) => position,
+            None => return Ok(None),
+        };
+    }
+    Ok(None)
+}
+
+/// A chunk in the bytes given to [`chunks`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SliceChunk<'a> {
+    pub info: ChunkInfo,
+    /// The data of the chunk, not including the header or the pad byte
+    pub data: &'a [u8],
+}
+
+/// Goes through every chunk in the `RIFF` chunk. Returned by [`chunks`].
+#[derive(Debug, Clone)]
+pub struct SliceChunks<'a> {
+    bytes: &'a [u8],
+    /// [`None`] after the last chunk or an error
+    position: Option<usize>,
+}
+
+/// Checks the `RIFF` header and goes through the chunks in it
+pub const fn chunks(bytes: &[u8]) -> Result<SliceChunks<'_>, Error> {
+    if let Err(e) = riff_sub_chunks_len(bytes) {
+        return Err(e);
+    }
+    Ok(SliceChunks {
+        bytes,
+        position: Some(SUB_CHUNKS_OFFSET as usize),
+    })
+}
+
+impl<'a> Iterator for SliceChunks<'a> {
+    /// A chunk whose data doesn't fit in the bytes is an error, and is the last item
+    type Item = Result<SliceChunk<'a>, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let info = chunk_at(self.bytes, self.position?)?;
+        let data = usize::try_from(info.data_position())
+            .ok()
+            .and_then(|data_position| self.bytes.get(data_position..))
+            .and_then(|data| data.get(..usize::try_from(info.len).ok()?));
+        self.position = data.and_then(|_| next_position(&info));
+        Some(
+            data.map(|data| SliceChunk { info, data })
+                .ok_or(Error::ChunkExtendsPastEnd {
+                    chunk_id: info.chunk_id,
+                    position: info.position,
+                }),
+        )
+    }
+}
@@ -0,0 +1,113 @@
+//! The `smpl` chunk, which has information for samplers such as loop points.
+
+use pure_riff::Id;
+use zerocopy::{FromBytes, Immutable, IntoBytes, KnownLayout, little_endian::U32};
+
+use crate::writer::WriteChunk;
+
+pub const SMPL_ID: Id = *b"smpl";
+
+/// The fixed-size part of the `smpl` chunk. It is followed by the loops and then the sampler
+/// specific data.
+#[derive(Debug, Clone, Copy, Default, FromBytes, IntoBytes, Immutable, KnownLayout)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[repr(C)]
+pub struct SmplData {
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_le::u32"))]
+    pub manufacturer: U32,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_le::u32"))]
+    pub product: U32,
+    /// The duration of one sample in nanoseconds
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_le::u32"))]
+    pub sample_period: U32,
+    /// The MIDI note that plays the samples at their original pitch
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_le::u32"))]
+    pub midi_unity_note: U32,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_le::u32"))]
+    pub midi_pitch_fraction: U32,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_le::u32"))]
+    pub smpte_format: U32,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_le::u32"))]
+    pub smpte_offset: U32,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_le::u32"))]
+    pub sample_loops_len: U32,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_le::u32"))]
+    pub sampler_data_len: U32,
+}
+
+pub const LOOP_TYPE_FORWARD: u32 = 0;
+pub const LOOP_TYPE_ALTERNATING: u32 = 1;
+pub const LOOP_TYPE_BACKWARD: u32 = 2;
+
+#[derive(Debug, Clone, Copy, FromBytes, IntoBytes, Immutable, KnownLayout)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[repr(C)]
+pub struct SampleLoop {
+    /// The id of a [`CuePoint`](crate::cue::CuePoint)
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_le::u32"))]
+    pub cue_point_id: U32,
+    /// One of the `LOOP_TYPE_*` constants
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_le::u32"))]
+    pub loop_type: U32,
+    /// The first frame of the loop
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_le::u32"))]
+    pub start: U32,
+    /// The last frame of the loop, which is played
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_le::u32"))]
+    pub end: U32,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_le::u32"))]
+    pub fraction: U32,
+    /// 0 means infinitely
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_le::u32"))]
+    pub play_count: U32,
+}
+
+impl SampleLoop {
+    pub fn forward(cue_point_id: u32, start: u32, end: u32, play_count: u32) -> Self {
+        Self {
+            cue_point_id: cue_point_id.into(),
+            loop_type: LOOP_TYPE_FORWARD.into(),
+            start: start.into(),
+            end: end.into(),
+            fraction: 0.into(),
+            play_count: play_count.into(),
+        }
+    }
+}
+
+/// A `smpl` chunk that can be written with
+/// [`Writer::write_chunk`](crate::writer::Writer::write_chunk). The `sample_loops_len` and
+/// `sampler_data_len` fields of `data` are set from `loops` and `sampler_data`.
+#[derive(Debug, Clone, Copy)]
+pub struct Smpl<'a> {
+    pub data: SmplData,
+    pub loops: &'a [SampleLoop],
+    pub sampler_data: &'a [u8],
+}
+
+impl WriteChunk for Smpl<'_> {
+    fn chunk_id(&self) -> Id {
+        SMPL_ID
+    }
+
+    fn data_len(&self) -> u32 {
+        (size_of::<SmplData>() + size_of_val(self.loops) + self.sampler_data.len())
+            .try_into()
+            .unwrap_or(u32::MAX)
+    }
+
+    fn write_data(&self, buffer: &mut [u8]) {
+        let (data, rest) = buffer.split_at_mut(size_of::<SmplData>());
+        data.copy_from_slice(
+            SmplData {
+                sample_loops_len: u32::try_from(self.loops.len()).unwrap().into(),
+                sampler_data_len: u32::try_from(self.sampler_data.len()).unwrap().into(),
+                ..self.data
+            }
+            .as_bytes(),
+        );
+        let (loops, sampler_data) = rest.split_at_mut(size_of_val(self.loops));
+        loops.copy_from_slice(self.loops.as_bytes());
+        sampler_data.copy_from_slice(self.sampler_data);
+    }
+}
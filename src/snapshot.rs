@@ -0,0 +1,69 @@
+//! Saving the state of a state machine, so it can continue after a power cycle.
+//!
+//! Devices that deep sleep between operations can lose their RAM. Instead of parsing the file
+//! again from the start, save a snapshot (for example in RTC memory or flash) before sleeping and
+//! restore the state machine from it after waking up.
+//!
+//! Snapshots are plain bytes with no padding. Use [`IntoBytes::as_bytes`] to save one and
+//! [`FromBytes::read_from_bytes`] to load it. Restoring returns [`None`] if the bytes aren't a
+//! valid snapshot, for example because they were saved by a different version of this library.
+//!
+//! - [`Parser::snapshot`](crate::Parser::snapshot) and [`Parser::restore`](crate::Parser::restore)
+//! - [`StreamData::snapshot`](crate::playback::StreamData::snapshot) and
+//!   [`StreamData::restore`](crate::playback::StreamData::restore)
+
+use zerocopy::{
+    FromBytes, Immutable, IntoBytes, KnownLayout,
+    little_endian::{U32, U64},
+};
+
+use crate::{FmtData, fmt::FmtExtension};
+
+/// Changes every time the layout or meaning of a snapshot changes
+pub(crate) const VERSION: u8 = 4;
+
+/// The state of a [`Parser`](crate::Parser)
+#[derive(Debug, Clone, Copy, FromBytes, IntoBytes, Immutable, KnownLayout)]
+#[repr(C)]
+pub struct ParserSnapshot {
+    pub(crate) version: u8,
+    pub(crate) stage: u8,
+    pub(crate) strictness: u8,
+    /// Bit 0 is `after_pad_byte`, bit 1 is set if there is a [`FmtExtension`], bit 2 is set if
+    /// there is a `fact_position`, bit 3 is set if there is a `fact_sample_length`, bit 4 is
+    /// `validate_fmt`, and bits 5, 6 and 7 are set if there is a `max_chunks`, a `max_bytes` and
+    /// a `file_len`
+    pub(crate) flags: u8,
+    /// One bit for every field of [`Warnings`](crate::Warnings)
+    pub(crate) warnings: u8,
+    pub(crate) reserved: [u8; 3],
+    pub(crate) sub_chunks_len: U32,
+    pub(crate) position_in_sub_chunks: U32,
+    pub(crate) extension_position: U32,
+    pub(crate) extension_len: U32,
+    pub(crate) fact_position: U32,
+    pub(crate) fact_sample_length: U32,
+    pub(crate) fmt_data: FmtData,
+    pub(crate) fmt_extension: FmtExtension,
+    pub(crate) requests: U32,
+    pub(crate) chunks_skipped: U32,
+    pub(crate) bytes: U64,
+    pub(crate) max_chunks: U32,
+    pub(crate) max_bytes: U64,
+    pub(crate) file_len: U64,
+}
+
+/// The state of a [`StreamData`](crate::playback::StreamData)
+#[derive(Debug, Clone, Copy, FromBytes, IntoBytes, Immutable, KnownLayout)]
+#[repr(C)]
+pub struct StreamDataSnapshot {
+    pub(crate) version: u8,
+    pub(crate) reserved: [u8; 3],
+    pub(crate) data_len: U32,
+    pub(crate) block_align: U32,
+    pub(crate) n_samples_per_sec: U32,
+    pub(crate) offset: U32,
+    pub(crate) read_len: U32,
+    pub(crate) data_position: U64,
+    pub(crate) frames_played: U64,
+}
@@ -0,0 +1,103 @@
+//! Counters for storage traffic and buffer fill levels.
+
+/// Counts how much a state machine had to read or write, so you can see how much storage traffic
+/// it causes and choose buffer sizes based on real numbers.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub struct Stats {
+    /// The number of reads or writes
+    pub requests: u32,
+    /// The total len of all reads or writes
+    pub bytes: u64,
+    /// The number of chunks that were skipped without reading their data
+    pub chunks_skipped: u32,
+}
+
+impl Stats {
+    pub(crate) fn add_request(&mut self, len: usize) {
+        self.requests = self.requests.saturating_add(1);
+        self.bytes = self
+            .bytes
+            .saturating_add(len.try_into().unwrap_or(u64::MAX));
+    }
+
+    pub(crate) fn add_chunk_skipped(&mut self) {
+        self.chunks_skipped = self.chunks_skipped.saturating_add(1);
+    }
+}
+
+/// Remembers how full a playback buffer was for the last `N` buffers, so you can read bigger chunks
+/// ahead of time when the storage has latency spikes.
+///
+/// Call [`FillStats::record`] every time a buffer is handed to the output, with the number of bytes
+/// that were already read ahead at that moment.
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct FillStats<const N: usize> {
+    headrooms: [u32; N],
+    /// The index that the next headroom is written to
+    next: usize,
+    len: usize,
+    underruns: u32,
+}
+
+impl<const N: usize> Default for FillStats<N> {
+    fn default() -> Self {
+        Self {
+            headrooms: [0; N],
+            next: 0,
+            len: 0,
+            underruns: 0,
+        }
+    }
+}
+
+impl<const N: usize> FillStats<N> {
+    /// Records a headroom of 0 as an underrun
+    pub fn record(&mut self, headroom: u32) {
+        if headroom == 0 {
+            self.underruns = self.underruns.saturating_add(1);
+        }
+        if N == 0 {
+            return;
+        }
+        self.headrooms[self.next] = headroom;
+        self.next = (self.next + 1) % N;
+        self.len = (self.len + 1).min(N);
+    }
+
+    /// The smallest headroom in the last `N` buffers, or [`None`] if nothing was recorded
+    pub fn min_headroom(&self) -> Option<u32> {
+        self.headrooms[..self.len].iter().copied().min()
+    }
+
+    /// The average headroom in the last `N` buffers, or [`None`] if nothing was recorded
+    pub fn average_headroom(&self) -> Option<u32> {
+        let sum = self.headrooms[..self.len]
+            .iter()
+            .map(|&headroom| u64::from(headroom))
+            .sum::<u64>();
+        let average = sum.checked_div(self.len.try_into().unwrap())?;
+        Some(average.try_into().unwrap())
+    }
+
+    /// The number of buffers in the window, at most `N`
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The number of times the headroom was 0 since this was created or reset
+    pub fn underruns(&self) -> u32 {
+        self.underruns
+    }
+
+    pub fn reset(&mut self) {
+        *self = Default::default();
+    }
+}
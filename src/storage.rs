@@ -0,0 +1,352 @@
+//! An optional, simpler way of using this library.
+//!
+//! If you don't need control over when and how reads happen, implement [`Storage`] (or
+//! [`AsyncStorage`]) for whatever stores your WAVE and use [`WavReader`]. It drives the
+//! [`Parser`] for you.
+
+use core::future::Future;
+#[cfg(feature = "std")]
+use std::io::{self, Read, Seek, SeekFrom};
+
+use crate::{
+    Error, Located, ParseOptions, Parser, ProcessDataOutput, ReadInstruction, WavMetaData,
+    transform::Transform,
+};
+
+/// Something that a WAVE can be read from
+pub trait Storage {
+    type Error;
+
+    /// Fill `buffer` with the bytes starting at `position`
+    fn read(&mut self, position: u64, buffer: &mut [u8]) -> Result<(), Self::Error>;
+}
+
+/// Like [`Storage`], but async
+pub trait AsyncStorage {
+    type Error;
+
+    /// Fill `buffer` with the bytes starting at `position`
+    fn read(
+        &mut self,
+        position: u64,
+        buffer: &mut [u8],
+    ) -> impl Future<Output = Result<(), Self::Error>>;
+}
+
+impl<T: Storage + ?Sized> Storage for &mut T {
+    type Error = T::Error;
+
+    fn read(&mut self, position: u64, buffer: &mut [u8]) -> Result<(), Self::Error> {
+        T::read(self, position, buffer)
+    }
+}
+
+impl<T: AsyncStorage + ?Sized> AsyncStorage for &mut T {
+    type Error = T::Error;
+
+    fn read(
+        &mut self,
+        position: u64,
+        buffer: &mut [u8],
+    ) -> impl Future<Output = Result<(), Self::Error>> {
+        T::read(self, position, buffer)
+    }
+}
+
+/// The requested bytes are not in the slice
+#[derive(Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct OutOfBounds;
+
+/// For WAVEs that are completely in memory
+impl Storage for &[u8] {
+    type Error = OutOfBounds;
+
+    fn read(&mut self, position: u64, buffer: &mut [u8]) -> Result<(), Self::Error> {
+        let start = usize::try_from(position).map_err(|_| OutOfBounds)?;
+        buffer.copy_from_slice(
+            self.get(start..)
+                .and_then(|data| data.get(..buffer.len()))
+                .ok_or(OutOfBounds)?,
+        );
+        Ok(())
+    }
+}
+
+impl core::fmt::Display for OutOfBounds {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "the requested bytes are out of bounds")
+    }
+}
+
+impl core::error::Error for OutOfBounds {}
+
+/// For anything that can be read and seeked, like a [`File`](std::fs::File). Requires the `std`
+/// feature.
+#[cfg(feature = "std")]
+#[derive(Debug)]
+pub struct IoStorage<T>(pub T);
+
+#[cfg(feature = "std")]
+impl<T: Read + Seek> Storage for IoStorage<T> {
+    type Error = io::Error;
+
+    fn read(&mut self, position: u64, buffer: &mut [u8]) -> Result<(), Self::Error> {
+        self.0.seek(SeekFrom::Start(position))?;
+        self.0.read_exact(buffer)
+    }
+}
+
+/// For readers that implement the `embedded-io` traits, or the `embedded-io-async` traits.
+/// Requires the `embedded-io` or `embedded-io-async` feature.
+#[cfg(any(feature = "embedded-io", feature = "embedded-io-async"))]
+#[derive(Debug)]
+pub struct EmbeddedIoStorage<T>(pub T);
+
+#[cfg(feature = "embedded-io")]
+impl<T: embedded_io::Read + embedded_io::Seek> Storage for EmbeddedIoStorage<T> {
+    type Error = embedded_io::ReadExactError<T::Error>;
+
+    fn read(&mut self, position: u64, buffer: &mut [u8]) -> Result<(), Self::Error> {
+        self.0
+            .seek(embedded_io::SeekFrom::Start(position))
+            .map_err(embedded_io::ReadExactError::Other)?;
+        self.0.read_exact(buffer)
+    }
+}
+
+#[cfg(feature = "embedded-io-async")]
+impl<T: embedded_io_async::Read + embedded_io_async::Seek> AsyncStorage for EmbeddedIoStorage<T> {
+    type Error = embedded_io_async::ReadExactError<T::Error>;
+
+    async fn read(&mut self, position: u64, buffer: &mut [u8]) -> Result<(), Self::Error> {
+        self.0
+            .seek(embedded_io_async::SeekFrom::Start(position))
+            .await
+            .map_err(embedded_io_async::ReadExactError::Other)?;
+        self.0.read_exact(buffer).await
+    }
+}
+
+/// For files on an SD card or other block device. Requires the `embedded-sdmmc` feature.
+#[cfg(feature = "embedded-sdmmc")]
+impl<D, T, const MAX_DIRS: usize, const MAX_FILES: usize, const MAX_VOLUMES: usize> Storage
+    for embedded_sdmmc::File<'_, D, T, MAX_DIRS, MAX_FILES, MAX_VOLUMES>
+where
+    D: embedded_sdmmc::BlockDevice,
+    T: embedded_sdmmc::TimeSource,
+{
+    type Error = embedded_sdmmc::Error<D::Error>;
+
+    fn read(&mut self, position: u64, mut buffer: &mut [u8]) -> Result<(), Self::Error> {
+        self.seek_from_start(
+            position
+                .try_into()
+                .map_err(|_| embedded_sdmmc::Error::InvalidOffset)?,
+        )?;
+        // A read can stop at the end of a cluster
+        while !buffer.is_empty() {
+            let len = embedded_sdmmc::File::read(self, buffer)?;
+            if len == 0 {
+                return Err(embedded_sdmmc::Error::EndOfFile);
+            }
+            buffer = &mut buffer[len..];
+        }
+        Ok(())
+    }
+}
+
+/// For files in a FAT file system. Requires the `fatfs` feature.
+#[cfg(feature = "fatfs")]
+impl<T: fatfs::ReadWriteSeek> Storage for fatfs::File<'_, T> {
+    type Error = io::Error;
+
+    fn read(&mut self, position: u64, buffer: &mut [u8]) -> Result<(), Self::Error> {
+        IoStorage(self).read(position, buffer)
+    }
+}
+
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ReadError<E> {
+    Storage(E),
+    Parse(Located<Error>),
+}
+
+impl<E: core::fmt::Display> core::fmt::Display for ReadError<E> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Storage(error) => write!(f, "storage error: {error}"),
+            Self::Parse(error) => write!(f, "parse error: {error}"),
+        }
+    }
+}
+
+impl<E: core::error::Error + 'static> core::error::Error for ReadError<E> {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        match self {
+            Self::Storage(error) => Some(error),
+            Self::Parse(error) => Some(error),
+        }
+    }
+}
+
+/// Parses a WAVE from a [`Storage`] or [`AsyncStorage`] and then lets you read the samples
+pub struct WavReader<S> {
+    storage: S,
+    meta_data: WavMetaData,
+}
+
+impl<S> WavReader<S> {
+    pub fn meta_data(&self) -> &WavMetaData {
+        &self.meta_data
+    }
+
+    pub fn into_inner(self) -> S {
+        self.storage
+    }
+
+    pub fn into_parts(self) -> (S, WavMetaData) {
+        (self.storage, self.meta_data)
+    }
+
+    /// Returns the position in the file and the len to read, clamped to the end of the samples
+    fn data_read_instruction(&self, offset: u32, buffer_len: usize) -> ReadInstruction {
+        let len = self
+            .meta_data
+            .data_len
+            .saturating_sub(offset)
+            .min(buffer_len.try_into().unwrap_or(u32::MAX));
+        ReadInstruction {
+            position: self.meta_data.data_position.saturating_add(offset.into()),
+            len,
+        }
+    }
+}
+
+impl<S: Storage> WavReader<S> {
+    pub fn open(storage: S) -> Result<Self, ReadError<S::Error>> {
+        Self::open_with_options(storage, Default::default())
+    }
+
+    /// Like [`WavReader::open`], but with [`ParseOptions`] for the [`Parser`]
+    pub fn open_with_options(
+        mut storage: S,
+        options: ParseOptions,
+    ) -> Result<Self, ReadError<S::Error>> {
+        let mut parser = Parser::new(options);
+        let mut buffer = [Default::default(); Parser::MAX_BUFFER_LEN];
+        let meta_data = loop {
+            let ReadInstruction { position, len } = parser.read_instruction();
+            let buffer = &mut buffer[..len.try_into().unwrap()];
+            storage.read(position, buffer).map_err(ReadError::Storage)?;
+            match parser.process_data(buffer).map_err(ReadError::Parse)? {
+                ProcessDataOutput::InProgress(next_parser) => {
+                    parser = next_parser;
+                }
+                ProcessDataOutput::Done(meta_data) => {
+                    break meta_data;
+                }
+            }
+        };
+        Ok(Self { storage, meta_data })
+    }
+
+    /// Reads samples starting at `offset` bytes into the `data` chunk. Returns the number of bytes
+    /// read, which is less than the buffer len at the end of the samples. Chunks after the `data`
+    /// chunk are never read.
+    pub fn read_data(
+        &mut self,
+        offset: u32,
+        buffer: &mut [u8],
+    ) -> Result<usize, ReadError<S::Error>> {
+        let ReadInstruction { position, len } = self.data_read_instruction(offset, buffer.len());
+        let buffer = &mut buffer[..len.try_into().unwrap()];
+        self.storage
+            .read(position, buffer)
+            .map_err(ReadError::Storage)?;
+        Ok(buffer.len())
+    }
+
+    /// Like [`WavReader::read_data`], but the bytes are passed through `transform` before they are
+    /// returned
+    pub fn read_data_with(
+        &mut self,
+        offset: u32,
+        buffer: &mut [u8],
+        mut transform: impl Transform,
+    ) -> Result<usize, ReadError<S::Error>> {
+        let len = self.read_data(offset, buffer)?;
+        transform.transform(offset, &mut buffer[..len]);
+        Ok(len)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<T: Read + Seek> WavReader<IoStorage<T>> {
+    /// Like [`WavReader::open`], for anything that can be read and seeked. Requires the `std`
+    /// feature.
+    pub fn open_io(reader: T) -> Result<Self, ReadError<io::Error>> {
+        Self::open(IoStorage(reader))
+    }
+}
+
+impl<S: AsyncStorage> WavReader<S> {
+    pub async fn open_async(storage: S) -> Result<Self, ReadError<S::Error>> {
+        Self::open_async_with_options(storage, Default::default()).await
+    }
+
+    /// Like [`WavReader::open_with_options`], but async
+    pub async fn open_async_with_options(
+        mut storage: S,
+        options: ParseOptions,
+    ) -> Result<Self, ReadError<S::Error>> {
+        let mut parser = Parser::new(options);
+        let mut buffer = [Default::default(); Parser::MAX_BUFFER_LEN];
+        let meta_data = loop {
+            let ReadInstruction { position, len } = parser.read_instruction();
+            let buffer = &mut buffer[..len.try_into().unwrap()];
+            storage
+                .read(position, buffer)
+                .await
+                .map_err(ReadError::Storage)?;
+            match parser.process_data(buffer).map_err(ReadError::Parse)? {
+                ProcessDataOutput::InProgress(next_parser) => {
+                    parser = next_parser;
+                }
+                ProcessDataOutput::Done(meta_data) => {
+                    break meta_data;
+                }
+            }
+        };
+        Ok(Self { storage, meta_data })
+    }
+
+    /// Like [`WavReader::read_data`], but async
+    pub async fn read_data_async(
+        &mut self,
+        offset: u32,
+        buffer: &mut [u8],
+    ) -> Result<usize, ReadError<S::Error>> {
+        let ReadInstruction { position, len } = self.data_read_instruction(offset, buffer.len());
+        let buffer = &mut buffer[..len.try_into().unwrap()];
+        self.storage
+            .read(position, buffer)
+            .await
+            .map_err(ReadError::Storage)?;
+        Ok(buffer.len())
+    }
+
+    /// Like [`WavReader::read_data_with`], but async
+    pub async fn read_data_async_with(
+        &mut self,
+        offset: u32,
+        buffer: &mut [u8],
+        mut transform: impl Transform,
+    ) -> Result<usize, ReadError<S::Error>> {
+        let len = self.read_data_async(offset, buffer).await?;
+        transform.transform(offset, &mut buffer[..len]);
+        Ok(len)
+    }
+}
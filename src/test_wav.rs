@@ -0,0 +1,450 @@
+//! Generating WAVE files in memory, for testing the parser and your own drivers.
+//!
+//! A [`TestWav`] describes a file: its format, how many frames it has, extra chunks, quirks that
+//! real files have, and an optional [`Corruption`]. [`TestWav::write`] puts the file in a buffer
+//! that is at least [`TestWav::len`] bytes.
+//!
+//! The samples are not audio. Byte `i` of the `data` chunk is [`data_byte`]`(i)`, so you can
+//! check that you read the right part of the samples.
+
+use crate::{
+    FmtData,
+    fact::FACT_ID,
+    fmt::Fmt,
+    writer::{WriteChunk, WriteError, write_chunk_header},
+};
+use pure_riff::Id;
+
+const HEADER_LEN: usize = 8;
+
+/// The byte at `offset` in the `data` chunk of every [`TestWav`]
+pub const fn data_byte(offset: u64) -> u8 {
+    (offset % 251) as u8
+}
+
+/// A chunk to add to a [`TestWav`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExtraChunk<'a> {
+    pub chunk_id: Id,
+    pub data: &'a [u8],
+    /// Put the chunk after the `data` chunk instead of before it
+    pub after_data: bool,
+}
+
+/// Things that real files do which aren't quite right, but which the parser handles
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Quirks {
+    /// Leave out the pad byte after chunks with an odd len
+    pub omit_pad_bytes: bool,
+    /// Subtract this from the len in the `RIFF` header
+    pub riff_len_short_by: u32,
+    /// Add a `fact` chunk with the number of frames before the `data` chunk
+    pub fact: bool,
+}
+
+/// Ways to make a [`TestWav`] invalid
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Corruption {
+    /// Use `RIFX` instead of `RIFF`
+    RiffId,
+    /// Use `AVI ` instead of `WAVE`
+    ContainerId,
+    /// Leave out the `fmt ` chunk
+    MissingFmt,
+    /// Make the `fmt ` chunk 2 bytes shorter than [`FmtData`]
+    FmtTooSmall,
+    /// Leave out the `data` chunk
+    MissingData,
+    /// Set the len of the first extra chunk before the `data` chunk to [`u32::MAX`]
+    ChunkLenOverflow,
+    /// Only write this many bytes, as if the file was cut off
+    Truncate(usize),
+}
+
+/// A WAVE file to generate
+#[derive(Debug, Clone, Copy)]
+pub struct TestWav<'a> {
+    fmt: Fmt,
+    frames: u32,
+    extra_chunks: &'a [ExtraChunk<'a>],
+    quirks: Quirks,
+    corruption: Option<Corruption>,
+}
+
+impl<'a> TestWav<'a> {
+    /// A file with a `fmt ` chunk and a `data` chunk with `frames` frames. Build the [`Fmt`] with
+    /// [`FmtDataBuilder`](crate::fmt::FmtDataBuilder).
+    pub fn new(fmt: Fmt, frames: u32) -> Self {
+        Self {
+            fmt,
+            frames,
+            extra_chunks: &[],
+            quirks: Quirks::default(),
+            corruption: None,
+        }
+    }
+
+    pub fn extra_chunks(mut self, extra_chunks: &'a [ExtraChunk<'a>]) -> Self {
+        self.extra_chunks = extra_chunks;
+        self
+    }
+
+    pub fn quirks(mut self, quirks: Quirks) -> Self {
+        self.quirks = quirks;
+        self
+    }
+
+    pub fn corruption(mut self, corruption: Corruption) -> Self {
+        self.corruption = Some(corruption);
+        self
+    }
+
+    /// The len of the data of the `data` chunk
+    pub fn data_len(&self) -> u32 {
+        self.frames
+            .saturating_mul(self.fmt.base.n_block_align.get().into())
+    }
+
+    /// The position of the data of the `data` chunk, or [`None`] if there is no `data` chunk
+    pub fn data_position(&self) -> Option<u64> {
+        let mut data_position = None;
+        let mut position = 0;
+        self.emit(&mut |chunk_id, bytes| {
+            if chunk_id == Some(*b"data") {
+                data_position = Some(position + HEADER_LEN as u64);
+            }
+            position += bytes.len() as u64;
+        });
+        data_position
+    }
+
+    /// The len of the whole file
+    pub fn len(&self) -> usize {
+        let mut len = 0;
+        self.emit(&mut |_, bytes| len += bytes.len());
+        match self.corruption {
+            Some(Corruption::Truncate(truncated_len)) => len.min(truncated_len),
+            _ => len,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Writes the file at the start of `buffer` and returns its len
+    pub fn write(&self, buffer: &mut [u8]) -> Result<usize, WriteError> {
+        let len = self.len();
+        let buffer = buffer
+            .get_mut(..len)
+            .ok_or(WriteError::BufferTooSmall(len))?;
+        let mut position = 0;
+        self.emit(&mut |_, bytes| {
+            let bytes = &bytes[..bytes.len().min(len.saturating_sub(position))];
+            buffer[position..][..bytes.len()].copy_from_slice(bytes);
+            position += bytes.len();
+        });
+        Ok(len)
+    }
+
+    #[cfg(feature = "std")]
+    pub fn to_vec(&self) -> std::vec::Vec<u8> {
+        let mut buffer = std::vec![0; self.len()];
+        self.write(&mut buffer).unwrap();
+        buffer
+    }
+
+    /// Gives the file to `out` in pieces, with the id of the chunk for the header of each chunk
+    fn emit(&self, out: &mut impl FnMut(Option<Id>, &[u8])) {
+        let corruption = self.corruption;
+        let chunk_size = |len: u32| HEADER_LEN as u64 + u64::from(len) + u64::from(self.pad(len));
+
+        let fmt_len = match corruption {
+            Some(Corruption::FmtTooSmall) => size_of::<FmtData>() as u32 - 2,
+            _ => self.fmt.data_len(),
+        };
+        let has_fmt = corruption != Some(Corruption::MissingFmt);
+        let has_data = corruption != Some(Corruption::MissingData);
+        let extra_chunks = |after_data| {
+            self.extra_chunks
+                .iter()
+                .filter(move |chunk| chunk.after_data == after_data)
+        };
+        let sub_chunks_len = size_of::<Id>() as u64
+            + if has_fmt { chunk_size(fmt_len) } else { 0 }
+            + if self.quirks.fact { chunk_size(4) } else { 0 }
+            + extra_chunks(false)
+                .chain(extra_chunks(true))
+                .map(|chunk| chunk_size(chunk.data.len() as u32))
+                .sum::<u64>()
+            + if has_data {
+                chunk_size(self.data_len())
+            } else {
+                0
+            };
+
+        let mut header = [0; HEADER_LEN];
+        write_chunk_header(
+            &mut header,
+            match corruption {
+                Some(Corruption::RiffId) => *b"RIFX",
+                _ => *b"RIFF",
+            },
+            u32::try_from(sub_chunks_len)
+                .unwrap_or(u32::MAX)
+                .saturating_sub(self.quirks.riff_len_short_by),
+        );
+        out(Some(*b"RIFF"), &header);
+        out(
+            None,
+            match corruption {
+                Some(Corruption::ContainerId) => b"AVI ",
+                _ => b"WAVE",
+            },
+        );
+
+        if has_fmt {
+            let mut fmt = [0; 40];
+            let fmt = &mut fmt[..self.fmt.data_len() as usize];
+            self.fmt.write_data(fmt);
+            self.emit_chunk(out, *b"fmt ", fmt_len, &fmt[..fmt_len as usize]);
+        }
+        if self.quirks.fact {
+            self.emit_chunk(out, FACT_ID, 4, &self.frames.to_le_bytes());
+        }
+        for (i, chunk) in extra_chunks(false).enumerate() {
+            let len = match corruption {
+                Some(Corruption::ChunkLenOverflow) if i == 0 => u32::MAX,
+                _ => chunk.data.len() as u32,
+            };
+            self.emit_chunk(out, chunk.chunk_id, len, chunk.data);
+        }
+        if has_data {
+            let data_len = self.data_len();
+            write_chunk_header(&mut header, *b"data", data_len);
+            out(Some(*b"data"), &header);
+            let mut buffer = [0; 64];
+            let mut offset = 0;
+            while offset < data_len {
+                let len = (data_len - offset).min(buffer.len() as u32);
+                for (i, byte) in buffer[..len as usize].iter_mut().enumerate() {
+                    *byte = data_byte(u64::from(offset) + i as u64);
+                }
+                out(None, &buffer[..len as usize]);
+                offset += len;
+            }
+            if self.pad(data_len) {
+                out(None, &[0]);
+            }
+        }
+        for chunk in extra_chunks(true) {
+            self.emit_chunk(out, chunk.chunk_id, chunk.data.len() as u32, chunk.data);
+        }
+    }
+
+    fn pad(&self, len: u32) -> bool {
+        !self.quirks.omit_pad_bytes && len % 2 == 1
+    }
+
+    fn emit_chunk(
+        &self,
+        out: &mut impl FnMut(Option<Id>, &[u8]),
+        chunk_id: Id,
+        len: u32,
+        data: &[u8],
+    ) {
+        let mut header = [0; HEADER_LEN];
+        write_chunk_header(&mut header, chunk_id, len);
+        out(Some(chunk_id), &header);
+        out(None, data);
+        if self.pad(data.len() as u32) {
+            out(None, &[0]);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        Error, ErrorLocation, ParseOptions, Parser, Warnings, WavMetaData, drive,
+        fmt::{Codec, FmtDataBuilder},
+        slice,
+    };
+
+    const EXTRA_CHUNKS: &[ExtraChunk] = &[
+        // Odd len, so it has a pad byte
+        ExtraChunk {
+            chunk_id: *b"LIST",
+            data: b"INFOx",
+            after_data: false,
+        },
+        ExtraChunk {
+            chunk_id: *b"JUNK",
+            data: b"abc",
+            after_data: true,
+        },
+    ];
+
+    /// 11 frames of 8 bit mono, so the `data` chunk has an odd len too
+    fn test_wav() -> TestWav<'static> {
+        let fmt = FmtDataBuilder::new(Codec::Pcm, 8000, 1, 8).build().unwrap();
+        TestWav::new(fmt, 11).extra_chunks(EXTRA_CHUNKS)
+    }
+
+    /// Parses the file with the [`Parser`] and with [`slice::parse`]
+    fn parse(wav: TestWav) -> [Result<WavMetaData, Error>; 2] {
+        let mut buffer = [0; 256];
+        let len = wav.write(&mut buffer).unwrap();
+        let file = &buffer[..len];
+        let parser = Parser::new(ParseOptions {
+            file_len: Some(len as u64),
+            ..Default::default()
+        });
+        let parsed = drive::drive(parser, |read| {
+            // The parser can ask for more than is left when it looks for the `fmt ` chunk
+            let start = (read.position as usize).min(len);
+            &file[start..(start + read.len as usize).min(len)]
+        })
+        .map_err(|located| located.error);
+        [parsed, slice::parse(file)]
+    }
+
+    fn check_data(wav: TestWav, meta_data: &WavMetaData) {
+        assert_eq!(Some(meta_data.data_position), wav.data_position());
+        assert_eq!(meta_data.data_len, wav.data_len());
+        let mut buffer = [0; 256];
+        let len = wav.write(&mut buffer).unwrap();
+        let data = &buffer[..len][meta_data.data_position as usize..][..wav.data_len() as usize];
+        for (offset, byte) in data.iter().enumerate() {
+            assert_eq!(*byte, data_byte(offset as u64));
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let wav = test_wav();
+        for meta_data in parse(wav) {
+            let meta_data = meta_data.unwrap();
+            check_data(wav, &meta_data);
+            assert_eq!(meta_data.warnings, Warnings::default());
+            assert_eq!(meta_data.fact_sample_length, None);
+        }
+    }
+
+    #[test]
+    fn omit_pad_bytes() {
+        let wav = test_wav().quirks(Quirks {
+            omit_pad_bytes: true,
+            ..Default::default()
+        });
+        for meta_data in parse(wav) {
+            let meta_data = meta_data.unwrap();
+            check_data(wav, &meta_data);
+            assert!(meta_data.warnings.missing_pad_byte);
+        }
+    }
+
+    #[test]
+    fn riff_len_short() {
+        // Past the `JUNK` chunk and into the `data` chunk
+        let wav = test_wav().quirks(Quirks {
+            riff_len_short_by: 20,
+            ..Default::default()
+        });
+        for meta_data in parse(wav) {
+            let meta_data = meta_data.unwrap();
+            check_data(wav, &meta_data);
+            assert!(meta_data.warnings.data_past_riff_end);
+        }
+    }
+
+    #[test]
+    fn fact() {
+        let wav = test_wav().quirks(Quirks {
+            fact: true,
+            ..Default::default()
+        });
+        for meta_data in parse(wav) {
+            let meta_data = meta_data.unwrap();
+            check_data(wav, &meta_data);
+            assert_eq!(meta_data.fact_sample_length, Some(11));
+        }
+    }
+
+    #[test]
+    fn riff_id() {
+        for result in parse(test_wav().corruption(Corruption::RiffId)) {
+            assert!(matches!(result, Err(Error::UnexpectedChunkId(id)) if &id == b"RIFX"));
+        }
+    }
+
+    #[test]
+    fn container_id() {
+        for result in parse(test_wav().corruption(Corruption::ContainerId)) {
+            assert!(matches!(result, Err(Error::UnexpectedContainerId(id)) if &id == b"AVI "));
+        }
+    }
+
+    #[test]
+    fn error_location() {
+        let mut buffer = [0; 256];
+        let len = test_wav()
+            .corruption(Corruption::ContainerId)
+            .write(&mut buffer)
+            .unwrap();
+        let parser = Parser::default();
+        let read = parser.read_instruction();
+        let error = parser
+            .process_data(&buffer[..len][..read.len as usize])
+            .err()
+            .unwrap();
+        assert_eq!(
+            error.location,
+            ErrorLocation {
+                position: 0,
+                chunk_id: Some(*b"RIFF")
+            }
+        );
+    }
+
+    #[test]
+    fn missing_fmt() {
+        let [parsed, sliced] = parse(test_wav().corruption(Corruption::MissingFmt));
+        assert!(parsed.is_err());
+        assert!(matches!(sliced, Err(Error::MissingChunk(id)) if &id == b"fmt "));
+    }
+
+    #[test]
+    fn fmt_too_small() {
+        for result in parse(test_wav().corruption(Corruption::FmtTooSmall)) {
+            assert!(matches!(result, Err(Error::FmtDataTooSmall(14))));
+        }
+    }
+
+    #[test]
+    fn missing_data() {
+        let [parsed, sliced] = parse(test_wav().corruption(Corruption::MissingData));
+        assert!(parsed.is_err());
+        assert!(matches!(sliced, Err(Error::MissingChunk(id)) if &id == b"data"));
+    }
+
+    #[test]
+    fn chunk_len_overflow() {
+        let [parsed, sliced] = parse(test_wav().corruption(Corruption::ChunkLenOverflow));
+        assert!(matches!(parsed, Err(Error::FileTruncated { .. })));
+        assert!(sliced.is_err());
+    }
+
+    #[test]
+    fn truncate() {
+        let wav = test_wav();
+        let [parsed, sliced] = parse(wav.corruption(Corruption::Truncate(60)));
+        assert!(matches!(parsed, Err(Error::FileTruncated { .. })));
+        assert!(matches!(
+            sliced,
+            Err(Error::ChunkExtendsPastEnd { chunk_id, position })
+                if &chunk_id == b"data" && Some(position + 8) == wav.data_position()
+        ));
+    }
+}
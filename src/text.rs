@@ -0,0 +1,224 @@
+//! Getting text out of chunks.
+//!
+//! Text in chunks, such as `INFO` values, cue labels and `bext` fields, is bytes that are usually
+//! null-terminated or padded with null bytes. [`until_null`] gets the text from such bytes.
+//!
+//! The text isn't always UTF-8. Files made on Windows often have `INFO` and `DISP` text in
+//! Windows-1252, which looks like mojibake if it is shown as UTF-8. [`chars`] decodes the text with
+//! an [`Encoding`]. If you don't know the encoding, [`Encoding::Detect`] uses UTF-8 if the text is
+//! valid UTF-8, and Windows-1252 if it isn't.
+//!
+//! With the `heapless` feature, [`HeaplessText`] copies the text into a
+//! [`heapless::String`], so that it can be kept without alloc:
+//! ```ignore
+//! let read = HeaplessText::<64>::read_instruction(&tag.value);
+//! let value = HeaplessText::<64>::decode(
+//!     &file[read.position as usize..][..read.len as usize],
+//!     Encoding::Detect,
+//! );
+//! if value.truncated {
+//!     // The value didn't fit in 64 bytes
+//! }
+//! let description = HeaplessText::<256>::from_bytes(&bext.description);
+//! ```
+
+#[cfg(feature = "heapless")]
+use crate::{ReadInstruction, adtl::Text};
+
+/// The bytes before the first null byte
+pub fn until_null(bytes: &[u8]) -> &[u8] {
+    let len = bytes
+        .iter()
+        .position(|&byte| byte == 0)
+        .unwrap_or(bytes.len());
+    &bytes[..len]
+}
+
+/// How text is encoded
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Encoding {
+    Utf8,
+    /// Also called CP1252. A superset of Latin-1 that is common in files from Windows.
+    Windows1252,
+    /// UTF-8 if the text is valid UTF-8, otherwise Windows-1252
+    Detect,
+}
+
+impl Encoding {
+    /// The encoding of a Windows code page, such as the `code_page` of
+    /// [`LabeledTextData`](crate::adtl::LabeledTextData). [`None`] if the code page isn't
+    /// supported.
+    pub fn from_code_page(code_page: u16) -> Option<Self> {
+        match code_page {
+            // 0 means the default, which is usually Windows-1252
+            0 | 1252 => Some(Self::Windows1252),
+            65001 => Some(Self::Utf8),
+            _ => None,
+        }
+    }
+}
+
+/// The chars of Windows-1252 from `0x80` to `0x9F`. The bytes that aren't used are `U+FFFD`.
+const WINDOWS_1252: [char; 32] = [
+    '€', '\u{FFFD}', '‚', 'ƒ', '„', '…', '†', '‡', 'ˆ', '‰', 'Š', '‹', 'Œ', '\u{FFFD}', 'Ž',
+    '\u{FFFD}', '\u{FFFD}', '‘', '’', '“', '”', '•', '–', '—', '˜', '™', 'š', '›', 'œ', '\u{FFFD}',
+    'ž', 'Ÿ',
+];
+
+/// Decodes `bytes` as text. Bytes that can't be decoded become `U+FFFD`.
+///
+/// With [`Encoding::Detect`], UTF-8 text that ends in the middle of a char, for example because
+/// only the start of it was read, is still UTF-8. The cut off char is left out.
+pub fn chars(bytes: &[u8], encoding: Encoding) -> Chars<'_> {
+    let (bytes, windows_1252, cut_off) = match encoding {
+        Encoding::Utf8 => (bytes, false, false),
+        Encoding::Windows1252 => (bytes, true, false),
+        Encoding::Detect => match core::str::from_utf8(bytes) {
+            Ok(_) => (bytes, false, false),
+            Err(e) if e.error_len().is_none() => (&bytes[..e.valid_up_to()], false, true),
+            Err(_) => (bytes, true, false),
+        },
+    };
+    Chars {
+        bytes,
+        windows_1252,
+        replaced: false,
+        cut_off,
+    }
+}
+
+/// The chars of decoded text
+#[derive(Debug, Clone)]
+pub struct Chars<'a> {
+    bytes: &'a [u8],
+    windows_1252: bool,
+    replaced: bool,
+    cut_off: bool,
+}
+
+impl Chars<'_> {
+    /// If any bytes so far couldn't be decoded and were replaced with `U+FFFD`
+    pub fn replaced(&self) -> bool {
+        self.replaced
+    }
+
+    /// If a char at the end was cut off and left out
+    pub fn cut_off(&self) -> bool {
+        self.cut_off
+    }
+
+    fn next_windows_1252(&mut self, byte: u8) -> char {
+        self.bytes = &self.bytes[1..];
+        match byte {
+            0x80..=0x9F => WINDOWS_1252[usize::from(byte - 0x80)],
+            _ => char::from(byte),
+        }
+    }
+
+    fn next_utf8(&mut self) -> char {
+        // A char is at most 4 bytes
+        let start = &self.bytes[..self.bytes.len().min(4)];
+        let (valid, invalid_len) = match core::str::from_utf8(start) {
+            Ok(valid) => (valid, 0),
+            Err(e) => (
+                core::str::from_utf8(&start[..e.valid_up_to()]).unwrap(),
+                e.error_len().unwrap_or(start.len()),
+            ),
+        };
+        let (c, len) = match valid.chars().next() {
+            Some(c) => (c, c.len_utf8()),
+            None => {
+                self.replaced = true;
+                (char::REPLACEMENT_CHARACTER, invalid_len)
+            }
+        };
+        self.bytes = &self.bytes[len..];
+        c
+    }
+}
+
+impl Iterator for Chars<'_> {
+    type Item = char;
+
+    fn next(&mut self) -> Option<char> {
+        let &byte = self.bytes.first()?;
+        Some(if self.windows_1252 {
+            let c = self.next_windows_1252(byte);
+            self.replaced |= c == char::REPLACEMENT_CHARACTER;
+            c
+        } else {
+            self.next_utf8()
+        })
+    }
+}
+
+/// Text copied into a string with a capacity of `N` bytes
+#[cfg(feature = "heapless")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct HeaplessText<const N: usize> {
+    pub text: heapless::String<N>,
+    /// The text didn't fit, so only the start of it is in `text`
+    pub truncated: bool,
+    /// Bytes that weren't UTF-8 were replaced with `U+FFFD`
+    pub replaced: bool,
+}
+
+#[cfg(feature = "heapless")]
+impl<const N: usize> HeaplessText<N> {
+    /// Reads enough of `text` to fill the string and to know if it was truncated
+    pub fn read_instruction(text: &Text) -> ReadInstruction {
+        text.read_instruction(u32::try_from(N).unwrap_or(u32::MAX).saturating_add(1))
+    }
+
+    /// Copies the UTF-8 text before the first null byte. Stops at a char boundary if it doesn't
+    /// fit.
+    pub fn from_bytes(bytes: &[u8]) -> Self {
+        Self::decode(bytes, Encoding::Utf8)
+    }
+
+    /// Like [`HeaplessText::from_bytes`], but decodes the text with `encoding`
+    pub fn decode(bytes: &[u8], encoding: Encoding) -> Self {
+        let mut chars = chars(until_null(bytes), encoding);
+        let mut text = heapless::String::new();
+        let truncated = chars.by_ref().any(|c| text.push(c).is_err()) || chars.cut_off();
+        Self {
+            text,
+            truncated,
+            replaced: chars.replaced(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detect_cut_off_utf8() {
+        let text = "héllo…".as_bytes();
+        let cut_off = &text[..text.len() - 1];
+        let mut chars = chars(cut_off, Encoding::Detect);
+        assert!(chars.by_ref().eq("héllo".chars()));
+        assert!(chars.cut_off());
+        assert!(!chars.replaced());
+    }
+
+    #[cfg(feature = "heapless")]
+    #[test]
+    fn heapless_detect_cut_off() {
+        // The read for 6 bytes is 7 bytes, which ends in the middle of "…"
+        let text = HeaplessText::<6>::decode(&"héllo…".as_bytes()[..7], Encoding::Detect);
+        assert_eq!(text.text, "héllo");
+        assert!(text.truncated);
+        assert!(!text.replaced);
+    }
+
+    #[test]
+    fn detect_windows_1252() {
+        assert!(chars(b"caf\xE9 \x80", Encoding::Detect).eq("café €".chars()));
+    }
+}
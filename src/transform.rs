@@ -0,0 +1,36 @@
+//! Changing the bytes of the `data` chunk after they are read, for example to decrypt them.
+
+/// Changes the bytes of the `data` chunk in place, after they are read and before they are decoded
+pub trait Transform {
+    /// `offset` is the position of `data` in the `data` chunk
+    fn transform(&mut self, offset: u32, data: &mut [u8]);
+}
+
+impl<T: Transform + ?Sized> Transform for &mut T {
+    fn transform(&mut self, offset: u32, data: &mut [u8]) {
+        T::transform(self, offset, data);
+    }
+}
+
+/// Leaves the bytes as they are
+impl Transform for () {
+    fn transform(&mut self, _offset: u32, _data: &mut [u8]) {}
+}
+
+/// XORs every byte with a repeating key. The key lines up with the start of the `data` chunk.
+#[derive(Debug, Clone, Copy)]
+pub struct XorTransform<'a> {
+    pub key: &'a [u8],
+}
+
+impl Transform for XorTransform<'_> {
+    fn transform(&mut self, offset: u32, data: &mut [u8]) {
+        if self.key.is_empty() {
+            return;
+        }
+        let start = usize::try_from(offset).unwrap() % self.key.len();
+        for (byte, key) in data.iter_mut().zip(self.key.iter().cycle().skip(start)) {
+            *byte ^= key;
+        }
+    }
+}
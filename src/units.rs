@@ -0,0 +1,102 @@
+//! Converting between byte offsets in the `data` chunk, sample indexes and time.
+//!
+//! A sample index is the index of a frame, which has one sample for every channel. This is what
+//! the `smpl` and `cue ` chunks use. Times are in nanoseconds.
+
+use crate::FmtData;
+
+const NANOS_PER_SEC: u128 = 1_000_000_000;
+
+/// How to round a value that is between two frames
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Rounding {
+    /// To the frame that it is in
+    #[default]
+    Down,
+    /// To the start of the next frame, unless it is already at the start of a frame
+    Up,
+    /// To whichever frame start is closer, rounding up when it is exactly in the middle
+    Nearest,
+}
+
+impl Rounding {
+    fn divide(self, value: u128, divisor: u128) -> Option<u128> {
+        match self {
+            Self::Down => value.checked_div(divisor),
+            Self::Up => value
+                .checked_add(divisor.checked_sub(1)?)?
+                .checked_div(divisor),
+            Self::Nearest => value.checked_add(divisor / 2)?.checked_div(divisor),
+        }
+    }
+}
+
+/// Converts between byte offsets, sample indexes and time for one format
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Units {
+    block_align: u32,
+    n_samples_per_sec: u32,
+}
+
+impl Units {
+    /// Returns [`None`] if the block align is 0
+    pub fn new(fmt: &FmtData) -> Option<Self> {
+        Self::from_parts(fmt.n_block_align.get().into(), fmt.n_samples_per_sec.get())
+    }
+
+    /// Returns [`None`] if the block align is 0
+    pub fn from_parts(block_align: u32, n_samples_per_sec: u32) -> Option<Self> {
+        (block_align != 0).then_some(Self {
+            block_align,
+            n_samples_per_sec,
+        })
+    }
+
+    /// The byte offset of the start of a frame
+    pub fn sample_to_byte(&self, sample: u64) -> u64 {
+        sample.saturating_mul(self.block_align.into())
+    }
+
+    /// The frame that a byte offset is in, or the next one
+    pub fn byte_to_sample(&self, byte: u64, rounding: Rounding) -> u64 {
+        rounding
+            .divide(byte.into(), self.block_align.into())
+            .map_or(u64::MAX, |sample| u64::try_from(sample).unwrap_or(u64::MAX))
+    }
+
+    /// Moves a byte offset to the start of a frame
+    pub fn snap_byte(&self, byte: u64, rounding: Rounding) -> u64 {
+        self.sample_to_byte(self.byte_to_sample(byte, rounding))
+    }
+
+    /// The frame that is playing at a time. 0 if the sample rate is 0.
+    pub fn time_to_sample(&self, ns: u64, rounding: Rounding) -> u64 {
+        ns_to_frames(ns, self.n_samples_per_sec, rounding)
+    }
+
+    /// The time that a frame starts playing at. 0 if the sample rate is 0.
+    pub fn sample_to_time(&self, sample: u64) -> u64 {
+        frames_to_ns(sample, self.n_samples_per_sec)
+    }
+}
+
+/// The time that `frames` take to play, rounded down. 0 if the sample rate is 0.
+pub fn frames_to_ns(frames: u64, n_samples_per_sec: u32) -> u64 {
+    (u128::from(frames) * NANOS_PER_SEC)
+        .checked_div(n_samples_per_sec.into())
+        .map_or(0, |ns| u64::try_from(ns).unwrap_or(u64::MAX))
+}
+
+/// The number of frames that play in `ns`
+pub fn ns_to_frames(ns: u64, n_samples_per_sec: u32, rounding: Rounding) -> u64 {
+    rounding
+        .divide(
+            u128::from(ns) * u128::from(n_samples_per_sec),
+            NANOS_PER_SEC,
+        )
+        .map_or(u64::MAX, |frames| u64::try_from(frames).unwrap_or(u64::MAX))
+}
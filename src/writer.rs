@@ -0,0 +1,507 @@
+//! Writing WAVE files.
+//!
+//! # Usage
+//! This works like [`Parser`](crate::Parser), but the other way around. Create a [`Writer`] with
+//! [`Writer::new`]. Every method that produces bytes takes a buffer, puts the bytes in it, and
+//! returns a [`WriteInstruction`] telling you where in the file to write them.
+//! 1. Call [`Writer::write_header`]
+//! 2. Optionally call [`Writer::write_chunk`] for things like [`InfoTags`](crate::info::InfoTags)
+//! 3. Call [`Writer::start_data`]
+//! 4. For every buffer of samples, call [`Writer::write_samples`] to get the position to write
+//!    the samples at. [`encode_samples`] turns samples into bytes.
+//! 5. Optionally call [`Writer::write_chunk`] to add chunks after the samples
+//! 6. Call [`Writer::finalize`] and do all the writes, which update the sizes in the headers
+//!
+//! # Files bigger than 4 GiB
+//! Use [`Writer::new_rf64_upgradable`]. See <https://tech.ebu.ch/docs/tech/tech3306v1_1.pdf>.
+
+use pure_riff::{Id, RiffChunkHeader};
+use zerocopy::{
+    FromBytes, Immutable, IntoBytes, KnownLayout,
+    little_endian::{U32, U64},
+};
+
+use crate::{
+    DisplayId, Strictness, arithmetic::checked_advance, fmt::Fmt, is_plausible_id, sample::Sample,
+    stats::Stats,
+};
+
+#[derive(Debug)]
+pub struct WriteInstruction<'a> {
+    pub position: u64,
+    pub data: &'a [u8],
+}
+
+#[derive(Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum WriteError {
+    /// The buffer is too small. Contains the needed len.
+    BufferTooSmall(usize),
+    /// The method can't be called at this point, for example writing the header twice
+    WrongStage,
+    /// The file would be bigger than 4 GiB, or bigger than what can be represented by RF64 if
+    /// the writer was created with [`Writer::new_rf64_upgradable`]
+    TooLarge,
+    /// The chunk id isn't printable ASCII, or it is the id of a chunk that the [`Writer`] writes
+    /// itself. Only returned with [`Strictness::Strict`].
+    InvalidChunkId(Id),
+}
+
+impl core::fmt::Display for WriteError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::BufferTooSmall(len) => write!(f, "the buffer is too small, it needs {len} bytes"),
+            Self::WrongStage => write!(f, "the method can't be called at this point"),
+            Self::TooLarge => write!(f, "the file would be too large"),
+            Self::InvalidChunkId(id) => write!(f, "can't write a chunk with id {}", DisplayId(id)),
+        }
+    }
+}
+
+impl core::error::Error for WriteError {}
+
+/// Anything that can be written as a chunk
+pub trait WriteChunk {
+    fn chunk_id(&self) -> Id;
+    /// The len of the data, not including the header and padding byte
+    fn data_len(&self) -> u32;
+    /// `buffer` will be exactly [`WriteChunk::data_len`] bytes
+    fn write_data(&self, buffer: &mut [u8]);
+}
+
+impl WriteChunk for Fmt {
+    fn chunk_id(&self) -> Id {
+        *b"fmt "
+    }
+
+    fn data_len(&self) -> u32 {
+        self.chunk_len()
+    }
+
+    fn write_data(&self, buffer: &mut [u8]) {
+        let (base, extension) = buffer.split_at_mut(size_of_val(&self.base));
+        base.copy_from_slice(self.base.as_bytes());
+        if let Some(fmt_extension) = &self.extension {
+            extension.copy_from_slice(fmt_extension.as_bytes());
+        }
+    }
+}
+
+const WRITE_INSTRUCTIONS_CAPACITY: usize = 8;
+
+/// Multiple writes, which can be done in any order
+#[derive(Debug, Clone)]
+pub struct WriteInstructions<'a> {
+    buffer: &'a [u8],
+    writes: [(u64, usize, usize); WRITE_INSTRUCTIONS_CAPACITY],
+    len: usize,
+    index: usize,
+}
+
+impl<'a> Iterator for WriteInstructions<'a> {
+    type Item = WriteInstruction<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.index == self.len {
+            return None;
+        }
+        let (position, start, end) = self.writes[self.index];
+        self.index += 1;
+        Some(WriteInstruction {
+            position,
+            data: &self.buffer[start..end],
+        })
+    }
+}
+
+/// Helps put multiple writes in one buffer
+pub(crate) struct WriteInstructionsBuilder<'a> {
+    buffer: &'a mut [u8],
+    writes: [(u64, usize, usize); WRITE_INSTRUCTIONS_CAPACITY],
+    len: usize,
+    buffer_len: usize,
+}
+
+impl<'a> WriteInstructionsBuilder<'a> {
+    pub(crate) fn new(buffer: &'a mut [u8]) -> Self {
+        Self {
+            buffer,
+            writes: Default::default(),
+            len: 0,
+            buffer_len: 0,
+        }
+    }
+
+    pub(crate) fn push(&mut self, position: u64, data: &[u8]) -> Result<(), WriteError> {
+        self.push_with(position, data.len(), |buffer| buffer.copy_from_slice(data))
+    }
+
+    /// Adds a write of `len` bytes, which are filled by `f`
+    pub(crate) fn push_with(
+        &mut self,
+        position: u64,
+        len: usize,
+        f: impl FnOnce(&mut [u8]),
+    ) -> Result<(), WriteError> {
+        let end = self.buffer_len + len;
+        f(self
+            .buffer
+            .get_mut(self.buffer_len..end)
+            .ok_or(WriteError::BufferTooSmall(end))?);
+        self.writes[self.len] = (position, self.buffer_len, end);
+        self.len += 1;
+        self.buffer_len = end;
+        Ok(())
+    }
+
+    pub(crate) fn build(self) -> WriteInstructions<'a> {
+        WriteInstructions {
+            buffer: self.buffer,
+            writes: self.writes,
+            len: self.len,
+            index: 0,
+        }
+    }
+}
+
+enum WriterStage {
+    Header,
+    Chunks,
+    Data { data_position: u64 },
+    AfterData { data_position: u64 },
+    Done,
+}
+
+pub struct Writer {
+    fmt: Fmt,
+    stage: WriterStage,
+    /// The end of everything written so far
+    position: u64,
+    data_len: u64,
+    rf64_upgradable: bool,
+    strictness: Strictness,
+    stats: Stats,
+}
+
+const HEADER_LEN: usize = size_of::<RiffChunkHeader>();
+
+/// Chunks that the [`Writer`] writes itself
+const RESERVED_IDS: [Id; 4] = [*b"fmt ", *b"data", *b"ds64", *b"RIFF"];
+
+/// The RIFF header and the WAVE id
+const RIFF_HEADER_LEN: usize = HEADER_LEN + size_of::<Id>();
+
+/// The data of a `ds64` chunk without any table entries
+#[derive(Debug, Clone, Copy, FromBytes, IntoBytes, Immutable, KnownLayout)]
+#[repr(C)]
+pub struct Ds64Data {
+    pub riff_size: U64,
+    pub data_size: U64,
+    pub sample_count: U64,
+    pub table_len: U32,
+}
+
+impl Writer {
+    /// The file can be at most 4 GiB
+    pub fn new(fmt: Fmt) -> Self {
+        Self {
+            fmt,
+            stage: WriterStage::Header,
+            position: 0,
+            data_len: 0,
+            rf64_upgradable: false,
+            strictness: Default::default(),
+            stats: Default::default(),
+        }
+    }
+
+    /// Reserves space for a `ds64` chunk with a `JUNK` chunk. If the file ends up bigger than
+    /// 4 GiB, [`Writer::finalize`] replaces the `JUNK` chunk with a `ds64` chunk and changes the
+    /// file to RF64. Otherwise it stays a normal RIFF file.
+    pub fn new_rf64_upgradable(fmt: Fmt) -> Self {
+        Self {
+            rf64_upgradable: true,
+            ..Self::new(fmt)
+        }
+    }
+
+    /// With [`Strictness::Strict`], [`Writer::write_chunk`] only writes chunks that a strict
+    /// [`Parser`](crate::Parser) accepts. The default is [`Strictness::Lenient`].
+    pub fn with_strictness(self, strictness: Strictness) -> Self {
+        Self { strictness, ..self }
+    }
+
+    pub fn fmt(&self) -> &Fmt {
+        &self.fmt
+    }
+
+    /// The len of the samples written so far
+    pub fn data_len(&self) -> u64 {
+        self.data_len
+    }
+
+    /// How much was written so far
+    pub fn stats(&self) -> Stats {
+        self.stats
+    }
+
+    /// [`Writer::write_header`] was called
+    pub fn header_written(&self) -> bool {
+        !matches!(self.stage, WriterStage::Header)
+    }
+
+    /// The len of the reserved `JUNK` chunk, if any
+    fn junk_chunk_len(&self) -> usize {
+        if self.rf64_upgradable {
+            HEADER_LEN + size_of::<Ds64Data>()
+        } else {
+            0
+        }
+    }
+
+    /// Writes the RIFF header and the `fmt ` chunk
+    pub fn write_header<'a>(
+        &mut self,
+        buffer: &'a mut [u8],
+    ) -> Result<WriteInstruction<'a>, WriteError> {
+        let WriterStage::Header = self.stage else {
+            return Err(WriteError::WrongStage);
+        };
+        let junk_chunk_len = self.junk_chunk_len();
+        let len = RIFF_HEADER_LEN + junk_chunk_len + chunk_len(&self.fmt);
+        let buffer = buffer
+            .get_mut(..len)
+            .ok_or(WriteError::BufferTooSmall(len))?;
+        let (riff_header, chunks) = buffer.split_at_mut(RIFF_HEADER_LEN);
+        write_chunk_header(riff_header, *b"RIFF", 0);
+        riff_header[HEADER_LEN..].copy_from_slice(b"WAVE");
+        let (junk_chunk, fmt_chunk) = chunks.split_at_mut(junk_chunk_len);
+        if self.rf64_upgradable {
+            write_chunk_header(
+                junk_chunk,
+                *b"JUNK",
+                size_of::<Ds64Data>().try_into().unwrap(),
+            );
+            junk_chunk[HEADER_LEN..].fill(0);
+        }
+        write_chunk(fmt_chunk, &self.fmt);
+        self.stage = WriterStage::Chunks;
+        self.position = len.try_into().unwrap();
+        self.stats.add_request(len);
+        Ok(WriteInstruction {
+            position: 0,
+            data: buffer,
+        })
+    }
+
+    /// Writes a chunk before or after the samples
+    pub fn write_chunk<'a>(
+        &mut self,
+        chunk: &impl WriteChunk,
+        buffer: &'a mut [u8],
+    ) -> Result<WriteInstruction<'a>, WriteError> {
+        let pad_len = match self.stage {
+            WriterStage::Chunks | WriterStage::AfterData { .. } => 0,
+            WriterStage::Data { .. } => usize::from(self.data_len % 2 == 1),
+            WriterStage::Header | WriterStage::Done => return Err(WriteError::WrongStage),
+        };
+        let chunk_id = chunk.chunk_id();
+        if self.strictness == Strictness::Strict
+            && (!is_plausible_id(&chunk_id) || RESERVED_IDS.contains(&chunk_id))
+        {
+            return Err(WriteError::InvalidChunkId(chunk_id));
+        }
+        let len = pad_len + chunk_len(chunk);
+        let new_position = self.advance(len.try_into().unwrap())?;
+        let buffer = buffer
+            .get_mut(..len)
+            .ok_or(WriteError::BufferTooSmall(len))?;
+        buffer[..pad_len].fill(0);
+        write_chunk(&mut buffer[pad_len..], chunk);
+        if let WriterStage::Data { data_position } = self.stage {
+            self.stage = WriterStage::AfterData { data_position };
+        }
+        let position = self.position;
+        self.position = new_position;
+        self.stats.add_request(len);
+        Ok(WriteInstruction {
+            position,
+            data: buffer,
+        })
+    }
+
+    /// Writes the header of the `data` chunk
+    pub fn start_data<'a>(
+        &mut self,
+        buffer: &'a mut [u8],
+    ) -> Result<WriteInstruction<'a>, WriteError> {
+        let WriterStage::Chunks = self.stage else {
+            return Err(WriteError::WrongStage);
+        };
+        let new_position = self.advance(HEADER_LEN.try_into().unwrap())?;
+        let buffer = buffer
+            .get_mut(..HEADER_LEN)
+            .ok_or(WriteError::BufferTooSmall(HEADER_LEN))?;
+        write_chunk_header(buffer, *b"data", 0);
+        let position = self.position;
+        self.position = new_position;
+        self.stage = WriterStage::Data {
+            data_position: new_position,
+        };
+        self.stats.add_request(HEADER_LEN);
+        Ok(WriteInstruction {
+            position,
+            data: buffer,
+        })
+    }
+
+    /// Returns the position to write the next `len` bytes of samples at
+    pub fn write_samples(&mut self, len: u32) -> Result<u64, WriteError> {
+        let WriterStage::Data { .. } = self.stage else {
+            return Err(WriteError::WrongStage);
+        };
+        let new_position = self.advance(len.into())?;
+        let position = self.position;
+        self.position = new_position;
+        self.data_len += u64::from(len);
+        self.stats.add_request(len.try_into().unwrap());
+        Ok(position)
+    }
+
+    /// Writes the sizes in the headers for everything written so far, so that the file is valid
+    /// even if writing stops unexpectedly. You can keep writing after this.
+    /// A buffer of [`Writer::FINALIZE_BUFFER_LEN`] bytes is enough.
+    pub fn flush<'a>(&mut self, buffer: &'a mut [u8]) -> Result<WriteInstructions<'a>, WriteError> {
+        let data_position = self.data_position()?;
+        let mut instructions = WriteInstructionsBuilder::new(buffer);
+        self.push_size_patches(&mut instructions, data_position)?;
+        Ok(self.build(instructions))
+    }
+
+    /// Writes the sizes in the headers, and the padding byte after the samples if needed.
+    /// A buffer of [`Writer::FINALIZE_BUFFER_LEN`] bytes is enough.
+    pub fn finalize<'a>(
+        &mut self,
+        buffer: &'a mut [u8],
+    ) -> Result<WriteInstructions<'a>, WriteError> {
+        let data_position = self.data_position()?;
+        let mut instructions = WriteInstructionsBuilder::new(buffer);
+        if let WriterStage::Data { .. } = self.stage
+            && self.data_len % 2 == 1
+        {
+            let new_position = self.advance(1)?;
+            instructions.push(self.position, &[0])?;
+            self.position = new_position;
+        }
+        self.push_size_patches(&mut instructions, data_position)?;
+        self.stage = WriterStage::Done;
+        Ok(self.build(instructions))
+    }
+
+    fn data_position(&self) -> Result<u64, WriteError> {
+        match self.stage {
+            WriterStage::Data { data_position } | WriterStage::AfterData { data_position } => {
+                Ok(data_position)
+            }
+            _ => Err(WriteError::WrongStage),
+        }
+    }
+
+    fn push_size_patches(
+        &self,
+        instructions: &mut WriteInstructionsBuilder,
+        data_position: u64,
+    ) -> Result<(), WriteError> {
+        let riff_len = self.position - u64::try_from(HEADER_LEN).unwrap();
+        let data_len_position = data_position - u64::try_from(size_of::<U32>()).unwrap();
+        match (u32::try_from(riff_len), u32::try_from(self.data_len)) {
+            (Ok(riff_len), Ok(data_len)) => {
+                instructions.push(4, &riff_len.to_le_bytes())?;
+                instructions.push(data_len_position, &data_len.to_le_bytes())?;
+            }
+            _ => {
+                // `advance` makes sure that we only get here if the file is RF64 upgradable
+                let ds64_position = u64::try_from(RIFF_HEADER_LEN).unwrap();
+                let mut ds64_chunk = [0; HEADER_LEN + size_of::<Ds64Data>()];
+                write_chunk_header(
+                    &mut ds64_chunk,
+                    *b"ds64",
+                    size_of::<Ds64Data>().try_into().unwrap(),
+                );
+                ds64_chunk[HEADER_LEN..].copy_from_slice(
+                    Ds64Data {
+                        riff_size: riff_len.into(),
+                        data_size: self.data_len.into(),
+                        sample_count: self
+                            .data_len
+                            .checked_div(self.fmt.base.n_block_align.get().into())
+                            .unwrap_or_default()
+                            .into(),
+                        table_len: 0.into(),
+                    }
+                    .as_bytes(),
+                );
+                instructions.push(0, b"RF64")?;
+                instructions.push(4, &u32::MAX.to_le_bytes())?;
+                instructions.push(ds64_position, &ds64_chunk)?;
+                instructions.push(data_len_position, &u32::MAX.to_le_bytes())?;
+            }
+        }
+        Ok(())
+    }
+
+    fn build<'a>(&mut self, instructions: WriteInstructionsBuilder<'a>) -> WriteInstructions<'a> {
+        let instructions = instructions.build();
+        for instruction in instructions.clone() {
+            self.stats.add_request(instruction.data.len());
+        }
+        instructions
+    }
+
+    pub const FINALIZE_BUFFER_LEN: usize =
+        1 + size_of::<Id>() + 2 * size_of::<u32>() + HEADER_LEN + size_of::<Ds64Data>();
+
+    /// Returns the new position after adding `len`
+    fn advance(&self, len: u64) -> Result<u64, WriteError> {
+        let max_position = if self.rf64_upgradable {
+            u64::MAX
+        } else {
+            // The RIFF len doesn't include the RIFF header
+            u64::from(u32::MAX) + u64::try_from(HEADER_LEN).unwrap()
+        };
+        checked_advance(self.position, len, false)
+            .ok()
+            .filter(|&position| position <= max_position)
+            .ok_or(WriteError::TooLarge)
+    }
+}
+
+/// The total len of a chunk, including the header and padding
+pub fn chunk_len(chunk: &impl WriteChunk) -> usize {
+    HEADER_LEN + usize::try_from(chunk.data_len().next_multiple_of(2)).unwrap()
+}
+
+/// Writes the chunk, including the header and padding. `buffer` must be [`chunk_len`] bytes.
+pub fn write_chunk(buffer: &mut [u8], chunk: &impl WriteChunk) {
+    let data_len = chunk.data_len();
+    let (header, data) = buffer.split_at_mut(HEADER_LEN);
+    write_chunk_header(header, chunk.chunk_id(), data_len);
+    let (data, padding) = data.split_at_mut(data_len.try_into().unwrap());
+    chunk.write_data(data);
+    padding.fill(0);
+}
+
+/// Writes samples as little-endian bytes, the way they are stored in the `data` chunk. Returns
+/// the number of bytes, which is limited by the len of `buffer`.
+pub fn encode_samples<S: Sample>(samples: &[S], buffer: &mut [u8]) -> usize {
+    let len = samples.len().min(buffer.len() / S::BYTES);
+    for (sample, bytes) in samples[..len].iter().zip(buffer.chunks_exact_mut(S::BYTES)) {
+        sample.write_le(bytes);
+    }
+    len * S::BYTES
+}
+
+pub(crate) fn write_chunk_header(buffer: &mut [u8], chunk_id: Id, chunk_len: u32) {
+    buffer[..size_of::<Id>()].copy_from_slice(&chunk_id);
+    buffer[size_of::<Id>()..HEADER_LEN].copy_from_slice(&chunk_len.to_le_bytes());
+}
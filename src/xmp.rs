@@ -0,0 +1,145 @@
+//! The `_PMX` chunk, which has XMP metadata.
+//!
+//! Adobe tools put an XMP packet, which is XML, in this chunk. It can be big, so [`XmpReader`]
+//! finds the chunk and reads it a part at a time, for example to send it to a host:
+//! ```ignore
+//! let mut reader = XmpReader::new(Chunks::all(&meta_data), 512);
+//! while let Some(read) = reader.read_instruction() {
+//!     let data = read_file(read);
+//!     reader = match reader.process_data(data)? {
+//!         XmpProcessDataOutput::InProgress(reader) => reader,
+//!         XmpProcessDataOutput::Part { chunk, offset, data, next } => {
+//!             send_to_host(chunk.len, offset, data);
+//!             next
+//!         }
+//!     };
+//! }
+//! ```
+//! To add XMP to a file, write an [`Xmp`] with
+//! [`Writer::write_chunk`](crate::writer::Writer::write_chunk).
+
+use pure_riff::Id;
+
+use crate::{
+    Error, ReadInstruction, check_read,
+    scan::{ChunkInfo, Chunks, ScanChunks, ScanChunksOutput},
+    writer::WriteChunk,
+};
+
+pub const XMP_ID: Id = *b"_PMX";
+
+/// A `_PMX` chunk that can be written
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Xmp<'a> {
+    /// The XMP packet
+    pub packet: &'a [u8],
+}
+
+impl WriteChunk for Xmp<'_> {
+    fn chunk_id(&self) -> Id {
+        XMP_ID
+    }
+
+    fn data_len(&self) -> u32 {
+        self.packet.len().try_into().unwrap_or(u32::MAX)
+    }
+
+    fn write_data(&self, buffer: &mut [u8]) {
+        buffer.copy_from_slice(self.packet);
+    }
+}
+
+#[derive(Debug, Clone)]
+enum XmpStage {
+    Find(ScanChunks),
+    Read(ScanChunks),
+    Done,
+}
+
+/// Finds the first `_PMX` chunk and reads its data
+#[derive(Debug, Clone)]
+pub struct XmpReader {
+    stage: XmpStage,
+    found: bool,
+}
+
+pub enum XmpProcessDataOutput<'a> {
+    InProgress(XmpReader),
+    /// Part of the XMP packet
+    Part {
+        chunk: ChunkInfo,
+        /// The offset of `data` in the XMP packet
+        offset: u32,
+        data: &'a [u8],
+        next: XmpReader,
+    },
+}
+
+impl XmpReader {
+    /// Looks for the chunk in `chunks`, and reads its data `read_len` bytes at a time
+    pub fn new(chunks: Chunks, read_len: u32) -> Self {
+        Self {
+            stage: XmpStage::Find(ScanChunks::new(chunks, read_len)),
+            found: false,
+        }
+    }
+
+    /// If the `_PMX` chunk was found
+    pub fn found(&self) -> bool {
+        self.found
+    }
+
+    /// [`None`] if the whole XMP packet was read, or if there is no `_PMX` chunk
+    pub fn read_instruction(&self) -> Option<ReadInstruction> {
+        match &self.stage {
+            XmpStage::Find(scan) | XmpStage::Read(scan) => scan.read_instruction(),
+            XmpStage::Done => None,
+        }
+    }
+
+    pub fn process_data(self, data: &[u8]) -> Result<XmpProcessDataOutput<'_>, Error> {
+        check_read(self.read_instruction(), data)?;
+        let (XmpStage::Find(scan) | XmpStage::Read(scan)) = self.stage else {
+            return Err(Error::NoPendingRead);
+        };
+        Ok(match scan.process_data(data)? {
+            ScanChunksOutput::Chunk(chunk, decision) => {
+                let found = chunk.chunk_id == XMP_ID;
+                let scan = if found {
+                    decision.read_payload()
+                } else {
+                    decision.skip()
+                };
+                XmpProcessDataOutput::InProgress(Self {
+                    stage: if !found {
+                        XmpStage::Find(scan)
+                    } else if scan.reading_payload() {
+                        XmpStage::Read(scan)
+                    } else {
+                        // The chunk is empty
+                        XmpStage::Done
+                    },
+                    found,
+                })
+            }
+            ScanChunksOutput::Payload {
+                chunk,
+                offset,
+                data,
+                next,
+            } => XmpProcessDataOutput::Part {
+                chunk,
+                offset,
+                data,
+                next: Self {
+                    stage: if next.reading_payload() {
+                        XmpStage::Read(next)
+                    } else {
+                        XmpStage::Done
+                    },
+                    found: true,
+                },
+            },
+        })
+    }
+}